@@ -0,0 +1,183 @@
+//! Known answers for the committed puzzle inputs.
+//!
+//! The manifest is a plain text file with one `day part answer` entry per
+//! line, separated by whitespace. Empty lines and lines starting with `#`
+//! are ignored.
+
+use crate::{Error, Part, Solution};
+use std::{fmt, fs, path::Path};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expected {
+	pub day: u32,
+	pub part: Part,
+	pub value: i128,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+	pub day: u32,
+	pub part: Part,
+	pub expected: Option<i128>,
+	pub actual: Result<i128, String>,
+}
+
+impl fmt::Display for Mismatch {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "day {} {}: expected ", self.day, self.part)?;
+		match self.expected {
+			Some(v) => write!(f, "{}", v)?,
+			None => write!(f, "<missing from manifest>")?,
+		}
+		match &self.actual {
+			Ok(v) => write!(f, ", actual {}", v),
+			Err(e) => write!(f, ", actual error: {}", e),
+		}
+	}
+}
+
+pub fn parse_manifest(s: &str) -> Result<Vec<Expected>, Error> {
+	s.lines()
+		.enumerate()
+		.map(|(n, l)| (n + 1, l.trim()))
+		.filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+		.map(|(n, l)| {
+			let invalid =
+				|| Error::Invalid(format!("manifest line {}: {:?}", n, l));
+			match l.split_whitespace().collect::<Vec<_>>()[..] {
+				[day, part, value] => Ok(Expected {
+					day: day.parse().map_err(|_| invalid())?,
+					part: part.parse().map_err(|_| invalid())?,
+					value: value.parse().map_err(|_| invalid())?,
+				}),
+				_ => Err(invalid()),
+			}
+		})
+		.collect()
+}
+
+pub fn load_manifest(path: &Path) -> Result<Vec<Expected>, Error> {
+	let s = fs::read_to_string(path)
+		.map_err(|e| Error::Io(Some(path.into()), e))?;
+	parse_manifest(&s)
+}
+
+/// Solve `input` with `s` and compare both parts against `expected`.
+pub fn check<S: Solution>(
+	s: &S,
+	input: &[u8],
+	expected: &[Expected],
+) -> Vec<Mismatch> {
+	let lookup = |part| {
+		expected
+			.iter()
+			.find(|e| e.day == S::DAY && e.part == part)
+			.map(|e| e.value)
+	};
+	let actual = match s.parse(input) {
+		Ok(input) => [
+			s.part1(&input).map(|a| a.value).map_err(|e| e.to_string()),
+			s.part2(&input).map(|a| a.value).map_err(|e| e.to_string()),
+		],
+		Err(e) => [Err(e.to_string()), Err(e.to_string())],
+	};
+	[Part::Part1, Part::Part2]
+		.iter()
+		.zip(actual.iter())
+		.map(|(&part, actual)| Mismatch {
+			day: S::DAY,
+			part,
+			expected: lookup(part),
+			actual: actual.clone(),
+		})
+		.filter(|m| m.expected.is_none() || m.expected != m.actual.clone().ok())
+		.collect()
+}
+
+/// Check the answers of `s` for the input file at `input` against the
+/// manifest at `manifest`, panicking with a listing of all mismatches.
+pub fn assert_answers<S: Solution>(s: &S, input: &Path, manifest: &Path) {
+	let expected = load_manifest(manifest).unwrap_or_else(|e| panic!("{}", e));
+	let input =
+		crate::load_input(Some(input)).unwrap_or_else(|e| panic!("{}", e));
+	let mismatches = check(s, &input, &expected);
+	if !mismatches.is_empty() {
+		let diff = mismatches
+			.iter()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>()
+			.join("\n");
+		panic!("Answers differ from manifest:\n{}", diff);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Answer;
+
+	#[derive(Default)]
+	struct Sum;
+
+	impl Solution for Sum {
+		const DAY: u32 = 1;
+
+		type Input<'a> = &'a [u8];
+
+		fn parse<'a>(&self, input: &'a [u8]) -> Result<&'a [u8], Error> {
+			Ok(input)
+		}
+
+		fn part1(&self, input: &&[u8]) -> Result<Answer, Error> {
+			Ok(Answer::new(input.len()))
+		}
+
+		fn part2(&self, _: &&[u8]) -> Result<Answer, Error> {
+			Err(Error::NoAnswer(Part::Part2))
+		}
+	}
+
+	#[test]
+	fn test_parse_manifest() {
+		let r = parse_manifest("# day part answer\n\n7 part2 -10\n")
+			.expect("Failed to parse manifest");
+		assert_eq!(
+			vec![Expected {
+				day: 7,
+				part: Part::Part2,
+				value: -10
+			}],
+			r
+		);
+	}
+
+	#[test]
+	fn test_parse_manifest_invalid() {
+		assert!(parse_manifest("7 part3 10").is_err());
+		assert!(parse_manifest("7 part1").is_err());
+	}
+
+	#[test]
+	fn test_check_mismatch() {
+		let expected = parse_manifest("1 part1 4\n1 part2 0\n")
+			.expect("Failed to parse manifest");
+		let r = check(&Sum, b"abc", &expected);
+		assert_eq!(
+			vec![
+				"day 1 part1: expected 4, actual 3".to_string(),
+				"day 1 part2: expected 0, actual error: No answer found for part2"
+					.to_string(),
+			],
+			r.iter().map(ToString::to_string).collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_check_missing() {
+		let expected =
+			parse_manifest("1 part1 3\n").expect("Failed to parse manifest");
+		let r = check(&Sum, b"abc", &expected);
+		assert_eq!(1, r.len());
+		assert_eq!(None, r[0].expected);
+	}
+}
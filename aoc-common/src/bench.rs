@@ -0,0 +1,369 @@
+//! Repeated timing of the parse and solve phases of a solution.
+//!
+//! Results can be saved as a baseline, a plain text file with one
+//! `day phase median_ns p95_ns` entry per line, and later runs compared
+//! against it.
+
+use crate::{Error, Solution};
+use std::{
+	fmt, fs,
+	hint::black_box,
+	io::{self, Write},
+	path::Path,
+	str,
+	time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+	Parse,
+	Part1,
+	Part2,
+}
+
+impl fmt::Display for Phase {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Phase::Parse => write!(f, "parse"),
+			Phase::Part1 => write!(f, "part1"),
+			Phase::Part2 => write!(f, "part2"),
+		}
+	}
+}
+
+impl str::FromStr for Phase {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"parse" => Ok(Phase::Parse),
+			"part1" => Ok(Phase::Part1),
+			"part2" => Ok(Phase::Part2),
+			_ => Err(Error::Invalid(format!("unknown phase {:?}", s))),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+	pub warmup: usize,
+	pub runs: usize,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			warmup: 3,
+			runs: 20,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+	pub median: Duration,
+	pub p95: Duration,
+}
+
+impl Stats {
+	/// Median and 95th percentile of `samples`, which must not be empty.
+	pub fn from_samples(samples: &mut [Duration]) -> Self {
+		samples.sort();
+		let n = samples.len();
+		let median = if n.is_multiple_of(2) {
+			(samples[n / 2 - 1] + samples[n / 2]) / 2
+		} else {
+			samples[n / 2]
+		};
+		let p95 = samples[(n * 95).div_ceil(100).max(1) - 1];
+		Stats { median, p95 }
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+	pub day: u32,
+	pub phase: Phase,
+	pub stats: Stats,
+}
+
+/// Time `f` after `config.warmup` runs, failing on the first error.
+pub fn sample<F: FnMut() -> Result<(), Error>>(
+	config: &Config,
+	mut f: F,
+) -> Result<Stats, Error> {
+	for _ in 0..config.warmup {
+		f()?;
+	}
+	let mut samples = Vec::with_capacity(config.runs.max(1));
+	for _ in 0..config.runs.max(1) {
+		let start = Instant::now();
+		f()?;
+		samples.push(start.elapsed());
+	}
+	Ok(Stats::from_samples(&mut samples))
+}
+
+/// Time the parser and both parts of `s` on `input`.
+pub fn bench<S: Solution>(
+	s: &S,
+	input: &[u8],
+	config: &Config,
+) -> Result<Vec<Measurement>, Error> {
+	let parse = sample(config, || s.parse(black_box(input)).map(drop))?;
+	let parsed = s.parse(input)?;
+	let part1 = sample(config, || s.part1(black_box(&parsed)).map(drop))?;
+	let part2 = sample(config, || s.part2(black_box(&parsed)).map(drop))?;
+	Ok(vec![
+		Measurement {
+			day: S::DAY,
+			phase: Phase::Parse,
+			stats: parse,
+		},
+		Measurement {
+			day: S::DAY,
+			phase: Phase::Part1,
+			stats: part1,
+		},
+		Measurement {
+			day: S::DAY,
+			phase: Phase::Part2,
+			stats: part2,
+		},
+	])
+}
+
+pub fn parse_baseline(s: &str) -> Result<Vec<Measurement>, Error> {
+	s.lines()
+		.enumerate()
+		.map(|(n, l)| (n + 1, l.trim()))
+		.filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+		.map(|(n, l)| {
+			let invalid =
+				|| Error::Invalid(format!("baseline line {}: {:?}", n, l));
+			match l.split_whitespace().collect::<Vec<_>>()[..] {
+				[day, phase, median, p95] => Ok(Measurement {
+					day: day.parse().map_err(|_| invalid())?,
+					phase: phase.parse().map_err(|_| invalid())?,
+					stats: Stats {
+						median: Duration::from_nanos(
+							median.parse().map_err(|_| invalid())?,
+						),
+						p95: Duration::from_nanos(
+							p95.parse().map_err(|_| invalid())?,
+						),
+					},
+				}),
+				_ => Err(invalid()),
+			}
+		})
+		.collect()
+}
+
+pub fn load_baseline(path: &Path) -> Result<Vec<Measurement>, Error> {
+	let s = fs::read_to_string(path)
+		.map_err(|e| Error::Io(Some(path.into()), e))?;
+	parse_baseline(&s)
+}
+
+pub fn write_baseline<W: Write>(
+	w: &mut W,
+	measurements: &[Measurement],
+) -> io::Result<()> {
+	writeln!(w, "# day\tphase\tmedian_ns\tp95_ns")?;
+	for m in measurements {
+		writeln!(
+			w,
+			"{}\t{}\t{}\t{}",
+			m.day,
+			m.phase,
+			m.stats.median.as_nanos(),
+			m.stats.p95.as_nanos()
+		)?;
+	}
+	Ok(())
+}
+
+pub fn save_baseline(
+	path: &Path,
+	measurements: &[Measurement],
+) -> Result<(), Error> {
+	let mut buffer = Vec::new();
+	write_baseline(&mut buffer, measurements).map_err(Error::Write)?;
+	fs::write(path, buffer).map_err(Error::Write)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+	pub measurement: Measurement,
+	pub baseline: Option<Stats>,
+}
+
+impl Comparison {
+	/// Relative change of the median against the baseline, `0.1` is 10%
+	/// slower.
+	pub fn change(&self) -> Option<f64> {
+		self.baseline.map(|b| {
+			let base = b.median.as_secs_f64();
+			let current = self.measurement.stats.median.as_secs_f64();
+			if base > 0.0 {
+				current / base - 1.0
+			} else {
+				0.0
+			}
+		})
+	}
+
+	pub fn is_regression(&self, threshold: f64) -> bool {
+		self.change().is_some_and(|c| c > threshold)
+	}
+}
+
+pub fn compare(
+	measurements: &[Measurement],
+	baseline: &[Measurement],
+) -> Vec<Comparison> {
+	measurements
+		.iter()
+		.map(|m| Comparison {
+			measurement: m.clone(),
+			baseline: baseline
+				.iter()
+				.find(|b| b.day == m.day && b.phase == m.phase)
+				.map(|b| b.stats),
+		})
+		.collect()
+}
+
+/// A duration in ns, us, ms or s, whichever keeps it under a thousand.
+pub struct Time(pub Duration);
+
+impl fmt::Display for Time {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let ns = self.0.as_nanos();
+		let s = if ns < 1_000 {
+			format!("{}ns", ns)
+		} else if ns < 1_000_000 {
+			format!("{:.2}us", ns as f64 / 1e3)
+		} else if ns < 1_000_000_000 {
+			format!("{:.2}ms", ns as f64 / 1e6)
+		} else {
+			format!("{:.2}s", ns as f64 / 1e9)
+		};
+		f.pad(&s)
+	}
+}
+
+/// Write a table of `comparisons`, flagging changes above `threshold`.
+pub fn write_table<W: Write>(
+	w: &mut W,
+	comparisons: &[Comparison],
+	threshold: f64,
+) -> io::Result<()> {
+	writeln!(
+		w,
+		"{:>3}  {:<5}  {:>10}  {:>10}  {:>10}  {:>8}",
+		"day", "phase", "median", "p95", "baseline", "change"
+	)?;
+	for c in comparisons {
+		let m = &c.measurement;
+		let baseline = c
+			.baseline
+			.map_or("-".into(), |b| Time(b.median).to_string());
+		let change = c
+			.change()
+			.map_or("-".into(), |c| format!("{:+.1}%", c * 100.0));
+		let flag = if c.is_regression(threshold) {
+			"  REGRESSION"
+		} else {
+			""
+		};
+		writeln!(
+			w,
+			"{:>3}  {:<5}  {:>10}  {:>10}  {:>10}  {:>8}{}",
+			m.day,
+			m.phase,
+			Time(m.stats.median),
+			Time(m.stats.p95),
+			baseline,
+			change,
+			flag
+		)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ms(n: u64) -> Duration {
+		Duration::from_millis(n)
+	}
+
+	fn measurement(phase: Phase, median: u64) -> Measurement {
+		Measurement {
+			day: 7,
+			phase,
+			stats: Stats {
+				median: ms(median),
+				p95: ms(median * 2),
+			},
+		}
+	}
+
+	#[test]
+	fn test_stats_odd() {
+		let mut samples = vec![ms(5), ms(1), ms(3)];
+		let stats = Stats::from_samples(&mut samples);
+		assert_eq!(ms(3), stats.median);
+		assert_eq!(ms(5), stats.p95);
+	}
+
+	#[test]
+	fn test_stats_even() {
+		let mut samples = (1..=20).map(ms).collect::<Vec<_>>();
+		let stats = Stats::from_samples(&mut samples);
+		assert_eq!(Duration::from_micros(10_500), stats.median);
+		assert_eq!(ms(19), stats.p95);
+	}
+
+	#[test]
+	fn test_baseline_round_trip() {
+		let measurements =
+			vec![measurement(Phase::Parse, 1), measurement(Phase::Part2, 3)];
+		let mut buffer = Vec::new();
+		write_baseline(&mut buffer, &measurements).expect("Failed to write");
+		let r = parse_baseline(str::from_utf8(&buffer).unwrap())
+			.expect("Failed to parse baseline");
+		assert_eq!(measurements, r);
+	}
+
+	#[test]
+	fn test_parse_baseline_invalid() {
+		assert!(parse_baseline("7 compile 1 2").is_err());
+		assert!(parse_baseline("7 parse 1").is_err());
+	}
+
+	#[test]
+	fn test_compare() {
+		let baseline = vec![measurement(Phase::Parse, 10)];
+		let current =
+			vec![measurement(Phase::Parse, 12), measurement(Phase::Part1, 1)];
+		let r = compare(&current, &baseline);
+		assert_eq!(2, r.len());
+		assert!((r[0].change().unwrap() - 0.2).abs() < 1e-9);
+		assert!(r[0].is_regression(0.1));
+		assert!(!r[0].is_regression(0.25));
+		assert_eq!(None, r[1].change());
+		assert!(!r[1].is_regression(0.0));
+	}
+
+	#[test]
+	fn test_time_display() {
+		assert_eq!("999ns", Time(Duration::from_nanos(999)).to_string());
+		assert_eq!("1.50us", Time(Duration::from_nanos(1500)).to_string());
+		assert_eq!("2.00ms", Time(ms(2)).to_string());
+		assert_eq!("1.00s", Time(ms(1000)).to_string());
+	}
+}
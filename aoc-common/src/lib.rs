@@ -0,0 +1,325 @@
+pub mod answers;
+pub mod bench;
+pub mod options;
+pub mod parse;
+pub mod report;
+
+pub use options::Options;
+pub use parse::{parse_complete, position, Mode, ParseError};
+use report::{Format, Reporter};
+use std::{
+	env, fmt, fs,
+	io::{self, Read},
+	path::{Path, PathBuf},
+	process, str,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Part {
+	Part1,
+	Part2,
+}
+
+impl fmt::Display for Part {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Part::Part1 => write!(f, "part1"),
+			Part::Part2 => write!(f, "part2"),
+		}
+	}
+}
+
+impl str::FromStr for Part {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"part1" => Ok(Part::Part1),
+			"part2" => Ok(Part::Part2),
+			_ => Err(Error::Invalid(format!("unknown part {:?}", s))),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum Error {
+	Usage(String),
+	Io(Option<PathBuf>, io::Error),
+	Write(io::Error),
+	Parse(ParseError),
+	Invalid(String),
+	NoAnswer(Part),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Usage(msg) => write!(f, "{}", msg),
+			Error::Io(Some(path), e) => {
+				write!(f, "Failed to read {}: {}", path.display(), e)
+			}
+			Error::Io(None, e) => write!(f, "Failed to read stdin: {}", e),
+			Error::Write(e) => write!(f, "Failed to write output: {}", e),
+			Error::Parse(e) => write!(f, "{}", e),
+			Error::Invalid(msg) => write!(f, "Invalid input: {}", msg),
+			Error::NoAnswer(part) => write!(f, "No answer found for {}", part),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// Command line arguments shared by all day binaries.
+///
+/// Input is read from the file given with `-i`/`--input`, or from stdin if
+/// no file (or `-`) is given. Answers are written in the format given with
+/// `-f`/`--format`. With `--lenient` malformed records are reported and
+/// skipped instead of failing, `--strict` is the default. Remaining
+/// arguments are left for the day.
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+	pub input: Option<PathBuf>,
+	pub format: Format,
+	pub mode: Mode,
+	pub free: Vec<String>,
+}
+
+impl Args {
+	pub fn from_env() -> Result<Self, Error> {
+		Self::parse(env::args().skip(1))
+	}
+
+	pub fn parse<I: IntoIterator<Item = String>>(
+		args: I,
+	) -> Result<Self, Error> {
+		let mut res = Args::default();
+		let mut iter = args.into_iter();
+		while let Some(arg) = iter.next() {
+			match arg.as_str() {
+				"-i" | "--input" => {
+					let path = iter.next().ok_or_else(|| {
+						Error::Usage(format!("Missing value for {}", arg))
+					})?;
+					res.input = match path.as_str() {
+						"-" => None,
+						_ => Some(path.into()),
+					};
+				}
+				"-f" | "--format" => {
+					res.format = iter
+						.next()
+						.ok_or_else(|| {
+							Error::Usage(format!("Missing value for {}", arg))
+						})?
+						.parse()?;
+				}
+				"--strict" => res.mode = Mode::Strict,
+				"--lenient" => res.mode = Mode::Lenient,
+				_ => res.free.push(arg),
+			}
+		}
+		Ok(res)
+	}
+
+	pub fn load_input(&self) -> Result<Vec<u8>, Error> {
+		load_input(self.input.as_deref())
+	}
+
+	/// Parse `input` for `s` in the mode given, printing a warning for
+	/// each record skipped in lenient mode.
+	pub fn parse_input<'a, S: Solution>(
+		&self,
+		s: &S,
+		input: &'a [u8],
+	) -> Result<S::Input<'a>, Error> {
+		match self.mode {
+			Mode::Strict => s.parse(input),
+			Mode::Lenient => {
+				let (parsed, warnings) = s.parse_lenient(input)?;
+				for w in &warnings {
+					eprintln!("Warning: {}", w);
+				}
+				Ok(parsed)
+			}
+		}
+	}
+}
+
+/// Read all input from `path`, or from stdin if `path` is `None`.
+pub fn load_input(path: Option<&Path>) -> Result<Vec<u8>, Error> {
+	match path {
+		Some(path) => {
+			fs::read(path).map_err(|e| Error::Io(Some(path.into()), e))
+		}
+		None => {
+			let mut buffer = Vec::new();
+			io::stdin()
+				.lock()
+				.read_to_end(&mut buffer)
+				.map_err(|e| Error::Io(None, e))?;
+			Ok(buffer)
+		}
+	}
+}
+
+/// Answer to one part of a puzzle, with optional named intermediate results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+	pub value: i128,
+	pub details: Vec<(&'static str, i128)>,
+}
+
+impl Answer {
+	pub fn new<T: Into<Answer>>(value: T) -> Self {
+		value.into()
+	}
+
+	pub fn with_detail<T: Into<Answer>>(
+		mut self,
+		name: &'static str,
+		value: T,
+	) -> Self {
+		self.details.push((name, value.into().value));
+		self
+	}
+}
+
+macro_rules! answer_from {
+	($($t:ty),*) => {
+		$(impl From<$t> for Answer {
+			fn from(value: $t) -> Self {
+				Answer {
+					value: value as i128,
+					details: vec![],
+				}
+			}
+		})*
+	};
+}
+
+answer_from!(u32, u64, usize, i64);
+
+impl fmt::Display for Answer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.value)
+	}
+}
+
+/// A puzzle solution, split into parsing the input and solving each part.
+pub trait Solution {
+	const DAY: u32;
+
+	type Input<'a>;
+
+	fn parse<'a>(&self, input: &'a [u8]) -> Result<Self::Input<'a>, Error>;
+
+	/// Parse `input`, skipping malformed records and returning an error
+	/// for each of them alongside the result.
+	///
+	/// Solutions whose input is not made of independent records keep the
+	/// default, which is as strict as `parse`.
+	fn parse_lenient<'a>(
+		&self,
+		input: &'a [u8],
+	) -> Result<(Self::Input<'a>, Vec<ParseError>), Error> {
+		Ok((self.parse(input)?, vec![]))
+	}
+
+	fn part1(&self, input: &Self::Input<'_>) -> Result<Answer, Error>;
+
+	fn part2(&self, input: &Self::Input<'_>) -> Result<Answer, Error>;
+}
+
+pub fn solve<S: Solution>(s: &S, input: &[u8]) -> Result<[Answer; 2], Error> {
+	let input = s.parse(input)?;
+	Ok([s.part1(&input)?, s.part2(&input)?])
+}
+
+/// Load input as given by `args`, solve both parts and report the answers.
+pub fn run_solution<S: Solution>(s: &S, args: &Args) -> Result<(), Error> {
+	let (records, warnings) =
+		report::measure(s, &args.load_input()?, args.mode)?;
+	for w in &warnings {
+		eprintln!("Warning: {}", w);
+	}
+	let mut reporter = Reporter::new(args.format);
+	let stdout = io::stdout();
+	let mut w = stdout.lock();
+	for r in &records {
+		reporter.write(&mut w, r).map_err(Error::Write)?;
+	}
+	Ok(())
+}
+
+/// Entry point for day binaries that take no arguments of their own.
+pub fn main<S: Solution + Default>() {
+	run(|| run_solution(&S::default(), &Args::from_env()?))
+}
+
+/// Run `f`, printing any error to stderr and exiting with a failure code.
+pub fn run<F: FnOnce() -> Result<(), Error>>(f: F) {
+	if let Err(e) = f() {
+		eprintln!("Error: {}", e);
+		process::exit(1);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_answer_with_detail() {
+		let a = Answer::new(6_u64).with_detail("a", 2_usize);
+		assert_eq!(6, a.value);
+		assert_eq!(vec![("a", 2)], a.details);
+		assert_eq!("6", a.to_string());
+	}
+
+	#[test]
+	fn test_answer_negative() {
+		assert_eq!(-3, Answer::new(-3_i64).value);
+	}
+
+	#[test]
+	fn test_args_parse() {
+		let args = Args::parse(
+			vec!["--input", "day1/input", "2020"]
+				.into_iter()
+				.map(String::from),
+		)
+		.expect("Failed to parse args");
+		assert_eq!(Some(PathBuf::from("day1/input")), args.input);
+		assert_eq!(vec!["2020".to_string()], args.free);
+	}
+
+	#[test]
+	fn test_args_parse_stdin() {
+		let args = Args::parse(vec!["-i".to_string(), "-".to_string()])
+			.expect("Failed to parse args");
+		assert_eq!(None, args.input);
+	}
+
+	#[test]
+	fn test_args_parse_format() {
+		let args = Args::parse(vec!["--format".to_string(), "tsv".to_string()])
+			.expect("Failed to parse args");
+		assert_eq!(Format::Tsv, args.format);
+		assert!(Args::parse(vec!["-f".to_string(), "xml".to_string()]).is_err());
+	}
+
+	#[test]
+	fn test_args_parse_mode() {
+		let args = Args::parse(vec!["--lenient".to_string()])
+			.expect("Failed to parse args");
+		assert_eq!(Mode::Lenient, args.mode);
+		assert!(args.free.is_empty());
+		assert_eq!(Mode::Strict, Args::default().mode);
+	}
+
+	#[test]
+	fn test_args_parse_missing_value() {
+		let r = Args::parse(vec!["-i".to_string()]);
+		assert!(matches!(r, Err(Error::Usage(_))));
+	}
+}
@@ -0,0 +1,130 @@
+//! Options of the commands a binary takes after the shared [`Args`].
+//!
+//! [`Args`]: crate::Args
+
+use crate::Error;
+use std::{fmt, slice, str::FromStr};
+
+/// Reads options one at a time, failing with usage errors that end in the
+/// usage text of the command.
+#[derive(Debug, Clone)]
+pub struct Options<'a> {
+	usage: &'a str,
+	iter: slice::Iter<'a, String>,
+}
+
+impl<'a> Options<'a> {
+	pub fn new(usage: &'a str, args: &'a [String]) -> Self {
+		Options {
+			usage,
+			iter: args.iter(),
+		}
+	}
+
+	/// Usage error with `msg` above the usage text.
+	pub fn error<M: fmt::Display>(&self, msg: M) -> Error {
+		Error::Usage(format!("{}\n{}", msg, self.usage))
+	}
+
+	/// Usage error for an option the command does not take.
+	pub fn unknown(&self, arg: &str) -> Error {
+		self.error(format!("Unknown option: {}", arg))
+	}
+
+	/// The arguments not read yet.
+	pub fn rest(&self) -> &'a [String] {
+		self.iter.as_slice()
+	}
+
+	/// The value following option `arg`.
+	pub fn value(&mut self, arg: &str) -> Result<&'a str, Error> {
+		self.next()
+			.ok_or_else(|| self.error(format!("Missing value for {}", arg)))
+	}
+
+	/// The value following option `arg`, parsed.
+	pub fn parse<T: FromStr>(&mut self, arg: &str) -> Result<T, Error> {
+		let value = self.value(arg)?;
+		value.parse().map_err(|_| {
+			self.error(format!("Invalid value for {}: {}", arg, value))
+		})
+	}
+
+	/// Fail if any argument is left.
+	pub fn end(mut self) -> Result<(), Error> {
+		match self.next() {
+			Some(arg) => Err(self.unknown(arg)),
+			None => Ok(()),
+		}
+	}
+}
+
+/// The arguments in order, for reading options and their values in turn.
+impl<'a> Iterator for Options<'a> {
+	type Item = &'a str;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next().map(String::as_str)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const USAGE: &str = "Usage: day0 cmd [--n N]";
+
+	fn args(s: &str) -> Vec<String> {
+		s.split_whitespace().map(String::from).collect()
+	}
+
+	fn message(e: Error) -> String {
+		match e {
+			Error::Usage(msg) => msg,
+			e => panic!("Not a usage error: {:?}", e),
+		}
+	}
+
+	#[test]
+	fn test_options() {
+		let args = args("--n 3 --flag rest");
+		let mut options = Options::new(USAGE, &args);
+		assert_eq!(Some("--n"), options.next());
+		assert_eq!(3, options.parse::<u32>("--n").unwrap());
+		assert_eq!(Some("--flag"), options.next());
+		assert_eq!(["rest".to_string()], options.rest());
+		assert_eq!("rest", options.value("--flag").unwrap());
+		assert_eq!(None, options.next());
+		assert!(options.end().is_ok());
+	}
+
+	#[test]
+	fn test_options_iter() {
+		let args = args("--a --b");
+		assert_eq!(
+			vec!["--a", "--b"],
+			Options::new(USAGE, &args).collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_options_errors() {
+		let args = args("--n x --n");
+		let mut options = Options::new(USAGE, &args);
+		options.next();
+		assert_eq!(
+			"Invalid value for --n: x\nUsage: day0 cmd [--n N]",
+			message(options.parse::<u32>("--n").unwrap_err())
+		);
+		options.next();
+		assert_eq!(
+			"Missing value for --n\nUsage: day0 cmd [--n N]",
+			message(options.value("--n").unwrap_err())
+		);
+		let extra = ["-x".to_string()];
+		assert_eq!(
+			"Unknown option: -x\nUsage: day0 cmd [--n N]",
+			message(Options::new(USAGE, &extra).end().unwrap_err())
+		);
+	}
+}
@@ -0,0 +1,344 @@
+//! Running nom parsers over puzzle input with position-aware errors.
+//!
+//! Parsers use `VerboseError` so that failures can be reported with the
+//! line, column and what was expected, taken from the innermost `context`
+//! around the failing parser.
+
+use crate::Error;
+use nom::{
+	error::{ErrorKind, ParseError as _, VerboseError, VerboseErrorKind},
+	Err,
+};
+use std::{fmt, str};
+
+const SNIPPET_LEN: usize = 40;
+
+pub type IResult<I, O> = nom::IResult<I, O, VerboseError<I>>;
+
+/// How malformed records in the input are handled.
+///
+/// In strict mode parsing fails on the first malformed record. In lenient
+/// mode all malformed records are collected and skipped.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Mode {
+	#[default]
+	Strict,
+	Lenient,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+	pub line: usize,
+	pub column: usize,
+	pub snippet: String,
+	pub expected: String,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"line {}, col {}: expected {}",
+			self.line, self.column, self.expected
+		)?;
+		match self.snippet.as_str() {
+			"" => write!(f, ", found end of line"),
+			s => write!(f, ", found {:?}", s),
+		}
+	}
+}
+
+impl ParseError {
+	/// Error at `offset` into `input`, where `expected` was not found.
+	pub fn new(input: &[u8], offset: usize, expected: String) -> Self {
+		let (line, column) = position(input, offset);
+		ParseError {
+			line,
+			column,
+			snippet: snippet(&input[offset.min(input.len())..]),
+			expected,
+		}
+	}
+
+	/// Error for `e`, raised by a parser run on the part of `input` that
+	/// ends at `end`.
+	fn from_verbose(input: &[u8], end: usize, e: &VerboseError<&[u8]>) -> Self {
+		let context = e.errors.iter().find_map(|(i, k)| match k {
+			VerboseErrorKind::Context(c) => Some((i, c.to_string())),
+			_ => None,
+		});
+		let (rest, expected) = match (context, e.errors.first()) {
+			(Some((i, c)), _) => (*i, c),
+			(None, Some((i, k))) => (*i, describe(k)),
+			(None, None) => (&input[end..end], "valid input".into()),
+		};
+		ParseError::new(input, end - rest.len(), expected)
+	}
+}
+
+fn describe(kind: &VerboseErrorKind) -> String {
+	match kind {
+		VerboseErrorKind::Context(c) => c.to_string(),
+		VerboseErrorKind::Char('\n') => "newline".into(),
+		VerboseErrorKind::Char(c) => format!("{:?}", c),
+		VerboseErrorKind::Nom(ErrorKind::Digit) => "digit".into(),
+		VerboseErrorKind::Nom(ErrorKind::Eof) => "end of input".into(),
+		VerboseErrorKind::Nom(k) => k.description().to_lowercase(),
+	}
+}
+
+/// Line and column, both starting at 1, of `offset` into `input`.
+pub fn position(input: &[u8], offset: usize) -> (usize, usize) {
+	let before = &input[..offset.min(input.len())];
+	let line = before.iter().filter(|&&c| c == b'\n').count() + 1;
+	let column = match before.iter().rposition(|&c| c == b'\n') {
+		Some(n) => offset - n,
+		None => offset + 1,
+	};
+	(line, column)
+}
+
+fn snippet(rest: &[u8]) -> String {
+	let end = rest
+		.iter()
+		.position(|&c| c == b'\n')
+		.unwrap_or(rest.len())
+		.min(SNIPPET_LEN);
+	String::from_utf8_lossy(&rest[..end]).into_owned()
+}
+
+fn is_blank(i: &[u8]) -> bool {
+	i.iter().all(u8::is_ascii_whitespace)
+}
+
+/// Run `parser` on `input[start..end]` and require that all of it but
+/// trailing whitespace is consumed. Errors are positioned in `input`.
+fn complete_at<'a, O, F>(
+	parser: &mut F,
+	input: &'a [u8],
+	start: usize,
+	end: usize,
+	what: &str,
+) -> Result<O, ParseError>
+where
+	F: FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+	match parser(&input[start..end]) {
+		Ok((rest, o)) if is_blank(rest) => Ok(o),
+		Ok((rest, _)) => Err(ParseError::new(
+			input,
+			end - rest.len(),
+			format!("end of {}", what),
+		)),
+		Err(Err::Error(e)) | Err(Err::Failure(e)) => {
+			Err(ParseError::from_verbose(input, end, &e))
+		}
+		Err(Err::Incomplete(_)) => Err(ParseError::new(
+			input,
+			end,
+			format!("more input before end of {}", what),
+		)),
+	}
+}
+
+/// Run `parser` on `input` and require that all input is consumed.
+///
+/// Trailing whitespace, such as a final newline, is accepted.
+pub fn parse_complete<'a, O, F>(
+	mut parser: F,
+	input: &'a [u8],
+) -> Result<O, Error>
+where
+	F: FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+	complete_at(&mut parser, input, 0, input.len(), "input")
+		.map_err(Error::Parse)
+}
+
+/// One or more `f` separated by `sep`, up to the end of input.
+///
+/// Unlike `separated_list1` this does not stop at the first record that
+/// fails to parse, it fails with the error of that record. Trailing
+/// whitespace is left unconsumed.
+pub fn records<'a, O, O2, F, G>(
+	mut sep: G,
+	mut f: F,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<O>>
+where
+	F: FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
+	G: FnMut(&'a [u8]) -> IResult<&'a [u8], O2>,
+{
+	move |i| {
+		let (mut i, o) = f(i)?;
+		let mut res = vec![o];
+		while !is_blank(i) {
+			let (rest, _) = sep(i)?;
+			if is_blank(rest) {
+				break;
+			}
+			let (rest, o) = f(rest)?;
+			if rest.len() == i.len() {
+				return Err(Err::Error(VerboseError::from_error_kind(
+					rest,
+					ErrorKind::SeparatedList,
+				)));
+			}
+			res.push(o);
+			i = rest;
+		}
+		Ok((i, res))
+	}
+}
+
+/// Parse each record of `input` that ends with `sep` with `f`, returning
+/// the records that parse and errors for those that do not.
+///
+/// Blank records are skipped.
+pub fn lenient<'a, O, F>(
+	mut f: F,
+	sep: &[u8],
+	input: &'a [u8],
+) -> (Vec<O>, Vec<ParseError>)
+where
+	F: FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+	let mut res = vec![];
+	let mut errors = vec![];
+	let mut start = 0;
+	while start < input.len() {
+		let end = input[start..]
+			.windows(sep.len())
+			.position(|w| w == sep)
+			.map_or(input.len(), |n| start + n + sep.len());
+		if !is_blank(&input[start..end]) {
+			match complete_at(&mut f, input, start, end, "record") {
+				Ok(o) => res.push(o),
+				Err(e) => errors.push(e),
+			}
+		}
+		start = end;
+	}
+	(res, errors)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use nom::{
+		branch::alt,
+		bytes::complete::tag,
+		character::complete::{digit1, newline},
+		combinator::map_res,
+		error::context,
+		multi::many1,
+		sequence::terminated,
+	};
+
+	fn lines(i: &[u8]) -> IResult<&[u8], Vec<&[u8]>> {
+		many1(terminated(tag("ab"), newline))(i)
+	}
+
+	fn bag(i: &[u8]) -> IResult<&[u8], &[u8]> {
+		context("\"bags\" or \"bag\"", alt((tag("bags"), tag("bag"))))(i)
+	}
+
+	fn number(i: &[u8]) -> IResult<&[u8], u32> {
+		map_res(map_res(digit1, str::from_utf8), str::parse)(i)
+	}
+
+	#[test]
+	fn test_position() {
+		assert_eq!((1, 1), position(b"ab\ncd", 0));
+		assert_eq!((1, 3), position(b"ab\ncd", 2));
+		assert_eq!((2, 1), position(b"ab\ncd", 3));
+		assert_eq!((2, 2), position(b"ab\ncd", 4));
+	}
+
+	#[test]
+	fn test_parse_complete() {
+		let r = parse_complete(lines, b"ab\nab\n").expect("Failed to parse");
+		assert_eq!(vec![b"ab", b"ab"], r);
+	}
+
+	#[test]
+	fn test_parse_complete_trailing_whitespace() {
+		let r = parse_complete(lines, b"ab\n\n").expect("Failed to parse");
+		assert_eq!(vec![b"ab"], r);
+	}
+
+	#[test]
+	fn test_parse_complete_trailing_input() {
+		match parse_complete(lines, b"ab\nab\ncd\n") {
+			Err(Error::Parse(e)) => {
+				assert_eq!(
+					"line 3, col 1: expected end of input, found \"cd\"",
+					e.to_string()
+				);
+			}
+			r => panic!("Unexpected result: {:?}", r),
+		}
+	}
+
+	#[test]
+	fn test_parse_complete_context() {
+		match parse_complete(bag, b"bg") {
+			Err(Error::Parse(e)) => assert_eq!(
+				"line 1, col 1: expected \"bags\" or \"bag\", found \"bg\"",
+				e.to_string()
+			),
+			r => panic!("Unexpected result: {:?}", r),
+		}
+	}
+
+	#[test]
+	fn test_parse_complete_char() {
+		match parse_complete(lines, b"ab") {
+			Err(Error::Parse(e)) => assert_eq!(
+				"line 1, col 3: expected newline, found end of line",
+				e.to_string()
+			),
+			r => panic!("Unexpected result: {:?}", r),
+		}
+	}
+
+	#[test]
+	fn test_records() {
+		let r = parse_complete(records(newline, number), b"1\n2\n3\n")
+			.expect("Failed to parse");
+		assert_eq!(vec![1, 2, 3], r);
+	}
+
+	#[test]
+	fn test_records_error() {
+		match parse_complete(records(newline, number), b"1\n2\nx\n4\n") {
+			Err(Error::Parse(e)) => {
+				assert_eq!((3, 1), (e.line, e.column));
+				assert_eq!("digit", e.expected);
+				assert_eq!("x", e.snippet);
+			}
+			r => panic!("Unexpected result: {:?}", r),
+		}
+	}
+
+	#[test]
+	fn test_lenient() {
+		let (r, errors) = lenient(number, b"\n", b"1\nx\n3\n4y\n\n5");
+		assert_eq!(vec![1, 3, 5], r);
+		assert_eq!(
+			vec![
+				"line 2, col 1: expected digit, found \"x\"".to_string(),
+				"line 4, col 2: expected end of record, found \"y\""
+					.to_string(),
+			],
+			errors.iter().map(ToString::to_string).collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_lenient_separator() {
+		let (r, errors) =
+			lenient(records(newline, number), b"\n\n", b"1\n2\n\n3\n");
+		assert_eq!(vec![vec![1, 2], vec![3]], r);
+		assert!(errors.is_empty());
+	}
+}
@@ -0,0 +1,245 @@
+//! Answer reporting in human readable or machine readable form.
+
+use crate::{Answer, Error, Mode, ParseError, Part, Solution};
+use std::{
+	fmt::Write as _,
+	io::{self, Write},
+	str,
+	time::{Duration, Instant},
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Format {
+	#[default]
+	Text,
+	Json,
+	Tsv,
+}
+
+impl str::FromStr for Format {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"text" => Ok(Format::Text),
+			"json" => Ok(Format::Json),
+			"tsv" => Ok(Format::Tsv),
+			_ => Err(Error::Usage(format!(
+				"Unknown format {:?}, expected json, tsv or text",
+				s
+			))),
+		}
+	}
+}
+
+/// Answer to one part, with the time spent parsing input and solving.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+	pub day: u32,
+	pub part: Part,
+	pub answer: Answer,
+	pub parse_time: Duration,
+	pub solve_time: Duration,
+}
+
+/// Parse `input` in `mode` and solve both parts, timing each phase.
+///
+/// Records skipped in lenient mode are returned alongside the answers.
+pub fn measure<S: Solution>(
+	s: &S,
+	input: &[u8],
+	mode: Mode,
+) -> Result<([Record; 2], Vec<ParseError>), Error> {
+	let start = Instant::now();
+	let (input, skipped) = match mode {
+		Mode::Strict => (s.parse(input)?, vec![]),
+		Mode::Lenient => s.parse_lenient(input)?,
+	};
+	let parse_time = start.elapsed();
+
+	let start = Instant::now();
+	let part1 = s.part1(&input)?;
+	let part1_time = start.elapsed();
+
+	let start = Instant::now();
+	let part2 = s.part2(&input)?;
+	let part2_time = start.elapsed();
+
+	let records = [
+		Record {
+			day: S::DAY,
+			part: Part::Part1,
+			answer: part1,
+			parse_time,
+			solve_time: part1_time,
+		},
+		Record {
+			day: S::DAY,
+			part: Part::Part2,
+			answer: part2,
+			parse_time,
+			solve_time: part2_time,
+		},
+	];
+	Ok((records, skipped))
+}
+
+fn json_string(s: &str) -> String {
+	let mut res = String::with_capacity(s.len() + 2);
+	res.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => res.push_str("\\\""),
+			'\\' => res.push_str("\\\\"),
+			c if c.is_control() => {
+				let _ = write!(res, "\\u{:04x}", c as u32);
+			}
+			c => res.push(c),
+		}
+	}
+	res.push('"');
+	res
+}
+
+/// Writes records in the chosen format.
+///
+/// Json output has one object per line. Tsv output starts with a header
+/// and has one row for the answer and one for each named sub-result.
+#[derive(Debug, Default)]
+pub struct Reporter {
+	format: Format,
+	header_written: bool,
+}
+
+impl Reporter {
+	pub fn new(format: Format) -> Self {
+		Reporter {
+			format,
+			header_written: false,
+		}
+	}
+
+	pub fn format(&self) -> Format {
+		self.format
+	}
+
+	pub fn write<W: Write>(&mut self, w: &mut W, r: &Record) -> io::Result<()> {
+		match self.format {
+			Format::Text => {
+				for (name, value) in &r.answer.details {
+					writeln!(w, "Answer {} {}: {}", r.part, name, value)?;
+				}
+				writeln!(w, "Answer {}: {}", r.part, r.answer)
+			}
+			Format::Json => {
+				let details = r
+					.answer
+					.details
+					.iter()
+					.map(|(name, value)| {
+						format!("{}:{}", json_string(name), value)
+					})
+					.collect::<Vec<_>>()
+					.join(",");
+				writeln!(
+					w,
+					"{{\"day\":{},\"part\":\"{}\",\"answer\":{},\"parse_time_ns\":{},\"solve_time_ns\":{},\"details\":{{{}}}}}",
+					r.day,
+					r.part,
+					r.answer.value,
+					r.parse_time.as_nanos(),
+					r.solve_time.as_nanos(),
+					details
+				)
+			}
+			Format::Tsv => {
+				if !self.header_written {
+					writeln!(
+						w,
+						"day\tpart\tresult\tanswer\tparse_time_ns\tsolve_time_ns"
+					)?;
+					self.header_written = true;
+				}
+				let rows = Some(("answer", r.answer.value))
+					.into_iter()
+					.chain(r.answer.details.iter().copied());
+				for (name, value) in rows {
+					writeln!(
+						w,
+						"{}\t{}\t{}\t{}\t{}\t{}",
+						r.day,
+						r.part,
+						name,
+						value,
+						r.parse_time.as_nanos(),
+						r.solve_time.as_nanos()
+					)?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn record() -> Record {
+		Record {
+			day: 3,
+			part: Part::Part2,
+			answer: Answer::new(6_u64).with_detail("slope1", 2_u64),
+			parse_time: Duration::from_nanos(10),
+			solve_time: Duration::from_nanos(20),
+		}
+	}
+
+	fn write_all(format: Format, records: &[Record]) -> String {
+		let mut reporter = Reporter::new(format);
+		let mut buffer = Vec::new();
+		for r in records {
+			reporter.write(&mut buffer, r).expect("Failed to write");
+		}
+		String::from_utf8(buffer).expect("Invalid utf8")
+	}
+
+	#[test]
+	fn test_format_from_str() {
+		assert_eq!(Format::Json, "json".parse().expect("Failed to parse"));
+		assert!("xml".parse::<Format>().is_err());
+	}
+
+	#[test]
+	fn test_write_text() {
+		assert_eq!(
+			"Answer part2 slope1: 2\nAnswer part2: 6\n",
+			write_all(Format::Text, &[record()])
+		);
+	}
+
+	#[test]
+	fn test_write_json() {
+		assert_eq!(
+			"{\"day\":3,\"part\":\"part2\",\"answer\":6,\"parse_time_ns\":10,\"solve_time_ns\":20,\"details\":{\"slope1\":2}}\n",
+			write_all(Format::Json, &[record()])
+		);
+	}
+
+	#[test]
+	fn test_write_tsv() {
+		assert_eq!(
+			"day\tpart\tresult\tanswer\tparse_time_ns\tsolve_time_ns\n\
+			 3\tpart2\tanswer\t6\t10\t20\n\
+			 3\tpart2\tslope1\t2\t10\t20\n\
+			 3\tpart2\tanswer\t6\t10\t20\n\
+			 3\tpart2\tslope1\t2\t10\t20\n",
+			write_all(Format::Tsv, &[record(), record()])
+		);
+	}
+
+	#[test]
+	fn test_json_string() {
+		assert_eq!("\"a\\\"b\\\\c\\u000a\"", json_string("a\"b\\c\n"));
+	}
+}
@@ -0,0 +1,497 @@
+//! Generators of valid puzzle inputs of any size, for stress testing the
+//! solutions. The same seed always gives the same input.
+
+mod rng;
+
+use aoc_common::Error;
+pub use rng::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+	/// Number of records, such as lines, passports, groups, bag rules or
+	/// instructions.
+	pub size: usize,
+	pub seed: u64,
+	/// Levels of bags below "shiny gold" in day 7 rules.
+	pub depth: usize,
+	/// Number of different bags in each day 7 bag above the bottom level.
+	pub fan_out: usize,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			size: 1000,
+			seed: 0,
+			depth: 6,
+			fan_out: 3,
+		}
+	}
+}
+
+/// Input for `day` as described by `config`.
+pub fn generate(day: u32, config: &Config) -> Result<String, Error> {
+	let mut rng = Rng::new(config.seed);
+	match day {
+		1 => expenses(config.size, &mut rng),
+		2 => Ok(password_policies(config.size, &mut rng)),
+		3 => Ok(tree_map(config.size, &mut rng)),
+		4 => Ok(passports(config.size, &mut rng)),
+		5 => boarding_passes(config.size, &mut rng),
+		6 => Ok(customs_groups(config.size, &mut rng)),
+		7 => bag_rules(config, &mut rng),
+		8 => program(config.size, &mut rng),
+		_ => Err(Error::Usage(format!("Unknown day: {}", day))),
+	}
+}
+
+fn letter(rng: &mut Rng) -> char {
+	(b'a' + rng.range(0..26) as u8) as char
+}
+
+/// Expenses where only a hidden pair and a hidden triple can sum to 2020.
+/// All other entries are larger than 2020, so the solver has to keep
+/// searching until it reaches the hidden ones.
+fn expenses(size: usize, rng: &mut Rng) -> Result<String, Error> {
+	if size < 5 {
+		return Err(Error::Usage(format!(
+			"Day 1 needs at least 5 expenses, got {}",
+			size
+		)));
+	}
+	let a = rng.range(1..1010);
+	let (b, c) = (rng.range(1..673), rng.range(1..673));
+	let mut entries = vec![a, 2020 - a, b, c, 2020 - b - c];
+	entries.extend((5..size).map(|_| rng.range(2021..10000)));
+	rng.shuffle(&mut entries);
+	Ok(entries.iter().map(|n| format!("{}\n", n)).collect())
+}
+
+fn password_policies(size: usize, rng: &mut Rng) -> String {
+	(0..size)
+		.map(|_| {
+			let lower = rng.range(1..10);
+			let upper = lower + rng.range(1..10);
+			let c = letter(rng);
+			let password = (0..rng.range(1..upper + 5))
+				.map(|_| if rng.chance(30) { c } else { letter(rng) })
+				.collect::<String>();
+			format!("{}-{} {}: {}\n", lower, upper, c, password)
+		})
+		.collect()
+}
+
+fn tree_map(size: usize, rng: &mut Rng) -> String {
+	(0..size)
+		.map(|_| {
+			let mut line = (0..31)
+				.map(|_| if rng.chance(20) { '#' } else { '.' })
+				.collect::<String>();
+			line.push('\n');
+			line
+		})
+		.collect()
+}
+
+fn passport_value(key: &str, rng: &mut Rng) -> String {
+	match key {
+		"byr" => rng.range(1900..2010).to_string(),
+		"iyr" => rng.range(2005..2025).to_string(),
+		"eyr" => rng.range(2015..2035).to_string(),
+		"hgt" => match rng.range(0..10) {
+			0 => rng.range(50..200).to_string(),
+			1..=4 => format!("{}in", rng.range(55..80)),
+			_ => format!("{}cm", rng.range(140..200)),
+		},
+		"hcl" => {
+			let hex = (0..6)
+				.map(|_| *rng.choose(b"0123456789abcdef") as char)
+				.collect::<String>();
+			if rng.chance(90) {
+				format!("#{}", hex)
+			} else {
+				hex
+			}
+		}
+		"ecl" => rng
+			.choose(&["amb", "blu", "brn", "gry", "grn", "hzl", "oth", "xry"])
+			.to_string(),
+		"pid" => {
+			let len = if rng.chance(90) { 9 } else { rng.range(5..12) };
+			(0..len).map(|_| rng.range(0..10).to_string()).collect()
+		}
+		_ => rng.range(100..350).to_string(),
+	}
+}
+
+fn passports(size: usize, rng: &mut Rng) -> String {
+	const KEYS: [&str; 8] =
+		["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+	(0..size)
+		.map(|_| {
+			let mut keys = KEYS
+				.iter()
+				.filter(|&&k| rng.chance(if k == "cid" { 60 } else { 90 }))
+				.collect::<Vec<_>>();
+			if keys.is_empty() {
+				keys.push(&"cid");
+			}
+			rng.shuffle(&mut keys);
+			let mut passport = String::new();
+			for (n, key) in keys.into_iter().enumerate() {
+				if n > 0 {
+					passport.push(if rng.chance(30) { '\n' } else { ' ' });
+				}
+				passport.push_str(key);
+				passport.push(':');
+				passport.push_str(&passport_value(key, rng));
+			}
+			passport.push('\n');
+			passport
+		})
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+/// Boarding passes for a row of consecutive seats with one seat missing.
+/// There are only 1024 seats, so at most 1023 passes.
+fn boarding_passes(size: usize, rng: &mut Rng) -> Result<String, Error> {
+	if !(2..=1023).contains(&size) {
+		return Err(Error::Usage(format!(
+			"Day 5 needs between 2 and 1023 boarding passes, got {}",
+			size
+		)));
+	}
+	let start = rng.range(0..1024 - size as u64);
+	let missing = rng.range(start + 1..start + size as u64);
+	let mut ids = (start..=start + size as u64)
+		.filter(|&id| id != missing)
+		.collect::<Vec<_>>();
+	rng.shuffle(&mut ids);
+	Ok(ids
+		.iter()
+		.map(|id| {
+			let mut pass = (0..10)
+				.rev()
+				.map(|bit| match (bit >= 3, id >> bit & 1 == 1) {
+					(true, false) => 'F',
+					(true, true) => 'B',
+					(false, false) => 'L',
+					(false, true) => 'R',
+				})
+				.collect::<String>();
+			pass.push('\n');
+			pass
+		})
+		.collect())
+}
+
+fn customs_groups(size: usize, rng: &mut Rng) -> String {
+	let mut letters = (b'a'..=b'z').map(char::from).collect::<Vec<_>>();
+	(0..size)
+		.map(|_| {
+			(0..rng.range(1..6))
+				.map(|_| {
+					rng.shuffle(&mut letters);
+					let len = rng.range(1..11) as usize;
+					let mut member = letters[..len].iter().collect::<String>();
+					member.push('\n');
+					member
+				})
+				.collect::<String>()
+		})
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+const ADJECTIVES: [&str; 16] = [
+	"light", "dark", "bright", "muted", "faded", "dotted", "vibrant", "pale",
+	"dull", "wavy", "striped", "drab", "posh", "plaid", "clear", "mirrored",
+];
+
+const COLORS: [&str; 16] = [
+	"red", "orange", "white", "yellow", "gold", "olive", "plum", "blue",
+	"black", "green", "tan", "teal", "lime", "cyan", "coral", "violet",
+];
+
+/// Unique name of bag `i`, never "shiny gold".
+fn bag_name(i: usize) -> String {
+	let mut name = format!(
+		"{} {}",
+		ADJECTIVES[i % ADJECTIVES.len()],
+		COLORS[i / ADJECTIVES.len() % COLORS.len()]
+	);
+	let mut n = i / (ADJECTIVES.len() * COLORS.len());
+	while n > 0 {
+		name.push((b'a' + (n % 26) as u8) as char);
+		n /= 26;
+	}
+	name
+}
+
+/// Bag rules forming a layered DAG. "shiny gold" is on the second level,
+/// inside every bag on the first level, with `depth` levels below it.
+/// Each bag above the bottom level contains up to `fan_out` bags from the
+/// level below.
+fn bag_rules(config: &Config, rng: &mut Rng) -> Result<String, Error> {
+	let levels = config.depth + 2;
+	if config.size < levels {
+		return Err(Error::Usage(format!(
+			"Day 7 needs at least {} bags for depth {}, got {}",
+			levels, config.depth, config.size
+		)));
+	}
+	let mut names = vec![];
+	let mut bags = vec![];
+	for level in 0..levels {
+		let count = config.size / levels
+			+ usize::from(level >= levels - config.size % levels);
+		let first = names.len();
+		for i in first..first + count {
+			names.push(match (level, i) {
+				(1, i) if i == first => "shiny gold".to_string(),
+				(_, i) => bag_name(i),
+			});
+		}
+		bags.push(first..first + count);
+	}
+	let shiny_gold = bags[1].start;
+	let mut rules = (0..levels)
+		.flat_map(|level| bags[level].clone().map(move |i| (level, i)))
+		.map(|(level, i)| {
+			let mut content = match bags.get(level + 1) {
+				Some(below) => below.clone().collect::<Vec<_>>(),
+				None => vec![],
+			};
+			rng.shuffle(&mut content);
+			content.truncate(config.fan_out);
+			if level == 0 && !content.contains(&shiny_gold) {
+				content.truncate(config.fan_out.max(1) - 1);
+				content.push(shiny_gold);
+			}
+			let content = content
+				.iter()
+				.map(|&j| match rng.range(1..4) {
+					1 => format!("1 {} bag", names[j]),
+					n => format!("{} {} bags", n, names[j]),
+				})
+				.collect::<Vec<_>>();
+			let content = match content.len() {
+				0 => "no other bags".to_string(),
+				_ => content.join(", "),
+			};
+			format!("{} bags contain {}.\n", names[i], content)
+		})
+		.collect::<Vec<_>>();
+	rng.shuffle(&mut rules);
+	Ok(rules.concat())
+}
+
+fn nonzero(rng: &mut Rng) -> i64 {
+	let n = rng.range(1..50) as i64;
+	if rng.chance(50) {
+		n
+	} else {
+		-n
+	}
+}
+
+/// Program that loops, and terminates once exactly one instruction is
+/// repaired.
+///
+/// Wherever flipping an instruction on the execution path would leave the
+/// path it leads into a trap, a block that loops forever: forward jumps
+/// skip over a trap and nops point into one. The only flip that avoids
+/// the traps is the backward jump closing the loop, which then continues
+/// into a tail that runs off the end of the program.
+fn program(size: usize, rng: &mut Rng) -> Result<String, Error> {
+	if size < 2 {
+		return Err(Error::Usage(format!(
+			"Day 8 needs at least 2 instructions, got {}",
+			size
+		)));
+	}
+	let tail = (size / 10).max(1);
+	let body = size - tail - 1;
+	let mut instrs: Vec<(&str, i64)> = Vec::with_capacity(size);
+	let mut path = vec![];
+	let mut traps = vec![];
+	while instrs.len() < body {
+		let left = body - instrs.len();
+		match rng.range(0..if left >= 2 { 3 } else { 2 }) {
+			0 => {
+				path.push(instrs.len());
+				instrs.push(("acc", nonzero(rng)));
+			}
+			1 => {
+				path.push(instrs.len());
+				instrs.push(("nop", 0));
+			}
+			_ => {
+				let trap = if left >= 3 && rng.chance(50) { 2 } else { 1 };
+				path.push(instrs.len());
+				instrs.push(("jmp", trap + 1));
+				traps.push(instrs.len());
+				if trap == 2 {
+					instrs.push(("acc", nonzero(rng)));
+					instrs.push(("jmp", -1));
+				} else {
+					instrs.push(("jmp", 0));
+				}
+			}
+		}
+	}
+	let pc = instrs.len();
+	let target = match path.len() {
+		0 => pc,
+		n => path[rng.index(n)],
+	};
+	instrs.push(("jmp", target as i64 - pc as i64));
+	while instrs.len() < size {
+		if rng.chance(50) {
+			instrs.push(("acc", nonzero(rng)));
+		} else {
+			instrs.push(("nop", 0));
+		}
+	}
+	for (pc, instr) in instrs.iter_mut().enumerate() {
+		if instr.0 == "nop" && !traps.is_empty() {
+			instr.1 = traps[rng.index(traps.len())] as i64 - pc as i64;
+		}
+	}
+	Ok(instrs
+		.iter()
+		.map(|(op, n)| format!("{} {:+}\n", op, n))
+		.collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use aoc_common::{solve, Solution};
+
+	fn config(size: usize, seed: u64) -> Config {
+		Config {
+			size,
+			seed,
+			..Config::default()
+		}
+	}
+
+	fn check<S: Solution>(s: &S, day: u32, sizes: &[usize]) {
+		for &size in sizes {
+			for seed in 0..10 {
+				let input = generate(day, &config(size, seed))
+					.expect("Failed to generate input");
+				if let Err(e) = solve(s, input.as_bytes()) {
+					panic!("day {} size {} seed {}: {}", day, size, seed, e);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_reproducible() {
+		for day in 1..=8 {
+			let a = generate(day, &config(50, 3)).expect("Failed to generate");
+			let b = generate(day, &config(50, 3)).expect("Failed to generate");
+			let c = generate(day, &config(50, 4)).expect("Failed to generate");
+			assert_eq!(a, b);
+			assert_ne!(a, c);
+		}
+	}
+
+	#[test]
+	fn test_unknown_day() {
+		assert!(generate(9, &Config::default()).is_err());
+	}
+
+	#[test]
+	fn test_day1() {
+		check(&day1::Day1::default(), 1, &[5, 100]);
+		assert!(generate(1, &config(4, 0)).is_err());
+	}
+
+	#[test]
+	fn test_day2() {
+		check(&day2::Day2, 2, &[1, 100]);
+	}
+
+	#[test]
+	fn test_day3() {
+		check(&day3::Day3, 3, &[1, 100]);
+	}
+
+	#[test]
+	fn test_day4() {
+		check(&day4::Day4, 4, &[1, 100]);
+	}
+
+	#[test]
+	fn test_day5() {
+		check(&day5::Day5, 5, &[2, 100, 1023]);
+		assert!(generate(5, &config(1024, 0)).is_err());
+	}
+
+	#[test]
+	fn test_day6() {
+		check(&day6::Day6, 6, &[1, 100]);
+	}
+
+	#[test]
+	fn test_day7() {
+		check(&day7::Day7, 7, &[8, 100]);
+		assert!(generate(7, &config(7, 0)).is_err());
+	}
+
+	#[test]
+	fn test_day7_shape() {
+		let input = generate(
+			7,
+			&Config {
+				size: 40,
+				depth: 3,
+				fan_out: 2,
+				..Config::default()
+			},
+		)
+		.expect("Failed to generate input");
+		assert_eq!(40, input.lines().count());
+		let containing = input
+			.lines()
+			.filter(|l| l.contains("contain") && l.contains("shiny gold bag"))
+			.filter(|l| !l.starts_with("shiny gold"))
+			.count();
+		assert_eq!(8, containing);
+	}
+
+	#[test]
+	fn test_day8_single_repair() {
+		use day8::Day8;
+		for size in [2, 3, 10, 100] {
+			for seed in 0..20 {
+				let input = generate(8, &config(size, seed))
+					.expect("Failed to generate input");
+				assert!(Day8
+					.part2(&Day8.parse(input.as_bytes()).unwrap())
+					.is_ok());
+				let lines = input.lines().collect::<Vec<_>>();
+				let repairs = (0..lines.len())
+					.filter(|&n| {
+						let flipped = match &lines[n][..3] {
+							"jmp" => lines[n].replacen("jmp", "nop", 1),
+							"nop" => lines[n].replacen("nop", "jmp", 1),
+							_ => return false,
+						};
+						let mut p = lines.clone();
+						p[n] = &flipped;
+						let p = p.join("\n") + "\n";
+						let p = Day8.parse(p.as_bytes()).unwrap();
+						Day8.part1(&p).is_err()
+					})
+					.count();
+				assert_eq!(1, repairs, "size {} seed {}", size, seed);
+			}
+		}
+	}
+}
@@ -0,0 +1,107 @@
+use aoc_common::Error;
+use aoc_gen::Config;
+use std::{
+	env, fs,
+	io::{self, Write},
+	path::PathBuf,
+	process,
+};
+
+const USAGE: &str = "Usage:
+  aoc-gen <day> [--size N] [--seed N] [--depth N] [--fan-out N] [-o FILE]";
+
+#[derive(Debug, PartialEq)]
+struct Options {
+	day: u32,
+	config: Config,
+	output: Option<PathBuf>,
+}
+
+fn usage(msg: &str) -> Error {
+	Error::Usage(format!("{}\n{}", msg, USAGE))
+}
+
+fn parse_value<T: std::str::FromStr>(
+	arg: &str,
+	value: &str,
+) -> Result<T, Error> {
+	value
+		.parse()
+		.map_err(|_| usage(&format!("Invalid value for {}: {}", arg, value)))
+}
+
+fn parse_options<I: IntoIterator<Item = String>>(
+	args: I,
+) -> Result<Options, Error> {
+	let mut args = args.into_iter();
+	let day = args.next().ok_or_else(|| usage("Missing day"))?;
+	let mut options = Options {
+		day: parse_value("day", &day)?,
+		config: Config::default(),
+		output: None,
+	};
+	while let Some(arg) = args.next() {
+		let value = args
+			.next()
+			.ok_or_else(|| usage(&format!("Missing value for {}", arg)))?;
+		match arg.as_str() {
+			"--size" => options.config.size = parse_value(&arg, &value)?,
+			"--seed" => options.config.seed = parse_value(&arg, &value)?,
+			"--depth" => options.config.depth = parse_value(&arg, &value)?,
+			"--fan-out" => options.config.fan_out = parse_value(&arg, &value)?,
+			"-o" | "--output" => options.output = Some(value.into()),
+			_ => return Err(usage(&format!("Unknown option: {}", arg))),
+		}
+	}
+	Ok(options)
+}
+
+fn main() {
+	let options = match parse_options(env::args().skip(1)) {
+		Ok(o) => o,
+		Err(e) => {
+			eprintln!("Error: {}", e);
+			process::exit(2);
+		}
+	};
+	aoc_common::run(|| {
+		let input = aoc_gen::generate(options.day, &options.config)?;
+		match &options.output {
+			Some(path) => fs::write(path, input).map_err(Error::Write),
+			None => io::stdout()
+				.lock()
+				.write_all(input.as_bytes())
+				.map_err(Error::Write),
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn args(s: &str) -> Vec<String> {
+		s.split_whitespace().map(String::from).collect()
+	}
+
+	#[test]
+	fn test_parse_options() {
+		let o = parse_options(args("7 --size 5000 --seed 3 --depth 10 -o x"))
+			.expect("Failed to parse options");
+		assert_eq!(7, o.day);
+		assert_eq!(5000, o.config.size);
+		assert_eq!(3, o.config.seed);
+		assert_eq!(10, o.config.depth);
+		assert_eq!(Config::default().fan_out, o.config.fan_out);
+		assert_eq!(Some(PathBuf::from("x")), o.output);
+	}
+
+	#[test]
+	fn test_parse_options_invalid() {
+		assert!(parse_options(args("")).is_err());
+		assert!(parse_options(args("seven")).is_err());
+		assert!(parse_options(args("1 --size")).is_err());
+		assert!(parse_options(args("1 --size many")).is_err());
+		assert!(parse_options(args("1 --colour red")).is_err());
+	}
+}
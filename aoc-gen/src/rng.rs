@@ -0,0 +1,74 @@
+//! Small seeded random number generator, so that generated inputs stay the
+//! same for a given seed across platforms and dependency updates.
+
+use std::ops::Range;
+
+/// SplitMix64 generator.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+	pub fn new(seed: u64) -> Self {
+		Rng(seed)
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	/// Uniform value in `range`, which must not be empty.
+	pub fn range(&mut self, range: Range<u64>) -> u64 {
+		range.start + self.next_u64() % (range.end - range.start)
+	}
+
+	/// Uniform index below `n`, which must not be zero.
+	pub fn index(&mut self, n: usize) -> usize {
+		self.range(0..n as u64) as usize
+	}
+
+	/// True with a chance of `percent` in 100.
+	pub fn chance(&mut self, percent: u64) -> bool {
+		self.range(0..100) < percent
+	}
+
+	pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+		&items[self.index(items.len())]
+	}
+
+	pub fn shuffle<T>(&mut self, items: &mut [T]) {
+		for i in (1..items.len()).rev() {
+			items.swap(i, self.index(i + 1));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_reproducible() {
+		let a = (0..4).map(|_| Rng::new(7).next_u64()).collect::<Vec<_>>();
+		assert!(a.iter().all(|&n| n == a[0]));
+		let mut rng = Rng::new(7);
+		assert_ne!(rng.next_u64(), rng.next_u64());
+	}
+
+	#[test]
+	fn test_range() {
+		let mut rng = Rng::new(1);
+		assert!((0..1000).all(|_| (3..5).contains(&rng.range(3..5))));
+	}
+
+	#[test]
+	fn test_shuffle() {
+		let mut items = (0..20).collect::<Vec<_>>();
+		Rng::new(3).shuffle(&mut items);
+		items.sort();
+		assert_eq!((0..20).collect::<Vec<_>>(), items);
+	}
+}
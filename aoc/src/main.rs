@@ -0,0 +1,327 @@
+use aoc_common::{
+	bench::{self, Measurement},
+	report::{self, Format, Record, Reporter},
+	Args, Error, Mode, ParseError, Solution,
+};
+use day1::Day1;
+use day2::Day2;
+use day3::Day3;
+use day4::Day4;
+use day5::Day5;
+use day6::Day6;
+use day7::Day7;
+use day8::Day8;
+use std::{
+	io,
+	path::{Path, PathBuf},
+	process,
+};
+
+const USAGE: &str = "Usage:
+  aoc run <day|all> [-i INPUT] [--inputs DIR] [-f json|tsv|text]
+                    [--strict|--lenient]
+  aoc bench <day|all> [-i INPUT] [--inputs DIR] [--warmup N] [--runs N]
+            [--save FILE] [--baseline FILE] [--threshold PERCENT]
+DIR holds dayN/input/input for each day, the workspace by default.";
+
+const DAYS: [u32; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+	Run,
+	Bench,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+	command: Command,
+	days: Vec<u32>,
+	inputs: PathBuf,
+	bench: bench::Config,
+	save: Option<PathBuf>,
+	baseline: Option<PathBuf>,
+	threshold: f64,
+}
+
+/// The days `s` names, a single day or `all`.
+fn parse_days(s: &str) -> Option<Vec<u32>> {
+	match s {
+		"all" => Some(DAYS.to_vec()),
+		_ => s.parse().ok().map(|d| vec![d]),
+	}
+}
+
+fn parse_options(args: &Args) -> Result<Options, Error> {
+	let mut free = aoc_common::Options::new(USAGE, &args.free);
+	let command = match free.next() {
+		Some("run") => Command::Run,
+		Some("bench") => Command::Bench,
+		Some(c) => return Err(free.error(format!("Unknown command: {}", c))),
+		None => return Err(free.error("Missing command")),
+	};
+	let day = free.next().ok_or_else(|| free.error("Missing day"))?;
+	let days = parse_days(day)
+		.ok_or_else(|| free.error(format!("Invalid day: {}", day)))?;
+	if days.len() > 1 && args.input.is_some() {
+		let msg = "Input file can only be given for a single day";
+		return Err(free.error(msg));
+	}
+	let mut options = Options {
+		command,
+		days,
+		inputs: default_inputs(),
+		bench: bench::Config::default(),
+		save: None,
+		baseline: None,
+		threshold: 10.0,
+	};
+	while let Some(arg) = free.next() {
+		match arg {
+			"--inputs" => options.inputs = free.value(arg)?.into(),
+			"--warmup" => options.bench.warmup = free.parse(arg)?,
+			"--runs" => options.bench.runs = free.parse(arg)?,
+			"--save" => options.save = Some(free.value(arg)?.into()),
+			"--baseline" => options.baseline = Some(free.value(arg)?.into()),
+			"--threshold" => options.threshold = free.parse(arg)?,
+			_ => return Err(free.unknown(arg)),
+		}
+	}
+	Ok(options)
+}
+
+/// The workspace the runner was built in, so that the inputs are found
+/// from any working directory.
+fn default_inputs() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+fn input_path(dir: &Path, day: u32) -> PathBuf {
+	dir.join(format!("day{}", day)).join("input").join("input")
+}
+
+/// Action run on the solution of a day, see `dispatch`.
+trait Visit {
+	type Output;
+
+	fn visit<S: Solution + Default>(
+		&mut self,
+		input: &[u8],
+	) -> Result<Self::Output, Error>;
+}
+
+struct Measure(Mode);
+
+impl Visit for Measure {
+	type Output = ([Record; 2], Vec<ParseError>);
+
+	fn visit<S: Solution + Default>(
+		&mut self,
+		input: &[u8],
+	) -> Result<Self::Output, Error> {
+		report::measure(&S::default(), input, self.0)
+	}
+}
+
+struct Bench(bench::Config);
+
+impl Visit for Bench {
+	type Output = Vec<Measurement>;
+
+	fn visit<S: Solution + Default>(
+		&mut self,
+		input: &[u8],
+	) -> Result<Self::Output, Error> {
+		bench::bench(&S::default(), input, &self.0)
+	}
+}
+
+fn dispatch<V: Visit>(
+	day: u32,
+	input: &Path,
+	v: &mut V,
+) -> Result<V::Output, Error> {
+	if !DAYS.contains(&day) {
+		return Err(Error::Usage(format!("Unknown day: {}", day)));
+	}
+	let buffer = aoc_common::load_input(Some(input))?;
+	match day {
+		1 => v.visit::<Day1>(&buffer),
+		2 => v.visit::<Day2>(&buffer),
+		3 => v.visit::<Day3>(&buffer),
+		4 => v.visit::<Day4>(&buffer),
+		5 => v.visit::<Day5>(&buffer),
+		6 => v.visit::<Day6>(&buffer),
+		7 => v.visit::<Day7>(&buffer),
+		8 => v.visit::<Day8>(&buffer),
+		_ => Err(Error::Usage(format!("Unknown day: {}", day))),
+	}
+}
+
+fn run(args: &Args, options: &Options) -> bool {
+	let mut reporter = Reporter::new(args.format);
+	let stdout = io::stdout();
+	let mut w = stdout.lock();
+	let mut ok = true;
+	for &day in &options.days {
+		if reporter.format() == Format::Text {
+			println!("Day {}", day);
+		}
+		let input = args
+			.input
+			.clone()
+			.unwrap_or_else(|| input_path(&options.inputs, day));
+		let res = dispatch(day, &input, &mut Measure(args.mode)).and_then(
+			|(records, warnings)| {
+				for warning in &warnings {
+					eprintln!("Warning: day {}: {}", day, warning);
+				}
+				records
+					.iter()
+					.try_for_each(|r| reporter.write(&mut w, r))
+					.map_err(Error::Write)
+			},
+		);
+		if let Err(e) = res {
+			eprintln!("Error: day {}: {}", day, e);
+			ok = false;
+		}
+	}
+	ok
+}
+
+fn bench(args: &Args, options: &Options) -> Result<bool, Error> {
+	let mut ok = true;
+	let mut measurements = vec![];
+	for &day in &options.days {
+		let input = args
+			.input
+			.clone()
+			.unwrap_or_else(|| input_path(&options.inputs, day));
+		match dispatch(day, &input, &mut Bench(options.bench)) {
+			Ok(mut m) => measurements.append(&mut m),
+			Err(e) => {
+				eprintln!("Error: day {}: {}", day, e);
+				ok = false;
+			}
+		}
+	}
+
+	let baseline = match &options.baseline {
+		Some(path) => bench::load_baseline(path)?,
+		None => vec![],
+	};
+	let threshold = options.threshold / 100.0;
+	let comparisons = bench::compare(&measurements, &baseline);
+	bench::write_table(&mut io::stdout().lock(), &comparisons, threshold)
+		.map_err(Error::Write)?;
+	if let Some(path) = &options.save {
+		bench::save_baseline(path, &measurements)?;
+	}
+
+	let regressions = comparisons
+		.iter()
+		.filter(|c| c.is_regression(threshold))
+		.count();
+	if regressions > 0 {
+		eprintln!(
+			"{} regression(s) above {}% against baseline",
+			regressions, options.threshold
+		);
+	}
+	Ok(ok && regressions == 0)
+}
+
+fn main() {
+	let (args, options) = match Args::from_env()
+		.and_then(|args| parse_options(&args).map(|o| (args, o)))
+	{
+		Ok(r) => r,
+		Err(e) => {
+			eprintln!("Error: {}", e);
+			process::exit(2);
+		}
+	};
+	let ok = match options.command {
+		Command::Run => run(&args, &options),
+		Command::Bench => bench(&args, &options).unwrap_or_else(|e| {
+			eprintln!("Error: {}", e);
+			false
+		}),
+	};
+	if !ok {
+		process::exit(1);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn args(s: &str) -> Args {
+		Args::parse(s.split_whitespace().map(String::from))
+			.expect("Failed to parse args")
+	}
+
+	#[test]
+	fn test_input_path() {
+		assert_eq!(
+			PathBuf::from("./day7/input/input"),
+			input_path(Path::new("."), 7)
+		);
+	}
+
+	#[test]
+	fn test_parse_days() {
+		assert_eq!(vec![7], parse_days("7").expect("Failed to parse day"));
+		assert_eq!(DAYS.to_vec(), parse_days("all").expect("Failed to parse"));
+		assert!(parse_days("seven").is_none());
+	}
+
+	#[test]
+	fn test_parse_options_run() {
+		let o = parse_options(&args("run 7 --inputs dir"))
+			.expect("Failed to parse options");
+		assert_eq!(Command::Run, o.command);
+		assert_eq!(vec![7], o.days);
+		assert_eq!(PathBuf::from("dir"), o.inputs);
+	}
+
+	#[test]
+	fn test_default_inputs() {
+		let o = parse_options(&args("run all")).expect("Failed to parse");
+		for day in o.days {
+			assert!(input_path(&o.inputs, day).is_file());
+		}
+	}
+
+	#[test]
+	fn test_parse_options_bench() {
+		let o = parse_options(&args(
+			"bench all --runs 5 --warmup 1 --baseline b --threshold 25",
+		))
+		.expect("Failed to parse options");
+		assert_eq!(Command::Bench, o.command);
+		assert_eq!(DAYS.to_vec(), o.days);
+		assert_eq!(bench::Config { warmup: 1, runs: 5 }, o.bench);
+		assert_eq!(Some(PathBuf::from("b")), o.baseline);
+		assert_eq!(25.0, o.threshold);
+	}
+
+	#[test]
+	fn test_parse_options_invalid() {
+		assert!(parse_options(&args("")).is_err());
+		assert!(parse_options(&args("walk 7")).is_err());
+		assert!(parse_options(&args("run")).is_err());
+		assert!(parse_options(&args("run all -i input")).is_err());
+		assert!(parse_options(&args("bench 7 --runs")).is_err());
+		assert!(parse_options(&args("bench 7 --runs many")).is_err());
+	}
+
+	#[test]
+	fn test_dispatch_unknown_day() {
+		assert!(matches!(
+			dispatch(9, Path::new("missing"), &mut Measure(Mode::Strict)),
+			Err(Error::Usage(_))
+		));
+	}
+}
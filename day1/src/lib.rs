@@ -0,0 +1,202 @@
+//! Day 1: Report Repair.
+//!
+//! Finds the entries of an expense report that sum to a given total and
+//! multiplies them.
+
+use aoc_common::{
+	parse::{self, records, IResult},
+	Answer, Error, ParseError, Part, Solution,
+};
+use nom::{
+	character::complete::{digit1, newline},
+	combinator::map_res,
+	error::context,
+};
+use std::str;
+
+/// A single expense report entry.
+pub fn number(i: &[u8]) -> IResult<&[u8], u64> {
+	context(
+		"number",
+		map_res(map_res(digit1, str::from_utf8), str::parse),
+	)(i)
+}
+
+/// Expense report entries, one per line.
+pub fn parse_numbers(i: &[u8]) -> IResult<&[u8], Vec<u64>> {
+	records(newline, number)(i)
+}
+
+/// Product of the first two entries of `input` that add up to `sum`,
+/// failing if it overflows.
+pub fn find_day1_part1_num(
+	input: &[u64],
+	sum: u64,
+) -> Result<Option<u64>, Error> {
+	let found = input.iter().enumerate().find_map(|(i, &m)| {
+		input
+			.iter()
+			.skip(i)
+			.find(|&&n| m.checked_add(n) == Some(sum))
+			.map(|&n| (m, n))
+	});
+	found
+		.map(|(m, n)| m.checked_mul(n).ok_or_else(overflow))
+		.transpose()
+}
+
+/// Product of the first three entries of `input` that add up to `sum`,
+/// failing if it overflows.
+pub fn find_day1_part2_num(
+	input: &[u64],
+	sum: u64,
+) -> Result<Option<u64>, Error> {
+	let found = input.iter().enumerate().find_map(|(i, &m)| {
+		input.iter().enumerate().skip(i).find_map(|(j, &n)| {
+			input
+				.iter()
+				.skip(j)
+				.find(|&&o| {
+					m.checked_add(n).and_then(|s| s.checked_add(o)) == Some(sum)
+				})
+				.map(|&o| (m, n, o))
+		})
+	});
+	found
+		.map(|(m, n, o)| {
+			m.checked_mul(n)
+				.and_then(|p| p.checked_mul(o))
+				.ok_or_else(overflow)
+		})
+		.transpose()
+}
+
+fn overflow() -> Error {
+	Error::Invalid("entry product overflows".into())
+}
+
+/// Solution for day 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Day1 {
+	/// Total the entries must add up to, 2020 by default.
+	pub sum: u64,
+}
+
+impl Default for Day1 {
+	fn default() -> Self {
+		Day1 { sum: 2020 }
+	}
+}
+
+impl Solution for Day1 {
+	const DAY: u32 = 1;
+
+	type Input<'a> = Vec<u64>;
+
+	fn parse<'a>(&self, input: &'a [u8]) -> Result<Self::Input<'a>, Error> {
+		parse::parse_complete(parse_numbers, input)
+	}
+
+	fn parse_lenient<'a>(
+		&self,
+		input: &'a [u8],
+	) -> Result<(Self::Input<'a>, Vec<ParseError>), Error> {
+		Ok(parse::lenient(number, b"\n", input))
+	}
+
+	fn part1(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		find_day1_part1_num(input, self.sum)?
+			.map(Answer::new)
+			.ok_or(Error::NoAnswer(Part::Part1))
+	}
+
+	fn part2(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		find_day1_part2_num(input, self.sum)?
+			.map(Answer::new)
+			.ok_or(Error::NoAnswer(Part::Part2))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	#[test]
+	fn test_parse_numbers() {
+		let input = parse::parse_complete(parse_numbers, b"0\n1\n2\n3\n4\n")
+			.expect("Failed to parse");
+		assert_eq!(input, vec![0, 1, 2, 3, 4]);
+	}
+
+	#[test]
+	fn test_parse_numbers_error() {
+		match Day1::default().parse(b"1\n2x\n3\n") {
+			Err(Error::Parse(e)) => assert_eq!((2, 2), (e.line, e.column)),
+			r => panic!("Unexpected result: {:?}", r),
+		}
+	}
+
+	#[test]
+	fn test_product_overflow() {
+		let day = Day1 { sum: u64::MAX };
+		let input = vec![u64::MAX / 2, u64::MAX / 2 + 1];
+		assert!(matches!(day.part1(&input), Err(Error::Invalid(_))));
+		let input = vec![u64::MAX / 2, u64::MAX / 2, 1];
+		assert!(matches!(day.part2(&input), Err(Error::Invalid(_))));
+		// The product only overflows for the sum looked for.
+		let day = Day1 { sum: 3 };
+		assert_eq!(2, day.part1(&vec![u64::MAX, 1, 2]).unwrap().value);
+	}
+
+	proptest! {
+		#[test]
+		fn prop_part1_matches_pairs(
+			input in prop::collection::vec(0_u64..100, 0..20),
+			sum in 0_u64..200,
+		) {
+			let pairs = (0..input.len())
+				.flat_map(|i| (i..input.len()).map(move |j| (i, j)))
+				.filter(|&(i, j)| input[i] + input[j] == sum)
+				.map(|(i, j)| input[i] * input[j])
+				.collect::<Vec<_>>();
+			match find_day1_part1_num(&input, sum).unwrap() {
+				Some(r) => prop_assert!(pairs.contains(&r)),
+				None => prop_assert!(pairs.is_empty()),
+			}
+		}
+
+		#[test]
+		fn prop_part2_matches_triples(
+			input in prop::collection::vec(0_u64..100, 0..12),
+			sum in 0_u64..300,
+		) {
+			let n = input.len();
+			let triples = (0..n)
+				.flat_map(|i| {
+					(i..n).flat_map(move |j| (j..n).map(move |k| (i, j, k)))
+				})
+				.filter(|&(i, j, k)| input[i] + input[j] + input[k] == sum)
+				.map(|(i, j, k)| input[i] * input[j] * input[k])
+				.collect::<Vec<_>>();
+			match find_day1_part2_num(&input, sum).unwrap() {
+				Some(r) => prop_assert!(triples.contains(&r)),
+				None => prop_assert!(triples.is_empty()),
+			}
+		}
+
+		#[test]
+		fn prop_parse_round_trip(
+			input in prop::collection::vec(any::<u64>(), 1..20),
+		) {
+			let text = input
+				.iter()
+				.map(|n| format!("{}\n", n))
+				.collect::<String>();
+			let r = Day1::default()
+				.parse(text.as_bytes())
+				.expect("Failed to parse");
+			prop_assert_eq!(input, r);
+		}
+	}
+}
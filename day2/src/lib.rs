@@ -0,0 +1,306 @@
+//! Day 2: Password Philosophy.
+//!
+//! Checks passwords against the corporate policy they were set under.
+
+use aoc_common::{
+	parse::{self, records, IResult},
+	Answer, Error, ParseError, Solution,
+};
+use nom::{
+	bytes::complete::tag,
+	character::complete::{alphanumeric1, anychar, digit1, newline, space0},
+	combinator::{map, map_res},
+	error::context,
+	sequence::{preceded, terminated, tuple},
+};
+use std::{fmt, str};
+
+/// A password with the policy it was set under, written as
+/// `1-3 a: abcde`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+	/// Second number of the policy.
+	pub upper: u64,
+	/// First number of the policy.
+	pub lower: u64,
+	/// Letter the policy applies to.
+	pub char: char,
+	pub password: String,
+}
+
+impl fmt::Display for Entry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{}-{} {}: {}",
+			self.lower, self.upper, self.char, self.password
+		)
+	}
+}
+
+impl str::FromStr for Entry {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		parse::parse_complete(entry, s.as_bytes())
+	}
+}
+
+fn number1(i: &[u8]) -> IResult<&[u8], u64> {
+	context(
+		"number",
+		map_res(map_res(digit1, str::from_utf8), str::parse),
+	)(i)
+}
+
+fn password1(i: &[u8]) -> IResult<&[u8], &str> {
+	context("password", map_res(alphanumeric1, str::from_utf8))(i)
+}
+
+/// A single password entry.
+pub fn entry(i: &[u8]) -> IResult<&[u8], Entry> {
+	map(
+		tuple((
+			terminated(number1, context("\"-\"", tag("-"))),
+			terminated(number1, space0),
+			terminated(context("letter", anychar), context("\":\"", tag(":"))),
+			preceded(space0, password1),
+		)),
+		|(l, u, c, p)| Entry {
+			lower: l,
+			upper: u,
+			char: c,
+			password: p.into(),
+		},
+	)(i)
+}
+
+/// Password entries, one per line.
+pub fn entries(i: &[u8]) -> IResult<&[u8], Vec<Entry>> {
+	records(newline, entry)(i)
+}
+
+/// Whether the password contains the letter between `lower` and `upper`
+/// times.
+pub fn valid_day2_part1(entry: &Entry) -> bool {
+	let char_count = entry.password.chars().fold(0, |acc, c| {
+		if c == entry.char {
+			acc + 1
+		} else {
+			acc
+		}
+	});
+	char_count >= entry.lower && char_count <= entry.upper
+}
+
+/// Whether exactly one of the positions `lower` and `upper`, counting
+/// from 1, holds the letter.
+pub fn valid_day2_part2(entry: &Entry) -> bool {
+	entry
+		.password
+		.char_indices()
+		.filter(|&(i, _)| {
+			entry.lower as usize == i + 1 || entry.upper as usize == i + 1
+		})
+		.filter(|&(_, c)| c == entry.char)
+		.count()
+		== 1
+}
+
+/// Solution for day 2.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Day2;
+
+impl Solution for Day2 {
+	const DAY: u32 = 2;
+
+	type Input<'a> = Vec<Entry>;
+
+	fn parse<'a>(&self, input: &'a [u8]) -> Result<Self::Input<'a>, Error> {
+		parse::parse_complete(entries, input)
+	}
+
+	fn parse_lenient<'a>(
+		&self,
+		input: &'a [u8],
+	) -> Result<(Self::Input<'a>, Vec<ParseError>), Error> {
+		Ok(parse::lenient(entry, b"\n", input))
+	}
+
+	fn part1(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		Ok(Answer::new(
+			input.iter().filter(|e| valid_day2_part1(e)).count(),
+		))
+	}
+
+	fn part2(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		Ok(Answer::new(
+			input.iter().filter(|e| valid_day2_part2(e)).count(),
+		))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	#[test]
+	fn entry_parse() {
+		let input = b"1-3 a: aaa";
+		let e = Entry {
+			lower: 1,
+			upper: 3,
+			char: 'a',
+			password: "aaa".into(),
+		};
+		let (_, r) = entry(input).unwrap();
+		assert_eq!(r, e);
+	}
+
+	#[test]
+	fn test_parse_lenient() {
+		let (r, errors) = Day2
+			.parse_lenient(b"1-3 a: aaa\n1 a: a\n2-3 b: bb\n")
+			.expect("Failed to parse");
+		assert_eq!(2, r.len());
+		assert_eq!(1, errors.len());
+		assert_eq!(
+			"line 2, col 2: expected \"-\", found \" a: a\"",
+			errors[0].to_string()
+		);
+	}
+
+	#[test]
+	fn test_valid_day2_trivial() {
+		let entry = Entry {
+			lower: 1,
+			upper: 1,
+			char: 'a',
+			password: "a".into(),
+		};
+		assert!(valid_day2_part1(&entry));
+	}
+
+	#[test]
+	fn test_valid_day2_between() {
+		let entry = Entry {
+			lower: 1,
+			upper: 3,
+			char: 'a',
+			password: "aa".into(),
+		};
+		assert!(valid_day2_part1(&entry));
+	}
+
+	#[test]
+	fn test_valid_day2_no_matching() {
+		let entry = Entry {
+			lower: 1,
+			upper: 1,
+			char: 'a',
+			password: "b".into(),
+		};
+		assert!(!valid_day2_part1(&entry));
+	}
+
+	#[test]
+	fn test_valid_day2_too_many() {
+		let entry = Entry {
+			lower: 1,
+			upper: 1,
+			char: 'a',
+			password: "aa".into(),
+		};
+		assert!(!valid_day2_part1(&entry));
+	}
+
+	#[test]
+	fn test_valid_day2_too_few() {
+		let entry = Entry {
+			lower: 3,
+			upper: 3,
+			char: 'a',
+			password: "aa".into(),
+		};
+		assert!(!valid_day2_part1(&entry));
+	}
+
+	#[test]
+	fn test_valid_day2_part2_a() {
+		let entry = Entry {
+			lower: 1,
+			upper: 3,
+			char: 'a',
+			password: "abcde".into(),
+		};
+		assert!(valid_day2_part2(&entry));
+	}
+
+	#[test]
+	fn test_valid_day2_part2_b() {
+		let entry = Entry {
+			lower: 1,
+			upper: 3,
+			char: 'b',
+			password: "cdefg".into(),
+		};
+		assert!(!valid_day2_part2(&entry));
+	}
+
+	#[test]
+	fn test_valid_day2_part2_c() {
+		let entry = Entry {
+			lower: 2,
+			upper: 9,
+			char: 'c',
+			password: "ccccccccc".into(),
+		};
+		assert!(!valid_day2_part2(&entry));
+	}
+
+	fn entries() -> impl Strategy<Value = Entry> {
+		(1_u64..20, 1_u64..20, "[a-z]", "[a-zA-Z0-9]{1,20}").prop_map(
+			|(lower, extra, c, password)| Entry {
+				lower,
+				upper: lower + extra,
+				char: c.chars().next().unwrap(),
+				password,
+			},
+		)
+	}
+
+	proptest! {
+		#[test]
+		fn prop_entry_round_trip(e in entries()) {
+			let text =
+				format!("{}-{} {}: {}", e.lower, e.upper, e.char, e.password);
+			let (rest, r) = entry(text.as_bytes()).expect("Failed to parse");
+			prop_assert!(rest.is_empty());
+			prop_assert_eq!(e, r);
+		}
+
+		#[test]
+		fn prop_entry_display_round_trip(e in entries()) {
+			let r = e.to_string().parse::<Entry>().expect("Failed to parse");
+			prop_assert_eq!(e, r);
+		}
+
+		#[test]
+		fn prop_valid_part1_counts(e in entries()) {
+			let count = e.password.matches(e.char).count() as u64;
+			prop_assert_eq!(
+				e.lower <= count && count <= e.upper,
+				valid_day2_part1(&e)
+			);
+		}
+
+		#[test]
+		fn prop_valid_part2_positions(e in entries()) {
+			let at = |n: u64| {
+				e.password.chars().nth(n as usize - 1) == Some(e.char)
+			};
+			prop_assert_eq!(at(e.lower) != at(e.upper), valid_day2_part2(&e));
+		}
+	}
+}
@@ -0,0 +1,353 @@
+//! Day 3: Toboggan Trajectory.
+//!
+//! Counts the trees hit when sliding down a map that repeats to the right.
+
+use aoc_common::{
+	parse::{self, records, IResult},
+	Answer, Error, ParseError, Solution,
+};
+use nom::{
+	branch::alt,
+	bytes::complete::tag,
+	character::complete::{multispace0, newline},
+	combinator::map,
+	error::context,
+	multi::many1,
+	sequence::preceded,
+};
+use std::{fmt, str};
+
+/// A single square of the map, `.` or `#`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terrain {
+	Open,
+	Tree,
+}
+
+impl fmt::Display for Terrain {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Terrain::Open => write!(f, "."),
+			Terrain::Tree => write!(f, "#"),
+		}
+	}
+}
+
+/// The map as rows from top to bottom. Each row repeats to the right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Map {
+	Map(Vec<Vec<Terrain>>),
+}
+
+impl fmt::Display for Map {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Map::Map(map) = self;
+		for line in map {
+			for t in line {
+				write!(f, "{}", t)?;
+			}
+			writeln!(f)?;
+		}
+		Ok(())
+	}
+}
+
+impl str::FromStr for Map {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Day3.parse(s.as_bytes())
+	}
+}
+
+/// A single square of the map.
+pub fn parse_terrain(i: &[u8]) -> IResult<&[u8], Terrain> {
+	context(
+		"\".\" or \"#\"",
+		alt((
+			map(tag("."), |_| Terrain::Open),
+			map(tag("#"), |_| Terrain::Tree),
+		)),
+	)(i)
+}
+
+/// A row of the map.
+pub fn parse_map_line(i: &[u8]) -> IResult<&[u8], Vec<Terrain>> {
+	preceded(multispace0, many1(parse_terrain))(i)
+}
+
+/// The map, one row per line. Rows are not checked to have the same
+/// width, see [`Map::validate`].
+pub fn parse_map(i: &[u8]) -> IResult<&[u8], Map> {
+	map(records(newline, parse_map_line), Map::Map)(i)
+}
+
+impl Map {
+	/// Terrain at column `x` of row `y`, or `None` below the map.
+	pub fn lookup(&self, x: usize, y: usize) -> Option<&Terrain> {
+		let Map::Map(map) = self;
+		map.get(y).and_then(|line| line.get(x % line.len()))
+	}
+
+	/// Number of rows.
+	pub fn height(&self) -> usize {
+		let Map::Map(map) = self;
+		map.len()
+	}
+
+	/// Check that the map has rows and all are of the same width.
+	pub fn validate(&self) -> Result<(), Error> {
+		let Map::Map(map) = self;
+		match map.first() {
+			Some(first) if map.iter().all(|l| l.len() == first.len()) => Ok(()),
+			Some(_) => Err(Error::Invalid("map lines differ in width".into())),
+			None => Err(Error::Invalid("map is empty".into())),
+		}
+	}
+}
+
+/// Trees hit going from the top left corner `right_step` columns right
+/// and `down_step` rows down at a time until below the map.
+pub fn count_day3(map: &Map, right_step: usize, down_step: usize) -> u64 {
+	(0..map.height()).step_by(down_step).fold(0, |acc, y| {
+		let x = match y {
+			0 => 0,
+			_ => (y / down_step) * right_step,
+		};
+		match map.lookup(x, y) {
+			Some(Terrain::Tree) => acc + 1,
+			_ => acc,
+		}
+	})
+}
+
+/// Solution for day 3.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Day3;
+
+impl Solution for Day3 {
+	const DAY: u32 = 3;
+
+	type Input<'a> = Map;
+
+	fn parse<'a>(&self, input: &'a [u8]) -> Result<Self::Input<'a>, Error> {
+		let map = parse::parse_complete(parse_map, input)?;
+		map.validate()?;
+		Ok(map)
+	}
+
+	fn parse_lenient<'a>(
+		&self,
+		input: &'a [u8],
+	) -> Result<(Self::Input<'a>, Vec<ParseError>), Error> {
+		let (lines, errors) = parse::lenient(parse_map_line, b"\n", input);
+		let map = Map::Map(lines);
+		map.validate()?;
+		Ok((map, errors))
+	}
+
+	fn part1(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		Ok(Answer::new(count_day3(input, 3, 1)))
+	}
+
+	fn part2(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		let answer_slope1 = count_day3(input, 1, 1);
+		let answer_slope3 = count_day3(input, 3, 1);
+		let answer_slope5 = count_day3(input, 5, 1);
+		let answer_slope7 = count_day3(input, 7, 1);
+		let answer_down2 = count_day3(input, 1, 2);
+		let answer =
+			[answer_slope3, answer_slope5, answer_slope7, answer_down2]
+				.iter()
+				.try_fold(answer_slope1, |acc, &n| acc.checked_mul(n))
+				.ok_or_else(|| {
+					Error::Invalid("tree count product overflows".into())
+				})?;
+
+		Ok(Answer::new(answer)
+			.with_detail("slope1", answer_slope1)
+			.with_detail("slope3", answer_slope3)
+			.with_detail("slope5", answer_slope5)
+			.with_detail("slope7", answer_slope7)
+			.with_detail("down2", answer_down2))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	#[test]
+	fn test_parse_terrain_open() {
+		let (rest, r) = parse_terrain(b".").unwrap();
+		assert_eq!(0, rest.len());
+		assert_eq!(Terrain::Open, r);
+	}
+
+	#[test]
+	fn test_parse_terrain_tree() {
+		let (rest, r) = parse_terrain(b"#").unwrap();
+		assert_eq!(0, rest.len());
+		assert_eq!(Terrain::Tree, r);
+	}
+
+	#[test]
+	fn test_parse_map_line() {
+		let (rest, r) = parse_map_line(b".#.").unwrap();
+		assert_eq!(0, rest.len());
+		assert_eq!(vec![Terrain::Open, Terrain::Tree, Terrain::Open], r);
+	}
+
+	#[test]
+	fn test_parse_map() {
+		let (rest, Map::Map(r)) = parse_map(b".\n#\n.").unwrap();
+		assert_eq!(0, rest.len());
+		assert_eq!(
+			vec![
+				vec![Terrain::Open],
+				vec![Terrain::Tree],
+				vec![Terrain::Open]
+			],
+			r
+		);
+	}
+
+	#[test]
+	fn test_count_day3_open() {
+		let map = Map::Map(vec![vec![Terrain::Open]]);
+		let count = count_day3(&map, 3, 1);
+		assert_eq!(0, count);
+	}
+
+	#[test]
+	fn test_count_day3_tree() {
+		let map = Map::Map(vec![vec![Terrain::Tree]]);
+		let count = count_day3(&map, 3, 1);
+		assert_eq!(1, count);
+	}
+
+	#[test]
+	fn test_count_day3_tree_open_tree() {
+		let map = Map::Map(vec![
+			vec![Terrain::Tree],
+			vec![Terrain::Open],
+			vec![Terrain::Tree],
+		]);
+		let count = count_day3(&map, 3, 1);
+		assert_eq!(2, count);
+	}
+
+	#[test]
+	fn test_count_day3_right1() {
+		let map = Map::Map(vec![
+			vec![Terrain::Open, Terrain::Open, Terrain::Open, Terrain::Open],
+			vec![Terrain::Open, Terrain::Tree, Terrain::Open, Terrain::Open],
+			vec![Terrain::Open, Terrain::Open, Terrain::Tree, Terrain::Open],
+			vec![Terrain::Open, Terrain::Open, Terrain::Open, Terrain::Tree],
+			vec![Terrain::Tree, Terrain::Open, Terrain::Open, Terrain::Open],
+		]);
+		let count = count_day3(&map, 1, 1);
+		assert_eq!(4, count);
+	}
+
+	#[test]
+	fn test_count_day3_down2() {
+		let map = Map::Map(vec![
+			vec![Terrain::Open, Terrain::Open, Terrain::Open, Terrain::Open],
+			vec![Terrain::Open, Terrain::Open, Terrain::Open, Terrain::Open],
+			vec![Terrain::Open, Terrain::Tree, Terrain::Open, Terrain::Open],
+			vec![Terrain::Open, Terrain::Open, Terrain::Open, Terrain::Open],
+			vec![Terrain::Open, Terrain::Open, Terrain::Tree, Terrain::Open],
+		]);
+		let count = count_day3(&map, 1, 2);
+		assert_eq!(2, count);
+	}
+
+	#[test]
+	fn test_count_day3_wrap5() {
+		let map = Map::Map(vec![
+			vec![Terrain::Open, Terrain::Open, Terrain::Open, Terrain::Open],
+			vec![Terrain::Open, Terrain::Tree, Terrain::Open, Terrain::Open],
+			vec![Terrain::Open, Terrain::Open, Terrain::Tree, Terrain::Open],
+			vec![Terrain::Open, Terrain::Open, Terrain::Open, Terrain::Tree],
+		]);
+		let count = count_day3(&map, 5, 1);
+		assert_eq!(3, count);
+	}
+
+	#[test]
+	fn test_count_day3() {
+		let map = Map::Map(vec![
+			vec![Terrain::Tree, Terrain::Open, Terrain::Open, Terrain::Open],
+			vec![Terrain::Open, Terrain::Open, Terrain::Open, Terrain::Tree],
+		]);
+		let count = count_day3(&map, 3, 1);
+		assert_eq!(2, count);
+	}
+
+	fn maps() -> impl Strategy<Value = Vec<Vec<bool>>> {
+		(1_usize..12, 1_usize..40).prop_flat_map(|(width, height)| {
+			prop::collection::vec(
+				prop::collection::vec(any::<bool>(), width),
+				height,
+			)
+		})
+	}
+
+	fn to_map(trees: &[Vec<bool>]) -> Map {
+		Map::Map(
+			trees
+				.iter()
+				.map(|l| {
+					l.iter()
+						.map(|&t| if t { Terrain::Tree } else { Terrain::Open })
+						.collect()
+				})
+				.collect(),
+		)
+	}
+
+	proptest! {
+		#[test]
+		fn prop_count_matches_walk(
+			trees in maps(),
+			right in 0_usize..8,
+			down in 1_usize..4,
+		) {
+			let width = trees[0].len();
+			let expected = (0..)
+				.map(|n| (n * right, n * down))
+				.take_while(|&(_, y)| y < trees.len())
+				.filter(|&(x, y)| trees[y][x % width])
+				.count() as u64;
+			prop_assert_eq!(expected, count_day3(&to_map(&trees), right, down));
+		}
+
+		#[test]
+		fn prop_parse_round_trip(trees in maps()) {
+			let text = trees
+				.iter()
+				.map(|l| {
+					let mut line = l
+						.iter()
+						.map(|&t| if t { '#' } else { '.' })
+						.collect::<String>();
+					line.push('\n');
+					line
+				})
+				.collect::<String>();
+			let Map::Map(r) =
+				Day3.parse(text.as_bytes()).expect("Failed to parse");
+			let Map::Map(expected) = to_map(&trees);
+			prop_assert_eq!(expected, r);
+		}
+
+		#[test]
+		fn prop_display_round_trip(trees in maps()) {
+			let map = to_map(&trees);
+			let r = map.to_string().parse::<Map>().expect("Failed to parse");
+			prop_assert_eq!(map, r);
+		}
+	}
+}
@@ -0,0 +1,551 @@
+//! Day 4: Passport Processing.
+//!
+//! Checks passport batches for the required fields and their values.
+//! Passports borrow their fields from the input, so they are parsed with
+//! [`parse_passports`] rather than `FromStr`.
+
+use aoc_common::{
+	parse::{self, records, IResult},
+	Answer, Error, ParseError, Solution,
+};
+use nom::{
+	branch::alt,
+	bytes::complete::{tag, take_while, take_while_m_n},
+	character::{
+		complete::{alphanumeric1, newline, none_of, one_of},
+		is_digit, is_hex_digit,
+	},
+	combinator::{map, map_res, recognize},
+	error::context,
+	multi::{many1, separated_list1},
+	sequence::{pair, terminated},
+};
+use std::{fmt, str};
+
+/// A passport field as name and value, written as `name:value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field<'a> {
+	Field(&'a [u8], &'a [u8]),
+}
+
+impl fmt::Display for Field<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Field::Field(k, v) = self;
+		write!(
+			f,
+			"{}:{}",
+			String::from_utf8_lossy(k),
+			String::from_utf8_lossy(v)
+		)
+	}
+}
+
+/// A passport as its fields in input order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Passport<'a> {
+	Passport(Vec<Field<'a>>),
+}
+
+impl fmt::Display for Passport<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Passport::Passport(fields) = self;
+		for (n, field) in fields.iter().enumerate() {
+			if n > 0 {
+				write!(f, " ")?;
+			}
+			write!(f, "{}", field)?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, PartialEq)]
+enum Unit {
+	Cm,
+	In,
+}
+
+/// A single `name:value` field.
+pub fn parse_field<'a>(i: &'a [u8]) -> IResult<&'a [u8], Field<'a>> {
+	map(
+		pair(
+			terminated(
+				context("field name", alphanumeric1),
+				context("\":\"", tag(":")),
+			),
+			context("field value", recognize(many1(none_of(" \t\n")))),
+		),
+		|(k, v)| Field::Field(k, v),
+	)(i)
+}
+
+/// A passport with fields separated by spaces or single newlines.
+pub fn parse_passport<'a>(i: &'a [u8]) -> IResult<&'a [u8], Passport<'a>> {
+	map(
+		separated_list1(alt((one_of(" \t"), newline)), parse_field),
+		Passport::Passport,
+	)(i)
+}
+
+/// Passports separated by blank lines.
+pub fn parse_passports<'a>(
+	i: &'a [u8],
+) -> IResult<&'a [u8], Vec<Passport<'a>>> {
+	records(pair(newline, newline), parse_passport)(i)
+}
+
+fn parse_year(i: &[u8]) -> IResult<&[u8], u64> {
+	map_res(
+		map_res(take_while_m_n(4, 4, is_digit), |r| str::from_utf8(r)),
+		str::parse,
+	)(i)
+}
+
+fn parse_length(i: &[u8]) -> IResult<&[u8], (u64, Unit)> {
+	pair(
+		map_res(
+			map_res(recognize(take_while(is_digit)), |r| str::from_utf8(r)),
+			str::parse,
+		),
+		alt((map(tag("cm"), |_| Unit::Cm), map(tag("in"), |_| Unit::In))),
+	)(i)
+}
+
+fn parse_color(i: &[u8]) -> IResult<&[u8], &str> {
+	map_res(
+		pair(tag("#"), take_while_m_n(6, 6, is_hex_digit)),
+		|(_, r)| str::from_utf8(r),
+	)(i)
+}
+
+fn parse_digit_count(i: &[u8]) -> IResult<&[u8], usize> {
+	map_res(take_while(is_digit), |r| str::from_utf8(r).map(|s| s.len()))(i)
+}
+
+struct Validation {
+	has_byr: bool,
+	has_iyr: bool,
+	has_eyr: bool,
+	has_hgt: bool,
+	has_hcl: bool,
+	has_ecl: bool,
+	has_pid: bool,
+}
+
+impl Passport<'_> {
+	/// Whether all required fields are present. `cid` is optional.
+	pub fn valid_part1(&self) -> bool {
+		let Passport::Passport(fields) = self;
+		let mut v = Validation {
+			has_byr: false,
+			has_iyr: false,
+			has_eyr: false,
+			has_hgt: false,
+			has_hcl: false,
+			has_ecl: false,
+			has_pid: false,
+		};
+		for field in fields {
+			let Field::Field(k, _) = field;
+			match str::from_utf8(k) {
+				Ok("byr") => v.has_byr = true,
+				Ok("iyr") => v.has_iyr = true,
+				Ok("eyr") => v.has_eyr = true,
+				Ok("hgt") => v.has_hgt = true,
+				Ok("hcl") => v.has_hcl = true,
+				Ok("ecl") => v.has_ecl = true,
+				Ok("pid") => v.has_pid = true,
+				_ => {}
+			};
+		}
+		v.has_byr
+			&& v.has_iyr
+			&& v.has_eyr
+			&& v.has_hgt
+			&& v.has_hcl
+			&& v.has_ecl
+			&& v.has_pid
+	}
+
+	/// Whether all required fields are present and have valid values.
+	pub fn valid_part2(&self) -> bool {
+		let Passport::Passport(fields) = self;
+		let mut val = Validation {
+			has_byr: false,
+			has_iyr: false,
+			has_eyr: false,
+			has_hgt: false,
+			has_hcl: false,
+			has_ecl: false,
+			has_pid: false,
+		};
+		for field in fields {
+			let Field::Field(k, v) = field;
+			match str::from_utf8(k) {
+				Ok("byr") => {
+					val.has_byr = parse_year(v)
+						.is_ok_and(|(_, year)| (1920..=2002).contains(&year))
+				}
+				Ok("iyr") => {
+					val.has_iyr = parse_year(v)
+						.is_ok_and(|(_, year)| (2010..=2020).contains(&year))
+				}
+				Ok("eyr") => {
+					val.has_eyr = parse_year(v)
+						.is_ok_and(|(_, year)| (2020..=2030).contains(&year))
+				}
+				Ok("hgt") => {
+					val.has_hgt =
+						parse_length(v).is_ok_and(|(_, (l, u))| match u {
+							Unit::Cm => (150..=193).contains(&l),
+							Unit::In => (59..=76).contains(&l),
+						})
+				}
+				Ok("hcl") => val.has_hcl = parse_color(v).is_ok(),
+				Ok("ecl") => {
+					val.has_ecl = matches!(
+						str::from_utf8(v),
+						Ok("amb"
+							| "blu" | "brn" | "gry"
+							| "grn" | "hzl" | "oth")
+					)
+				}
+				Ok("pid") => {
+					val.has_pid =
+						parse_digit_count(v).is_ok_and(|(_, c)| c == 9)
+				}
+				_ => {}
+			};
+		}
+		val.has_byr
+			&& val.has_iyr
+			&& val.has_eyr
+			&& val.has_hgt
+			&& val.has_hcl
+			&& val.has_ecl
+			&& val.has_pid
+	}
+}
+
+/// Solution for day 4.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Day4;
+
+impl Solution for Day4 {
+	const DAY: u32 = 4;
+
+	type Input<'a> = Vec<Passport<'a>>;
+
+	fn parse<'a>(&self, input: &'a [u8]) -> Result<Self::Input<'a>, Error> {
+		parse::parse_complete(parse_passports, input)
+	}
+
+	fn parse_lenient<'a>(
+		&self,
+		input: &'a [u8],
+	) -> Result<(Self::Input<'a>, Vec<ParseError>), Error> {
+		Ok(parse::lenient(parse_passport, b"\n\n", input))
+	}
+
+	fn part1(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		Ok(Answer::new(
+			input.iter().filter(|p| p.valid_part1()).count(),
+		))
+	}
+
+	fn part2(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		Ok(Answer::new(
+			input.iter().filter(|p| p.valid_part2()).count(),
+		))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	#[test]
+	fn test_parse_field() {
+		let (rest, r) = parse_field(b"key:value").unwrap();
+		assert_eq!(0, rest.len());
+		assert_eq!(Field::Field(b"key", b"value"), r);
+	}
+
+	#[test]
+	fn test_parse_passport_color() {
+		let input = b"hcl:#602927";
+		let (rest, Passport::Passport(r)) = parse_passport(input).unwrap();
+		assert_eq!(
+			b"",
+			rest,
+			"Not all input consumed, rest: {}",
+			str::from_utf8(rest).unwrap()
+		);
+		assert_eq!(vec![Field::Field(b"hcl", b"#602927"),], r);
+	}
+
+	#[test]
+	fn test_parse_passport_multi_space() {
+		let (rest, Passport::Passport(r)) = parse_passport(b"a:1 b:2").unwrap();
+		assert_eq!(
+			b"",
+			rest,
+			"Not all input consumed, rest: {}",
+			str::from_utf8(rest).unwrap()
+		);
+		assert_eq!(vec![Field::Field(b"a", b"1"), Field::Field(b"b", b"2")], r);
+	}
+
+	#[test]
+	fn test_passport_display() {
+		let (_, p) = parse_passport(b"a:1\nb:#2 c:3").unwrap();
+		assert_eq!("a:1 b:#2 c:3", p.to_string());
+	}
+
+	#[test]
+	fn test_parse_passport_multi_newline() {
+		let (rest, Passport::Passport(r)) =
+			parse_passport(b"a:1\nb:2").unwrap();
+		assert_eq!(
+			b"",
+			rest,
+			"Not all input consumed, rest: {}",
+			str::from_utf8(rest).unwrap()
+		);
+		assert_eq!(vec![Field::Field(b"a", b"1"), Field::Field(b"b", b"2")], r);
+	}
+
+	#[test]
+	fn test_parse_passport_delimeted() {
+		let (rest, Passport::Passport(r)) =
+			parse_passport(b"a:1\n\nb:2").unwrap();
+		assert_eq!(b"\n\nb:2", rest, "Expected partial parse");
+		assert_eq!(vec![Field::Field(b"a", b"1")], r);
+	}
+
+	#[test]
+	fn test_parse_passports() {
+		let (rest, ps) = parse_passports(b"a:1\n\nb:2").unwrap();
+		assert_eq!(
+			b"",
+			rest,
+			"Not all input consumed, rest: {}",
+			str::from_utf8(rest).unwrap()
+		);
+
+		let Passport::Passport(a) =
+			ps.first().expect("Failed to parse passport a");
+		let Passport::Passport(b) =
+			ps.get(1).expect("Failed to parse passport b");
+
+		assert_eq!(vec![Field::Field(b"a", b"1")], *a);
+		assert_eq!(vec![Field::Field(b"b", b"2")], *b);
+	}
+
+	#[test]
+	fn test_parse_passports_with_newline() {
+		let (rest, ps) = parse_passports(b"a:1\na:2\n\nb:1").unwrap();
+		assert_eq!(
+			b"",
+			rest,
+			"Not all input consumed, rest: {}",
+			str::from_utf8(rest).unwrap()
+		);
+
+		let Passport::Passport(a) =
+			ps.first().expect("Failed to parse passport a");
+		let Passport::Passport(b) =
+			ps.get(1).expect("Failed to parse passport b");
+
+		assert_eq!(
+			vec![Field::Field(b"a", b"1"), Field::Field(b"a", b"2")],
+			*a
+		);
+		assert_eq!(vec![Field::Field(b"b", b"1")], *b);
+	}
+
+	#[test]
+	fn test_parse_year() {
+		let (rest, r) = parse_year(b"1990").expect("Failed to parse input");
+		assert_eq!(
+			b"",
+			rest,
+			"Not all input consumed, rest: {}",
+			str::from_utf8(rest).unwrap()
+		);
+		assert_eq!(1990, r);
+	}
+
+	#[test]
+	fn test_parse_length_cm() {
+		let (rest, r) = parse_length(b"199cm").expect("Failed to parse input");
+		assert_eq!(
+			b"",
+			rest,
+			"Not all input consumed, rest: {}",
+			str::from_utf8(rest).unwrap()
+		);
+		assert_eq!((199, Unit::Cm), r);
+	}
+
+	#[test]
+	fn test_parse_length_in() {
+		let (rest, r) = parse_length(b"99in").expect("Failed to parse input");
+		assert_eq!(
+			b"",
+			rest,
+			"Not all input consumed, rest: {}",
+			str::from_utf8(rest).unwrap()
+		);
+		assert_eq!((99, Unit::In), r);
+	}
+
+	#[test]
+	fn test_parse_color() {
+		let (rest, r) = parse_color(b"#aabbcc").expect("Failed to parse input");
+		assert_eq!(
+			b"",
+			rest,
+			"Not all input consumed, rest: {}",
+			str::from_utf8(rest).unwrap()
+		);
+		assert_eq!("aabbcc", r);
+	}
+
+	#[test]
+	fn test_passport_valid_part1_empty_invalid() {
+		let a = Passport::Passport(vec![]);
+		assert!(!a.valid_part1());
+	}
+
+	#[test]
+	fn test_passport_valid_part1_valid() {
+		let a = Passport::Passport(vec![
+			Field::Field(b"byr", b"1"),
+			Field::Field(b"iyr", b"2"),
+			Field::Field(b"eyr", b"3"),
+			Field::Field(b"hgt", b"4"),
+			Field::Field(b"hcl", b"5"),
+			Field::Field(b"ecl", b"6"),
+			Field::Field(b"pid", b"7"),
+		]);
+		assert!(a.valid_part1());
+	}
+
+	#[test]
+	fn test_passport_valid_part1_cid_valid() {
+		let a = Passport::Passport(vec![
+			Field::Field(b"byr", b"1"),
+			Field::Field(b"iyr", b"2"),
+			Field::Field(b"eyr", b"3"),
+			Field::Field(b"hgt", b"4"),
+			Field::Field(b"hcl", b"5"),
+			Field::Field(b"ecl", b"6"),
+			Field::Field(b"pid", b"7"),
+			Field::Field(b"cid", b"8"),
+		]);
+		assert!(a.valid_part1());
+	}
+
+	#[test]
+	fn test_passport_valid_part2_valid() {
+		let a = Passport::Passport(vec![
+			Field::Field(b"byr", b"1980"),
+			Field::Field(b"iyr", b"2012"),
+			Field::Field(b"eyr", b"2030"),
+			Field::Field(b"hgt", b"74in"),
+			Field::Field(b"hcl", b"#623a2f"),
+			Field::Field(b"ecl", b"grn"),
+			Field::Field(b"pid", b"087499704"),
+		]);
+		assert!(a.valid_part2());
+	}
+
+	const KEYS: [&str; 8] =
+		["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+	fn fields() -> impl Strategy<Value = (String, String)> {
+		let key = prop_oneof![
+			prop::sample::select(&KEYS[..]).prop_map(String::from),
+			"[a-z0-9]{1,4}",
+		];
+		let value = prop_oneof![
+			prop::sample::select(vec![
+				"1980",
+				"2012",
+				"2030",
+				"190cm",
+				"60in",
+				"#623a2f",
+				"grn",
+				"087499704",
+			])
+			.prop_map(String::from),
+			"[a-z0-9#]{1,10}",
+		];
+		(key, value)
+	}
+
+	fn passports() -> impl Strategy<Value = Vec<Vec<(String, String)>>> {
+		prop::collection::vec(prop::collection::vec(fields(), 1..10), 1..6)
+	}
+
+	proptest! {
+		#[test]
+		fn prop_parse_round_trip(
+			passports in passports(),
+			seps in prop::collection::vec(prop::bool::ANY, 10),
+		) {
+			let text = passports
+				.iter()
+				.map(|fields| {
+					fields
+						.iter()
+						.zip(seps.iter())
+						.enumerate()
+						.map(|(n, ((k, v), &newline))| {
+							let sep = match (n, newline) {
+								(0, _) => "",
+								(_, true) => "\n",
+								(_, false) => " ",
+							};
+							format!("{}{}:{}", sep, k, v)
+						})
+						.collect::<String>()
+				})
+				.collect::<Vec<_>>()
+				.join("\n\n");
+			let r = Day4.parse(text.as_bytes()).expect("Failed to parse");
+			prop_assert_eq!(passports.len(), r.len());
+			for (expected, Passport::Passport(fields)) in
+				passports.iter().zip(&r)
+			{
+				let fields = fields
+					.iter()
+					.map(|Field::Field(k, v)| (k.to_vec(), v.to_vec()))
+					.collect::<Vec<_>>();
+				let expected = expected
+					.iter()
+					.map(|(k, v)| {
+						(k.as_bytes().to_vec(), v.as_bytes().to_vec())
+					})
+					.collect::<Vec<_>>();
+				prop_assert_eq!(expected, fields);
+			}
+		}
+
+		#[test]
+		fn prop_valid_part2_implies_part1(
+			fields in prop::collection::vec(fields(), 0..12),
+		) {
+			let p = Passport::Passport(
+				fields
+					.iter()
+					.map(|(k, v)| Field::Field(k.as_bytes(), v.as_bytes()))
+					.collect(),
+			);
+			prop_assert!(!p.valid_part2() || p.valid_part1());
+		}
+	}
+}
@@ -0,0 +1,12 @@
+use day4::Day4;
+use std::path::Path;
+
+#[test]
+fn test_answers() {
+	let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+	aoc_common::answers::assert_answers(
+		&Day4,
+		&dir.join("input").join("input"),
+		&dir.join("..").join("answers.txt"),
+	);
+}
@@ -0,0 +1,242 @@
+//! Day 5: Binary Boarding.
+//!
+//! Decodes binary space partitioned boarding passes into seat ids.
+
+use aoc_common::{
+	parse::{self, records, IResult},
+	Answer, Error, ParseError, Part, Solution,
+};
+use nom::{
+	branch::alt, bytes::complete::tag, character::complete::newline,
+	combinator::map, error::context, multi::count, sequence::pair,
+};
+use std::{fmt, str};
+
+/// A seat by its id, row times 8 plus column.
+///
+/// Written as a boarding pass such as `FBFBBFFRLR`, seven `F` or `B` for
+/// the row and three `L` or `R` for the column. Only the low ten bits of
+/// the id are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Seat {
+	Seat(u32),
+}
+
+impl Seat {
+	pub fn id(&self) -> u32 {
+		let &Seat::Seat(seat_id) = self;
+		seat_id
+	}
+}
+
+impl fmt::Display for Seat {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for bit in (0..10).rev() {
+			let c = match (bit >= 3, self.id() >> bit & 1 == 1) {
+				(true, false) => 'F',
+				(true, true) => 'B',
+				(false, false) => 'L',
+				(false, true) => 'R',
+			};
+			write!(f, "{}", c)?;
+		}
+		Ok(())
+	}
+}
+
+impl str::FromStr for Seat {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		parse::parse_complete(parse_seat, s.as_bytes())
+	}
+}
+
+fn parse_fb(i: &[u8]) -> IResult<&[u8], bool> {
+	context(
+		"\"F\" or \"B\"",
+		alt((map(tag("F"), |_| false), map(tag("B"), |_| true))),
+	)(i)
+}
+
+fn parse_lr(i: &[u8]) -> IResult<&[u8], bool> {
+	context(
+		"\"L\" or \"R\"",
+		alt((map(tag("L"), |_| false), map(tag("R"), |_| true))),
+	)(i)
+}
+
+/// A single boarding pass.
+pub fn parse_seat(i: &[u8]) -> IResult<&[u8], Seat> {
+	map(pair(count(parse_fb, 7), count(parse_lr, 3)), |(fb, lr)| {
+		let iter = fb.iter().chain(lr.iter());
+		let seat_id = iter.fold(0, |acc, &b| acc << 1 | b as u32);
+		Seat::Seat(seat_id)
+	})(i)
+}
+
+/// Boarding passes, one per line.
+pub fn parse_seats(i: &[u8]) -> IResult<&[u8], Vec<Seat>> {
+	records(newline, parse_seat)(i)
+}
+
+/// Solution for day 5.
+///
+/// Parsed seats are sorted by id.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Day5;
+
+impl Solution for Day5 {
+	const DAY: u32 = 5;
+
+	type Input<'a> = Vec<Seat>;
+
+	fn parse<'a>(&self, input: &'a [u8]) -> Result<Self::Input<'a>, Error> {
+		let mut seats = parse::parse_complete(parse_seats, input)?;
+		seats.sort_by_key(Seat::id);
+		Ok(seats)
+	}
+
+	fn parse_lenient<'a>(
+		&self,
+		input: &'a [u8],
+	) -> Result<(Self::Input<'a>, Vec<ParseError>), Error> {
+		let (mut seats, errors) = parse::lenient(parse_seat, b"\n", input);
+		seats.sort_by_key(Seat::id);
+		Ok((seats, errors))
+	}
+
+	fn part1(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		input
+			.last()
+			.map(|s| Answer::new(s.id()))
+			.ok_or(Error::NoAnswer(Part::Part1))
+	}
+
+	fn part2(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		input
+			.iter()
+			.zip(input.iter().skip(1))
+			.find(|&(a, b)| a.id() + 1 != b.id())
+			.map(|(a, _)| Answer::new(a.id() + 1))
+			.ok_or(Error::NoAnswer(Part::Part2))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	#[test]
+	fn test_parse_fb_f() {
+		let (rest, r) = parse_fb(b"F").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert!(!r);
+	}
+
+	#[test]
+	fn test_parse_fb_b() {
+		let (rest, r) = parse_fb(b"B").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert!(r);
+	}
+
+	#[test]
+	fn test_parse_lr_l() {
+		let (rest, r) = parse_lr(b"L").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert!(!r);
+	}
+
+	#[test]
+	fn test_parse_lr_r() {
+		let (rest, r) = parse_lr(b"R").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert!(r);
+	}
+
+	#[test]
+	fn test_parse_seat_row_0() {
+		let (rest, r) =
+			parse_seat(b"FFFFFFFLLL").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Seat::Seat(0_u32), r);
+	}
+
+	#[test]
+	fn test_parse_seat_row_127() {
+		let (rest, r) =
+			parse_seat(b"BBBBBBBLLL").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Seat::Seat(127_u32 << 3), r);
+	}
+
+	#[test]
+	fn test_parse_seat_column_1() {
+		let (rest, r) =
+			parse_seat(b"FFFFFFFLLR").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Seat::Seat(1_u32), r);
+	}
+
+	#[test]
+	fn test_parse_seat_column_7() {
+		let (rest, r) =
+			parse_seat(b"FFFFFFFRRR").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Seat::Seat(7_u32), r);
+	}
+
+	fn encode(id: u32) -> String {
+		(0..10)
+			.rev()
+			.map(|bit| match (bit >= 3, id >> bit & 1 == 1) {
+				(true, false) => 'F',
+				(true, true) => 'B',
+				(false, false) => 'L',
+				(false, true) => 'R',
+			})
+			.collect()
+	}
+
+	proptest! {
+		#[test]
+		fn prop_parse_seat_round_trip(id in 0_u32..1024) {
+			let text = encode(id);
+			let (rest, r) =
+				parse_seat(text.as_bytes()).expect("Failed to parse");
+			prop_assert!(rest.is_empty());
+			prop_assert_eq!(id, r.id());
+		}
+
+		#[test]
+		fn prop_seat_display(id in 0_u32..1024) {
+			let text = Seat::Seat(id).to_string();
+			prop_assert_eq!(encode(id), text.clone());
+			let r = text.parse::<Seat>().expect("Failed to parse");
+			prop_assert_eq!(Seat::Seat(id), r);
+		}
+
+		#[test]
+		fn prop_finds_missing_seat(
+			(ids, missing) in (0_u32..900, 3_u32..120)
+				.prop_flat_map(|(start, len)| {
+					(Just(start), Just(start + len), start + 1..start + len - 1)
+				})
+				.prop_flat_map(|(start, end, missing)| {
+					let ids = (start..end)
+						.filter(|&id| id != missing)
+						.collect::<Vec<_>>();
+					(Just(ids).prop_shuffle(), Just(missing))
+				})
+		) {
+			let text =
+				ids.iter().map(|&id| encode(id) + "\n").collect::<String>();
+			let seats = Day5.parse(text.as_bytes()).expect("Failed to parse");
+			let max = ids.iter().max().copied().unwrap_or_default();
+			prop_assert_eq!(max as i128, Day5.part1(&seats).unwrap().value);
+			prop_assert_eq!(missing as i128, Day5.part2(&seats).unwrap().value);
+		}
+	}
+}
@@ -0,0 +1,288 @@
+//! Day 6: Custom Customs.
+//!
+//! Counts the questions answered "yes" by anyone and by everyone in each
+//! group.
+
+use aoc_common::{
+	parse::{self, records, IResult},
+	Error, ParseError, Solution,
+};
+use nom::{
+	character::complete::{newline, one_of},
+	combinator::map,
+	error::context,
+	multi::{count, many1, separated_list1},
+};
+use std::{collections::BTreeMap, fmt, str};
+
+/// A question answered "yes", written as its letter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Answer {
+	Answer(char),
+}
+
+impl fmt::Display for Answer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Answer::Answer(c) = self;
+		write!(f, "{}", c)
+	}
+}
+
+/// A group as its number of members and how many of them gave each
+/// answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Group {
+	Group(usize, BTreeMap<Answer, usize>),
+}
+
+impl Group {
+	/// Number of questions anyone in the group answered.
+	pub fn anyone(&self) -> usize {
+		let Group::Group(_, a) = self;
+		a.len()
+	}
+
+	/// Number of questions everyone in the group answered.
+	pub fn everyone(&self) -> usize {
+		let Group::Group(members, a) = self;
+		a.values().filter(|&c| c == members).count()
+	}
+}
+
+impl str::FromStr for Group {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		parse::parse_complete(parse_group, s.as_bytes())
+	}
+}
+
+/// A single answer letter.
+pub fn parse_answer(i: &[u8]) -> IResult<&[u8], Answer> {
+	context(
+		"answer letter",
+		map(one_of("abcdefghijklmnopqrstuvwxyz"), Answer::Answer),
+	)(i)
+}
+
+/// A group, one line of answers per member.
+pub fn parse_group(i: &[u8]) -> IResult<&[u8], Group> {
+	map(separated_list1(newline, many1(parse_answer)), |v| {
+		let members = v.len();
+		let mut map = BTreeMap::new();
+		for a in v.into_iter().flatten() {
+			map.entry(a).and_modify(|v| *v += 1).or_insert(1);
+		}
+		Group::Group(members, map)
+	})(i)
+}
+
+/// Groups separated by blank lines.
+pub fn parse_groups(i: &[u8]) -> IResult<&[u8], Vec<Group>> {
+	records(count(newline, 2), parse_group)(i)
+}
+
+/// Solution for day 6.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Day6;
+
+impl Solution for Day6 {
+	const DAY: u32 = 6;
+
+	type Input<'a> = Vec<Group>;
+
+	fn parse<'a>(&self, input: &'a [u8]) -> Result<Self::Input<'a>, Error> {
+		parse::parse_complete(parse_groups, input)
+	}
+
+	fn parse_lenient<'a>(
+		&self,
+		input: &'a [u8],
+	) -> Result<(Self::Input<'a>, Vec<ParseError>), Error> {
+		Ok(parse::lenient(parse_group, b"\n\n", input))
+	}
+
+	fn part1(
+		&self,
+		input: &Self::Input<'_>,
+	) -> Result<aoc_common::Answer, Error> {
+		let anyone_answered_count =
+			input.iter().map(Group::anyone).sum::<usize>();
+		Ok(aoc_common::Answer::new(anyone_answered_count)
+			.with_detail("groups", input.len()))
+	}
+
+	fn part2(
+		&self,
+		input: &Self::Input<'_>,
+	) -> Result<aoc_common::Answer, Error> {
+		let everyone_answered_count =
+			input.iter().map(Group::everyone).sum::<usize>();
+		Ok(aoc_common::Answer::new(everyone_answered_count))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+	use std::collections::BTreeSet;
+
+	#[test]
+	fn test_parse_answer_a() {
+		let (rest, r) = parse_answer(b"a").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Answer::Answer('a'), r);
+	}
+
+	#[test]
+	fn test_parse_group_a() {
+		let (rest, Group::Group(members, r)) =
+			parse_group(b"a").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(1, members);
+		assert_eq!(
+			vec![(Answer::Answer('a'), 1)],
+			r.into_iter().collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_parse_group_aabc() {
+		let (rest, Group::Group(members, r)) =
+			parse_group(b"aab\nc").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(2, members);
+		assert_eq!(
+			vec![
+				(Answer::Answer('a'), 2),
+				(Answer::Answer('b'), 1),
+				(Answer::Answer('c'), 1),
+			],
+			r.into_iter().collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_parse_group_abc() {
+		let (rest, Group::Group(members, r)) =
+			parse_group(b"ab\nc").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(2, members);
+		assert_eq!(
+			vec![
+				(Answer::Answer('a'), 1),
+				(Answer::Answer('b'), 1),
+				(Answer::Answer('c'), 1),
+			],
+			r.into_iter().collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_parse_group_fekdcbayqxnwvh() {
+		let (rest, Group::Group(members, r)) =
+			parse_group(b"fekdcbayqxnwvh").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(1, members);
+		assert_eq!(
+			vec![
+				(Answer::Answer('a'), 1),
+				(Answer::Answer('b'), 1),
+				(Answer::Answer('c'), 1),
+				(Answer::Answer('d'), 1),
+				(Answer::Answer('e'), 1),
+				(Answer::Answer('f'), 1),
+				(Answer::Answer('h'), 1),
+				(Answer::Answer('k'), 1),
+				(Answer::Answer('n'), 1),
+				(Answer::Answer('q'), 1),
+				(Answer::Answer('v'), 1),
+				(Answer::Answer('w'), 1),
+				(Answer::Answer('x'), 1),
+				(Answer::Answer('y'), 1),
+			],
+			r.into_iter().collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_parse_group_abc_not_d() {
+		let (rest, Group::Group(members, r)) =
+			parse_group(b"ab\nc\n\nd").expect("Failed to parse input");
+		assert_eq!(b"\n\nd", rest, "Unexpected remaining input");
+		assert_eq!(2, members);
+		assert_eq!(
+			vec![
+				(Answer::Answer('a'), 1),
+				(Answer::Answer('b'), 1),
+				(Answer::Answer('c'), 1),
+			],
+			r.into_iter().collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_parse_groups_ab_ac() {
+		let (rest, r) =
+			parse_groups(b"ab\n\nac").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(
+			vec![
+				vec![(Answer::Answer('a'), 1), (Answer::Answer('b'), 1)],
+				vec![(Answer::Answer('a'), 1), (Answer::Answer('c'), 1)],
+			],
+			r.into_iter()
+				.map(|Group::Group(_, v)| v.into_iter().collect::<Vec<_>>())
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_group_from_str() {
+		let g = "ab\nac".parse::<Group>().expect("Failed to parse");
+		assert_eq!(3, g.anyone());
+		assert_eq!(1, g.everyone());
+	}
+
+	/// Groups of members, each answering a distinct set of questions as in
+	/// the puzzle input.
+	fn groups() -> impl Strategy<Value = Vec<Vec<BTreeSet<char>>>> {
+		let member = prop::collection::btree_set(
+			prop::sample::select(vec!['a', 'b', 'c', 'd', 'e', 'f']),
+			1..6,
+		);
+		prop::collection::vec(prop::collection::vec(member, 1..5), 1..8)
+	}
+
+	proptest! {
+		#[test]
+		fn prop_anyone_at_least_everyone(groups in groups()) {
+			let text = groups
+				.iter()
+				.map(|g| {
+					g.iter()
+						.map(|m| m.iter().collect::<String>())
+						.collect::<Vec<_>>()
+						.join("\n")
+				})
+				.collect::<Vec<_>>()
+				.join("\n\n");
+			let input = Day6.parse(text.as_bytes()).expect("Failed to parse");
+			let anyone = Day6.part1(&input).unwrap().value;
+			let everyone = Day6.part2(&input).unwrap().value;
+			prop_assert!(anyone >= everyone);
+
+			let (union, intersection) = groups.iter().fold((0, 0), |(u, i), g| {
+				let all = g.iter().flatten().collect::<BTreeSet<_>>();
+				let common = all
+					.iter()
+					.filter(|c| g.iter().all(|m| m.contains(c)))
+					.count();
+				(u + all.len() as i128, i + common as i128)
+			});
+			prop_assert_eq!(union, anyone);
+			prop_assert_eq!(intersection, everyone);
+		}
+	}
+}
@@ -0,0 +1,333 @@
+//! The bag rules as a graph, written in Graphviz DOT or Mermaid.
+//!
+//! Every bag is a node and every rule content an edge from the outer bag
+//! to the inner one, labelled with the count. A graph may be focused on
+//! a bag, whose ancestors and descendants are then highlighted.
+
+use crate::{Bag, BagRuleLookup};
+use aoc_common::Error;
+use std::{
+	collections::BTreeSet,
+	io::{self, Write},
+};
+
+/// Which bags a focused graph keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+	/// Every bag.
+	All,
+	/// The focus and the bags inside it.
+	Descendants,
+	/// The focus and the bags containing it.
+	Ancestors,
+	/// The focus and the bags inside or containing it.
+	Related,
+}
+
+/// Part of the rule graph, with the focus it was made for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Graph<'a> {
+	nodes: BTreeSet<Bag<'a>>,
+	/// Outer bag, count and inner bag of each edge.
+	edges: Vec<(Bag<'a>, usize, Bag<'a>)>,
+	focus: Option<Bag<'a>>,
+	ancestors: BTreeSet<Bag<'a>>,
+	descendants: BTreeSet<Bag<'a>>,
+}
+
+impl<'a> Graph<'a> {
+	/// The graph of every rule.
+	pub fn new(lookup: &BagRuleLookup<'a>) -> Self {
+		let mut nodes = BTreeSet::new();
+		let mut edges = vec![];
+		for (&bag, content) in lookup.rules() {
+			nodes.insert(bag);
+			for &(n, b) in content {
+				nodes.insert(b);
+				edges.push((bag, n, b));
+			}
+		}
+		Graph {
+			nodes,
+			edges,
+			focus: None,
+			ancestors: BTreeSet::new(),
+			descendants: BTreeSet::new(),
+		}
+	}
+
+	/// The graph of the bags in `scope` of `focus`, failing if no rule
+	/// mentions `focus`.
+	pub fn focused(
+		lookup: &BagRuleLookup<'a>,
+		focus: Bag,
+		scope: Scope,
+	) -> Result<Self, Error> {
+		let mut graph = Graph::new(lookup);
+		let focus =
+			*graph.nodes.iter().find(|&&b| b == focus).ok_or_else(|| {
+				Error::Invalid(format!("no rule mentions {} bags", focus))
+			})?;
+		let ancestors = lookup.ancestors(focus);
+		let descendants = lookup.descendants(focus);
+		let keep = |b: &Bag| {
+			*b == focus
+				|| match scope {
+					Scope::All => true,
+					Scope::Descendants => descendants.contains(b),
+					Scope::Ancestors => ancestors.contains(b),
+					Scope::Related => {
+						descendants.contains(b) || ancestors.contains(b)
+					}
+				}
+		};
+		graph.nodes.retain(keep);
+		graph.edges.retain(|(a, _, b)| keep(a) && keep(b));
+		graph.focus = Some(focus);
+		graph.ancestors = ancestors;
+		graph.descendants = descendants;
+		Ok(graph)
+	}
+
+	/// Bags in the graph.
+	pub fn nodes(&self) -> &BTreeSet<Bag<'a>> {
+		&self.nodes
+	}
+
+	/// Outer bag, count and inner bag of each edge, in rule order.
+	pub fn edges(&self) -> &[(Bag<'a>, usize, Bag<'a>)] {
+		&self.edges
+	}
+
+	/// How a bag is highlighted, `None` if it is not. A bag both inside
+	/// and containing the focus, which only cyclic rules allow, is shown
+	/// as a descendant.
+	fn class(&self, b: &Bag) -> Option<&'static str> {
+		if self.focus.as_ref() == Some(b) {
+			Some("focus")
+		} else if self.descendants.contains(b) {
+			Some("descendant")
+		} else if self.ancestors.contains(b) {
+			Some("ancestor")
+		} else {
+			None
+		}
+	}
+
+	/// Write the graph in Graphviz DOT format. The focus is gold, the
+	/// bags containing it blue and the bags inside it green.
+	pub fn write_dot<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+		self.dot(w).map_err(Error::Write)
+	}
+
+	fn dot<W: Write>(&self, w: &mut W) -> io::Result<()> {
+		writeln!(w, "digraph bags {{")?;
+		writeln!(w, "\trankdir=LR;")?;
+		writeln!(w, "\tnode [shape=box];")?;
+		for b in &self.nodes {
+			let color = match self.class(b) {
+				Some("focus") => "gold",
+				Some("descendant") => "palegreen",
+				Some(_) => "lightblue",
+				None => {
+					writeln!(w, "\t\"{}\";", b)?;
+					continue;
+				}
+			};
+			writeln!(w, "\t\"{}\" [style=filled, fillcolor={}];", b, color)?;
+		}
+		for (a, n, b) in &self.edges {
+			writeln!(w, "\t\"{}\" -> \"{}\" [label=\"{}\"];", a, b, n)?;
+		}
+		writeln!(w, "}}")
+	}
+
+	/// Write the graph as a Mermaid flowchart, highlighted with the
+	/// classes `focus`, `ancestor` and `descendant`.
+	pub fn write_mermaid<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+		self.mermaid(w).map_err(Error::Write)
+	}
+
+	fn mermaid<W: Write>(&self, w: &mut W) -> io::Result<()> {
+		// Colors are two words of letters, so this is a valid and unique
+		// id, and never a keyword such as `end`.
+		let id = |b: &Bag| b.to_string().replace(' ', "_");
+		writeln!(w, "flowchart LR")?;
+		for b in &self.nodes {
+			writeln!(w, "\t{}[\"{}\"]", id(b), b)?;
+		}
+		for (a, n, b) in &self.edges {
+			writeln!(w, "\t{} -->|{}| {}", id(a), n, id(b))?;
+		}
+		if self.focus.is_none() {
+			return Ok(());
+		}
+		writeln!(w, "\tclassDef focus fill:gold")?;
+		writeln!(w, "\tclassDef ancestor fill:lightblue")?;
+		writeln!(w, "\tclassDef descendant fill:palegreen")?;
+		for class in &["focus", "ancestor", "descendant"] {
+			let ids = self
+				.nodes
+				.iter()
+				.filter(|b| self.class(b) == Some(*class))
+				.map(id)
+				.collect::<Vec<_>>();
+			if !ids.is_empty() {
+				writeln!(w, "\tclass {} {}", ids.join(","), class)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		tests::{name, reaches, render, rules},
+		Day7,
+	};
+	use aoc_common::Solution;
+	use proptest::prelude::*;
+
+	const EXAMPLE: &[u8] = b"light red bags contain 1 bright white bag, \
+		2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag.
+dark olive bags contain 3 faded blue bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags.
+";
+
+	fn names<'a>(bags: &BTreeSet<Bag<'a>>) -> Vec<&'a str> {
+		bags.iter().map(|&Bag::Bag(name)| name).collect()
+	}
+
+	#[test]
+	fn test_focused_scopes() {
+		let lookup = Day7.parse(EXAMPLE).expect("Failed to parse input");
+		let gold = Bag::Bag("shiny gold");
+		let graph = |scope| Graph::focused(&lookup, gold, scope).unwrap();
+		assert_eq!(
+			vec!["dark olive", "faded blue", "shiny gold"],
+			names(graph(Scope::Descendants).nodes())
+		);
+		assert_eq!(
+			vec!["bright white", "light red", "muted yellow", "shiny gold"],
+			names(graph(Scope::Ancestors).nodes())
+		);
+		let related = graph(Scope::Related);
+		assert_eq!(6, related.nodes().len());
+		// Edges between kept bags are kept, also from an ancestor to a
+		// descendant.
+		assert_eq!(7, related.edges().len());
+		assert_eq!(7, graph(Scope::All).nodes().len());
+		assert_eq!(Graph::new(&lookup).edges(), graph(Scope::All).edges());
+		assert!(
+			Graph::focused(&lookup, Bag::Bag("pale red"), Scope::All).is_err()
+		);
+	}
+
+	#[test]
+	fn test_write_dot() {
+		let lookup = Day7.parse(EXAMPLE).expect("Failed to parse input");
+		let graph =
+			Graph::focused(&lookup, Bag::Bag("dark olive"), Scope::Related)
+				.unwrap();
+		let mut out = vec![];
+		graph.write_dot(&mut out).unwrap();
+		assert_eq!(
+			"digraph bags {
+	rankdir=LR;
+	node [shape=box];
+	\"bright white\" [style=filled, fillcolor=lightblue];
+	\"dark olive\" [style=filled, fillcolor=gold];
+	\"faded blue\" [style=filled, fillcolor=palegreen];
+	\"light red\" [style=filled, fillcolor=lightblue];
+	\"muted yellow\" [style=filled, fillcolor=lightblue];
+	\"shiny gold\" [style=filled, fillcolor=lightblue];
+	\"bright white\" -> \"shiny gold\" [label=\"1\"];
+	\"dark olive\" -> \"faded blue\" [label=\"3\"];
+	\"light red\" -> \"bright white\" [label=\"1\"];
+	\"light red\" -> \"muted yellow\" [label=\"2\"];
+	\"muted yellow\" -> \"shiny gold\" [label=\"2\"];
+	\"muted yellow\" -> \"faded blue\" [label=\"9\"];
+	\"shiny gold\" -> \"dark olive\" [label=\"1\"];
+}
+",
+			String::from_utf8(out).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_write_mermaid() {
+		let lookup = Day7
+			.parse(
+				b"shiny gold bags contain 2 dark red bags.\n\
+				dark red bags contain no other bags.\n\
+				dotted black bags contain 1 shiny gold bag.\n\
+				faded blue bags contain no other bags.\n",
+			)
+			.expect("Failed to parse input");
+		let mut out = vec![];
+		Graph::new(&lookup).write_mermaid(&mut out).unwrap();
+		assert_eq!(
+			"flowchart LR
+	dark_red[\"dark red\"]
+	dotted_black[\"dotted black\"]
+	faded_blue[\"faded blue\"]
+	shiny_gold[\"shiny gold\"]
+	dotted_black -->|1| shiny_gold
+	shiny_gold -->|2| dark_red
+",
+			String::from_utf8(out).unwrap()
+		);
+
+		let mut out = vec![];
+		Graph::focused(&lookup, Bag::Bag("shiny gold"), Scope::All)
+			.unwrap()
+			.write_mermaid(&mut out)
+			.unwrap();
+		let out = String::from_utf8(out).unwrap();
+		assert!(out.contains("\tfaded_blue[\"faded blue\"]\n"));
+		assert!(out.ends_with(
+			"\tclass shiny_gold focus
+	class dotted_black ancestor
+	class dark_red descendant
+"
+		));
+	}
+
+	proptest! {
+		#[test]
+		fn prop_focused_matches_dfs((n, edges) in rules(), t in 0_usize..8) {
+			let t = t % n;
+			let text = render(n, &edges);
+			let lookup = Day7.parse(text.as_bytes()).expect("Failed to parse");
+			let focus = name(t);
+			let focused = |scope| {
+				Graph::focused(&lookup, Bag::Bag(&focus), scope).unwrap()
+			};
+			let expected = |f: &dyn Fn(usize) -> bool| {
+				let mut v =
+					(0..n).filter(|&i| f(i)).map(name).collect::<Vec<_>>();
+				v.sort();
+				v
+			};
+			let down = expected(&|i| reaches(n, &edges, t, i));
+			let up = expected(&|i| reaches(n, &edges, i, t));
+			let related = expected(&|i| {
+				reaches(n, &edges, t, i) || reaches(n, &edges, i, t)
+			});
+			prop_assert_eq!(down, names(focused(Scope::Descendants).nodes()));
+			prop_assert_eq!(up, names(focused(Scope::Ancestors).nodes()));
+			let graph = focused(Scope::Related);
+			prop_assert_eq!(related, names(graph.nodes()));
+			for (a, _, b) in graph.edges() {
+				prop_assert!(graph.nodes().contains(a));
+				prop_assert!(graph.nodes().contains(b));
+			}
+		}
+	}
+}
@@ -0,0 +1,545 @@
+//! Day 7: Handy Haversacks.
+//!
+//! Follows the rules for which bags must contain which other bags. Bags
+//! borrow their names from the input, so rules are parsed with
+//! [`parse_rule_lookup`] rather than `FromStr`.
+
+pub mod graph;
+
+use aoc_common::{
+	parse::{self, records, IResult},
+	Answer, Error, ParseError, Solution,
+};
+use nom::{
+	branch::alt,
+	bytes::complete::tag,
+	character::complete::{alpha1, digit1, newline, space0},
+	combinator::{map, map_res, recognize},
+	error::context,
+	multi::separated_list1,
+	sequence::{pair, separated_pair, terminated},
+};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fmt, str,
+};
+
+/// A bag by its color, such as `shiny gold`.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord)]
+pub enum Bag<'a> {
+	Bag(&'a str),
+}
+
+impl fmt::Display for Bag<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Bag::Bag(name) = self;
+		write!(f, "{}", name)
+	}
+}
+
+/// Bags directly inside a bag, each with its count.
+pub type Content<'a> = Vec<(usize, Bag<'a>)>;
+/// Content of each bag that has a rule.
+pub type Rules<'a> = BTreeMap<Bag<'a>, Content<'a>>;
+/// Total number of bags inside each bag.
+pub type Totals<'a> = BTreeMap<Bag<'a>, usize>;
+
+/// The bag rules, written one rule per line in the input format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BagRuleLookup<'a> {
+	map: Rules<'a>,
+}
+
+impl<'a> From<Rules<'a>> for BagRuleLookup<'a> {
+	fn from(map: Rules<'a>) -> Self {
+		BagRuleLookup { map }
+	}
+}
+
+impl fmt::Display for BagRuleLookup<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (bag, content) in &self.map {
+			write!(f, "{} bags contain ", bag)?;
+			if content.is_empty() {
+				write!(f, "no other bags")?;
+			}
+			for (n, (count, b)) in content.iter().enumerate() {
+				let sep = if n > 0 { ", " } else { "" };
+				let plural = if *count == 1 { "" } else { "s" };
+				write!(f, "{}{} {} bag{}", sep, count, b, plural)?;
+			}
+			writeln!(f, ".")?;
+		}
+		Ok(())
+	}
+}
+
+/// Number of bags in a rule's content.
+pub fn parse_count(i: &[u8]) -> IResult<&[u8], usize> {
+	context(
+		"bag count",
+		map_res(map_res(recognize(digit1), str::from_utf8), str::parse),
+	)(i)
+}
+
+/// A bag color followed by `bag` or `bags`.
+pub fn parse_bag(i: &[u8]) -> IResult<&[u8], Bag<'_>> {
+	map(
+		terminated(
+			context(
+				"bag color",
+				map_res(
+					recognize(separated_pair(alpha1, tag(" "), alpha1)),
+					str::from_utf8,
+				),
+			),
+			context("\"bags\" or \"bag\"", alt((tag(" bags"), tag(" bag")))),
+		),
+		Bag::Bag,
+	)(i)
+}
+
+/// A single rule such as
+/// `bright white bags contain 1 shiny gold bag.`
+pub fn parse_rule(i: &[u8]) -> IResult<&[u8], (Bag<'_>, Content<'_>)> {
+	pair(
+		terminated(parse_bag, context("\"contain\"", tag(" contain "))),
+		terminated(
+			alt((
+				map(tag("no other bags"), |_| vec![]),
+				separated_list1(
+					terminated(tag(","), space0),
+					separated_pair(parse_count, tag(" "), parse_bag),
+				),
+			)),
+			context("\".\"", tag(".")),
+		),
+	)(i)
+}
+
+/// Rules, one per line. A later rule for the same bag replaces an
+/// earlier one.
+pub fn parse_rule_lookup(i: &[u8]) -> IResult<&[u8], Rules<'_>> {
+	map(records(newline, parse_rule), |m| m.into_iter().collect())(i)
+}
+
+fn into_revers_rule_lookup<'a>(map: &Rules<'a>) -> Rules<'a> {
+	map.iter().fold(BTreeMap::new(), |mut acc, (&b, bgs)| {
+		for &(n, c) in bgs {
+			acc.entry(c)
+				.and_modify(|e| {
+					e.push((n, b));
+				})
+				.or_insert(vec![(n, b)]);
+		}
+		acc
+	})
+}
+
+/// Bags reachable from `bag` in `map`, found with an explicit stack so
+/// that deep rules do not overflow the call stack.
+fn create_set<'a>(map: &Rules<'a>, bag: Bag) -> BTreeSet<Bag<'a>> {
+	let mut set = BTreeSet::new();
+	// Bags whose content is still to be added.
+	let mut stack = vec![];
+	let mut content = map.get(&bag);
+	loop {
+		stack.extend(content.into_iter().flatten().map(|&(_, b)| b));
+		content = match stack.pop() {
+			Some(b) if set.insert(b) => map.get(&b),
+			Some(_) => None,
+			None => return set,
+		};
+	}
+}
+
+/// Total number of bags inside each bag reachable from `roots`, failing
+/// if the rules are cyclic or a count overflows.
+///
+/// Bags are evaluated depth first with an explicit stack, each once after
+/// all bags inside it, so shared content is only counted once.
+fn count_totals<'a>(
+	map: &Rules<'a>,
+	roots: impl IntoIterator<Item = Bag<'a>>,
+) -> Result<Totals<'a>, Error> {
+	let mut totals = Totals::new();
+	// Bags being evaluated, each with the index of its next content.
+	let mut stack: Vec<(Bag<'a>, usize)> = vec![];
+	let mut path = BTreeSet::new();
+	for root in roots {
+		if totals.contains_key(&root) {
+			continue;
+		}
+		stack.push((root, 0));
+		path.insert(root);
+		while let Some((bag, next)) = stack.last_mut() {
+			let content = map.get(bag).map_or(&[][..], Vec::as_slice);
+			if let Some(&(_, b)) = content.get(*next) {
+				*next += 1;
+				if path.contains(&b) {
+					let Bag::Bag(name) = b;
+					return Err(Error::Invalid(format!(
+						"bag rules contain a cycle through {:?}",
+						name
+					)));
+				}
+				if !totals.contains_key(&b) {
+					stack.push((b, 0));
+					path.insert(b);
+				}
+				continue;
+			}
+			let bag = *bag;
+			let total = content
+				.iter()
+				.try_fold(0_usize, |acc, (n, b)| {
+					let inner = totals[b].checked_add(1)?;
+					acc.checked_add(n.checked_mul(inner)?)
+				})
+				.ok_or_else(overflow)?;
+			totals.insert(bag, total);
+			stack.pop();
+			path.remove(&bag);
+		}
+	}
+	Ok(totals)
+}
+
+fn overflow() -> Error {
+	Error::Invalid("bag count overflows".into())
+}
+
+impl<'a> BagRuleLookup<'a> {
+	/// Content of each bag that has a rule.
+	pub fn rules(&self) -> &Rules<'a> {
+		&self.map
+	}
+
+	/// Bags that eventually contain `b`.
+	pub fn ancestors(&self, b: Bag) -> BTreeSet<Bag<'a>> {
+		let reverse_map = into_revers_rule_lookup(&self.map);
+		create_set(&reverse_map, b)
+	}
+
+	/// Bags that `b` eventually contains.
+	pub fn descendants(&self, b: Bag) -> BTreeSet<Bag<'a>> {
+		create_set(&self.map, b)
+	}
+
+	/// Number of distinct bags that eventually contain `b`.
+	pub fn count_bags_containing(&self, b: Bag) -> usize {
+		self.ancestors(b).len()
+	}
+
+	/// Total number of bags inside every bag, failing if the rules are
+	/// cyclic or a count overflows.
+	pub fn content_totals(&self) -> Result<Totals<'a>, Error> {
+		count_totals(&self.map, self.map.keys().copied())
+	}
+
+	/// Total number of bags inside `b` and each bag it eventually
+	/// contains, failing if these rules are cyclic or a count overflows.
+	pub fn content_totals_of(&self, b: Bag<'a>) -> Result<Totals<'a>, Error> {
+		count_totals(&self.map, Some(b))
+	}
+
+	/// Total number of bags inside `b`, failing if the rules are cyclic or
+	/// the count overflows.
+	pub fn count_bag_content(&self, b: Bag<'a>) -> Result<usize, Error> {
+		Ok(self.content_totals_of(b)?[&b])
+	}
+}
+
+/// Solution for day 7.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Day7;
+
+impl Solution for Day7 {
+	const DAY: u32 = 7;
+
+	type Input<'a> = BagRuleLookup<'a>;
+
+	fn parse<'a>(&self, input: &'a [u8]) -> Result<Self::Input<'a>, Error> {
+		parse::parse_complete(parse_rule_lookup, input).map(BagRuleLookup::from)
+	}
+
+	fn parse_lenient<'a>(
+		&self,
+		input: &'a [u8],
+	) -> Result<(Self::Input<'a>, Vec<ParseError>), Error> {
+		let (rules, errors) = parse::lenient(parse_rule, b"\n", input);
+		let map = rules.into_iter().collect::<Rules>();
+		Ok((map.into(), errors))
+	}
+
+	fn part1(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		Ok(Answer::new(
+			input.count_bags_containing(Bag::Bag("shiny gold")),
+		))
+	}
+
+	fn part2(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		input
+			.count_bag_content(Bag::Bag("shiny gold"))
+			.map(Answer::new)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	#[test]
+	fn test_parse_count() {
+		let (rest, r) = parse_count(b"10").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(10, r);
+	}
+
+	#[test]
+	fn test_parse_bag() {
+		let (rest, r) =
+			parse_bag(b"shiny gold bag").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Bag::Bag("shiny gold"), r);
+	}
+
+	#[test]
+	fn test_parse_bag_plural() {
+		let (rest, r) =
+			parse_bag(b"shiny gold bags").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Bag::Bag("shiny gold"), r);
+	}
+
+	#[test]
+	fn test_parse_rule_contains_none() {
+		let (rest, r) = parse_rule(b"faded blue bags contain no other bags.")
+			.expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!((Bag::Bag("faded blue"), vec![]), r);
+	}
+
+	#[test]
+	fn test_parse_rule_contains_one() {
+		let (rest, r) =
+			parse_rule(b"vibrant plum bags contain 5 faded blue bags.")
+				.expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(
+			(Bag::Bag("vibrant plum"), vec![(5, Bag::Bag("faded blue"))]),
+			r
+		);
+	}
+
+	#[test]
+	fn test_parse_rule_contains_two() {
+		let (rest, r) =
+			parse_rule(b"vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(
+			(
+				Bag::Bag("vibrant plum"),
+				vec![
+					(5, Bag::Bag("faded blue")),
+					(6, Bag::Bag("dotted black"))
+				]
+			),
+			r
+		);
+	}
+
+	#[test]
+	fn test_count_bag_content_cycle() {
+		let input = b"shiny gold bags contain 1 dark red bag.\n\
+			dark red bags contain 2 shiny gold bags.\n";
+		let lookup = Day7.parse(input).expect("Failed to parse input");
+		assert!(Day7.part2(&lookup).is_err());
+	}
+
+	#[test]
+	fn test_content_totals() {
+		let input = b"shiny gold bags contain 2 dark red bags.\n\
+			dark red bags contain 2 dark orange bags.\n\
+			dark orange bags contain no other bags.\n\
+			dotted black bags contain 3 dark red bags.\n";
+		let lookup = Day7.parse(input).expect("Failed to parse input");
+		let gold = lookup
+			.content_totals_of(Bag::Bag("shiny gold"))
+			.expect("Failed to count");
+		assert_eq!(
+			vec![
+				(Bag::Bag("dark orange"), 0),
+				(Bag::Bag("dark red"), 2),
+				(Bag::Bag("shiny gold"), 6)
+			],
+			gold.into_iter().collect::<Vec<_>>()
+		);
+		let totals = lookup.content_totals().expect("Failed to count");
+		assert_eq!(4, totals.len());
+		assert_eq!(9, totals[&Bag::Bag("dotted black")]);
+		assert_eq!(0, lookup.count_bag_content(Bag::Bag("pale red")).unwrap());
+	}
+
+	/// Rules of `n` levels of two bags, each containing one of both bags
+	/// on the next level, so the number of paths doubles with each level.
+	fn diamond(n: usize) -> String {
+		(0..n)
+			.map(|i| {
+				let content = if i + 1 < n {
+					let (a, b) = (name(2 * i + 2), name(2 * i + 3));
+					format!("1 {} bag, 1 {} bag", a, b)
+				} else {
+					"no other bags".to_string()
+				};
+				[2 * i, 2 * i + 1]
+					.iter()
+					.map(|&b| {
+						format!("{} bags contain {}.\n", name(b), content)
+					})
+					.collect::<String>()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_count_bag_content_diamond() {
+		let text = diamond(40);
+		let lookup = Day7.parse(text.as_bytes()).expect("Failed to parse");
+		let top = name(0);
+		let r = lookup.count_bag_content(Bag::Bag(&top)).unwrap();
+		assert_eq!((1 << 40) - 2, r);
+		let text = diamond(100);
+		let lookup = Day7.parse(text.as_bytes()).expect("Failed to parse");
+		assert!(lookup.count_bag_content(Bag::Bag(&top)).is_err());
+		assert!(lookup.count_bag_content(Bag::Bag(&name(190))).is_ok());
+	}
+
+	#[test]
+	fn test_deep_chain() {
+		// Each bag holds the next, down to the shiny gold bag.
+		let n = 17_000;
+		let mut text = (0..n)
+			.map(|i| {
+				let inner = match i + 1 {
+					i if i < n => name(i),
+					_ => "shiny gold".to_string(),
+				};
+				format!("{} bags contain 1 {} bag.\n", name(i), inner)
+			})
+			.collect::<String>();
+		text.push_str("shiny gold bags contain no other bags.\n");
+		let lookup = Day7.parse(text.as_bytes()).expect("Failed to parse");
+		assert_eq!(n as i128, Day7.part1(&lookup).unwrap().value);
+		assert_eq!(0, Day7.part2(&lookup).unwrap().value);
+		let top = name(0);
+		assert_eq!(n, lookup.descendants(Bag::Bag(&top)).len());
+		assert_eq!(n, lookup.count_bag_content(Bag::Bag(&top)).unwrap());
+	}
+
+	pub(crate) fn name(i: usize) -> String {
+		let color = (0..3)
+			.map(|n| (b'a' + (i / 26_usize.pow(n) % 26) as u8) as char)
+			.collect::<String>();
+		format!("dull {}", color)
+	}
+
+	/// Random acyclic rules as `(n, edges)`, where bag `i` may only contain
+	/// bags `j > i` and `edges[i * n + j]` is the count.
+	pub(crate) fn rules() -> impl Strategy<Value = (usize, Vec<Option<usize>>)>
+	{
+		(1_usize..8).prop_flat_map(|n| {
+			(
+				Just(n),
+				prop::collection::vec(prop::option::of(1_usize..4), n * n),
+			)
+		})
+	}
+
+	pub(crate) fn render(n: usize, edges: &[Option<usize>]) -> String {
+		(0..n)
+			.map(|i| {
+				let content = (i + 1..n)
+					.filter_map(|j| edges[i * n + j].map(|c| (c, j)))
+					.map(|(c, j)| {
+						let plural = if c == 1 { "" } else { "s" };
+						format!("{} {} bag{}", c, name(j), plural)
+					})
+					.collect::<Vec<_>>();
+				let content = match content.len() {
+					0 => "no other bags".to_string(),
+					_ => content.join(", "),
+				};
+				format!("{} bags contain {}.\n", name(i), content)
+			})
+			.collect()
+	}
+
+	fn dfs_total(n: usize, edges: &[Option<usize>], i: usize) -> usize {
+		(i + 1..n)
+			.filter_map(|j| edges[i * n + j].map(|c| (c, j)))
+			.map(|(c, j)| c * (1 + dfs_total(n, edges, j)))
+			.sum()
+	}
+
+	pub(crate) fn reaches(
+		n: usize,
+		edges: &[Option<usize>],
+		i: usize,
+		t: usize,
+	) -> bool {
+		i == t
+			|| (i + 1..n)
+				.any(|j| edges[i * n + j].is_some() && reaches(n, edges, j, t))
+	}
+
+	proptest! {
+		#[test]
+		fn prop_count_bag_content_matches_dfs((n, edges) in rules()) {
+			let text = render(n, &edges);
+			let lookup = Day7.parse(text.as_bytes()).expect("Failed to parse");
+			for i in 0..n {
+				let name = name(i);
+				let r = lookup.count_bag_content(Bag::Bag(&name)).unwrap();
+				prop_assert_eq!(dfs_total(n, &edges, i), r);
+			}
+		}
+
+		#[test]
+		fn prop_content_totals_match_dfs((n, edges) in rules()) {
+			let text = render(n, &edges);
+			let lookup = Day7.parse(text.as_bytes()).expect("Failed to parse");
+			let totals = lookup.content_totals().expect("Failed to count");
+			prop_assert_eq!(n, totals.len());
+			for i in 0..n {
+				let name = name(i);
+				let r = totals[&Bag::Bag(&name)];
+				prop_assert_eq!(dfs_total(n, &edges, i), r);
+			}
+		}
+
+		#[test]
+		fn prop_count_bags_containing_matches_dfs((n, edges) in rules()) {
+			let text = render(n, &edges);
+			let lookup = Day7.parse(text.as_bytes()).expect("Failed to parse");
+			for t in 0..n {
+				let name = name(t);
+				let expected = (0..n)
+					.filter(|&i| i != t && reaches(n, &edges, i, t))
+					.count();
+				let r = lookup.count_bags_containing(Bag::Bag(&name));
+				prop_assert_eq!(expected, r);
+			}
+		}
+
+		#[test]
+		fn prop_display_round_trip((n, edges) in rules()) {
+			let text = render(n, &edges);
+			let lookup = Day7.parse(text.as_bytes()).expect("Failed to parse");
+			let display = lookup.to_string();
+			let r = Day7.parse(display.as_bytes()).expect("Failed to parse");
+			prop_assert_eq!(lookup, r);
+		}
+	}
+}
@@ -0,0 +1,371 @@
+//! Assembly source for console programs.
+//!
+//! The source is the puzzle format extended with labels and comments:
+//!
+//! ```text
+//! ; count down from 3
+//!         acc +3
+//! loop:   acc -1
+//!         jnz loop   ; until acc is zero
+//!         halt
+//! ```
+//!
+//! A line holds an optional `label:`, an optional instruction and an
+//! optional `;` comment. An immediate operand may name a label instead,
+//! which becomes the offset from the instruction to the labelled one. A
+//! label after the last instruction is the end of the program.
+//!
+//! Leniently assembled, a line that fails to parse is left out, as is a
+//! repeated label or an instruction naming an undefined label.
+
+use crate::{
+	immediate,
+	isa::{register, Kind, Operand},
+	Immediate, Instr, Isa, Op,
+};
+use aoc_common::{
+	parse::{self, IResult},
+	Error, ParseError,
+};
+use nom::{
+	branch::alt,
+	bytes::complete::{take_while, take_while1},
+	character::complete::{alpha1, char, newline, not_line_ending},
+	combinator::{cut, eof, map, map_opt, opt, peek, recognize},
+	error::context,
+	multi::separated_list1,
+	sequence::{pair, preceded, terminated},
+};
+use std::collections::HashMap;
+
+/// An operand as written, before labels are resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arg<'a> {
+	Operand(Operand),
+	Label(&'a [u8]),
+}
+
+/// A line of source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Line<'a> {
+	label: Option<&'a [u8]>,
+	instr: Option<(Op, Vec<Arg<'a>>)>,
+}
+
+fn is_space(c: u8) -> bool {
+	c == b' ' || c == b'\t'
+}
+
+fn label(i: &[u8]) -> IResult<&[u8], &[u8]> {
+	recognize(pair(
+		alt((alpha1, take_while1(|c| c == b'_'))),
+		take_while(|c: u8| c.is_ascii_alphanumeric() || c == b'_'),
+	))(i)
+}
+
+fn operands(op: Op) -> impl FnMut(&[u8]) -> IResult<&[u8], Vec<Arg>> {
+	move |mut i| {
+		let mut args = vec![];
+		for kind in op.operands() {
+			let (rest, _) = context("space", take_while1(is_space))(i)?;
+			let (rest, arg) = match kind {
+				Kind::Imm => context(
+					"immediate or label",
+					alt((
+						map(immediate, |n| Arg::Operand(Operand::Imm(n))),
+						map(label, Arg::Label),
+					)),
+				)(rest)?,
+				Kind::Reg => {
+					map(register, |r| Arg::Operand(Operand::Reg(r)))(rest)?
+				}
+			};
+			args.push(arg);
+			i = rest;
+		}
+		Ok((i, args))
+	}
+}
+
+fn instruction(
+	isa: Isa,
+) -> impl FnMut(&[u8]) -> IResult<&[u8], (Op, Vec<Arg>)> {
+	move |i| {
+		let (i, op) = context("opcode", map_opt(alpha1, |n| isa.opcode(n)))(i)?;
+		let (i, args) = cut(operands(op))(i)?;
+		Ok((i, (op, args)))
+	}
+}
+
+fn line(isa: Isa) -> impl FnMut(&[u8]) -> IResult<&[u8], Line> {
+	move |i| {
+		let (i, _) = take_while(is_space)(i)?;
+		let (i, label) =
+			opt(terminated(label, pair(char(':'), take_while(is_space))))(i)?;
+		let (i, instr) = match i.first() {
+			None | Some(b'\n') | Some(b';') => (i, None),
+			Some(_) => map(cut(instruction(isa)), Some)(i)?,
+		};
+		let (i, _) = take_while(is_space)(i)?;
+		let (i, _) = opt(preceded(char(';'), not_line_ending))(i)?;
+		let (i, _) = cut(context("end of line", end_of_line))(i)?;
+		Ok((i, Line { label, instr }))
+	}
+}
+
+fn end_of_line(i: &[u8]) -> IResult<&[u8], &[u8]> {
+	peek(alt((recognize(newline), eof)))(i)
+}
+
+/// Offset of `part` into `src`, which it is a slice of.
+fn offset(src: &[u8], part: &[u8]) -> usize {
+	part.as_ptr() as usize - src.as_ptr() as usize
+}
+
+/// Assemble `src` for `isa` into a program.
+pub fn assemble_with(isa: Isa, src: &[u8]) -> Result<Vec<Instr>, Error> {
+	let lines =
+		parse::parse_complete(separated_list1(newline, line(isa)), src)?;
+	link(src, lines, None)
+}
+
+/// Assemble `src` for `isa`, leaving out what does not assemble and
+/// returning an error for each such part.
+pub fn assemble_lenient_with(
+	isa: Isa,
+	src: &[u8],
+) -> (Vec<Instr>, Vec<ParseError>) {
+	let (lines, mut errors) = parse::lenient(line(isa), b"\n", src);
+	let program = link(src, lines, Some(&mut errors))
+		.expect("Lenient linking does not fail");
+	errors.sort_by_key(|e| (e.line, e.column));
+	(program, errors)
+}
+
+/// Fail with `msg` about `part` of `src`, or if `errors` are collected
+/// add one for `expected` not found there.
+fn problem(
+	src: &[u8],
+	part: &[u8],
+	errors: Option<&mut Vec<ParseError>>,
+	expected: &str,
+	msg: String,
+) -> Result<(), Error> {
+	let offset = offset(src, part);
+	match errors {
+		Some(errors) => {
+			errors.push(ParseError::new(src, offset, expected.into()));
+			Ok(())
+		}
+		None => {
+			let (line, col) = parse::position(src, offset);
+			Err(Error::Invalid(format!(
+				"line {}, col {}: {}",
+				line, col, msg
+			)))
+		}
+	}
+}
+
+/// Resolve the labels of `lines`, failing on the first one that cannot
+/// be, or with `errors` leaving it out.
+fn link<'a>(
+	src: &'a [u8],
+	mut lines: Vec<Line<'a>>,
+	mut errors: Option<&mut Vec<ParseError>>,
+) -> Result<Vec<Instr>, Error> {
+	let mut defined: HashMap<&[u8], &[u8]> = HashMap::new();
+	for l in &mut lines {
+		if let Some(name) = l.label {
+			match defined.get(name) {
+				Some(&first) => {
+					let (line, _) = parse::position(src, offset(src, first));
+					let msg = format!(
+						"label {:?} is already defined on line {}",
+						String::from_utf8_lossy(name),
+						line
+					);
+					problem(
+						src,
+						name,
+						errors.as_deref_mut(),
+						"new label",
+						msg,
+					)?;
+					l.label = None;
+				}
+				None => {
+					defined.insert(name, name);
+				}
+			}
+		}
+	}
+	for l in &mut lines {
+		let undefined = l.instr.as_ref().and_then(|(_, args)| {
+			args.iter().find_map(|arg| match arg {
+				Arg::Label(name) if !defined.contains_key(name) => Some(*name),
+				_ => None,
+			})
+		});
+		if let Some(name) = undefined {
+			let msg =
+				format!("undefined label {:?}", String::from_utf8_lossy(name));
+			problem(src, name, errors.as_deref_mut(), "defined label", msg)?;
+			l.instr = None;
+		}
+	}
+
+	let mut labels = HashMap::new();
+	let mut pc = 0;
+	for l in &lines {
+		if let Some(name) = l.label {
+			labels.insert(name, pc);
+		}
+		if l.instr.is_some() {
+			pc += 1;
+		}
+	}
+
+	let mut program = vec![];
+	for (op, args) in lines.into_iter().filter_map(|l| l.instr) {
+		let pc = program.len();
+		let operands = args
+			.into_iter()
+			.map(|arg| match arg {
+				Arg::Operand(o) => o,
+				Arg::Label(name) => {
+					Operand::Imm(Immediate::between(pc, labels[name]))
+				}
+			})
+			.collect::<Vec<_>>();
+		let instr = Instr::new(op, &operands)
+			.expect("Operands are parsed for the opcode");
+		program.push(instr);
+	}
+	Ok(program)
+}
+
+/// Assemble `src` for the full instruction set.
+pub fn assemble(src: &[u8]) -> Result<Vec<Instr>, Error> {
+	assemble_with(Isa::FULL, src)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{bytecode::disassemble, isa::tests::instrs, program};
+	use proptest::prelude::*;
+
+	#[test]
+	fn test_assemble() {
+		let src = b"; count down from 3
+        acc +3
+loop:   acc -1
+
+        jnz loop   ; until acc is zero
+	jmp\tend
+        halt
+end:
+";
+		let p = assemble(src).expect("Failed to assemble");
+		assert_eq!("acc +3\nacc -1\njnz -1\njmp +2\nhalt\n", disassemble(&p));
+	}
+
+	#[test]
+	fn test_assemble_label_only_line() {
+		let p = assemble(b"start:\nnop start\nnext: jmp start").unwrap();
+		assert_eq!(
+			vec![Instr::Nop(Immediate::Pos(0)), Instr::Jmp(Immediate::Neg(1))],
+			p
+		);
+	}
+
+	#[test]
+	fn test_undefined_label() {
+		match assemble(b"acc +1\njmp nowhere\n") {
+			Err(Error::Invalid(msg)) => {
+				assert_eq!("line 2, col 5: undefined label \"nowhere\"", msg)
+			}
+			r => panic!("Unexpected result: {:?}", r),
+		}
+	}
+
+	#[test]
+	fn test_duplicate_label() {
+		match assemble(b"a: nop +0\nb: acc +1\n a: jmp a\n") {
+			Err(Error::Invalid(msg)) => assert_eq!(
+				"line 3, col 2: label \"a\" is already defined on line 1",
+				msg
+			),
+			r => panic!("Unexpected result: {:?}", r),
+		}
+	}
+
+	#[test]
+	fn test_syntax_errors() {
+		let error = |src: &[u8]| match assemble(src) {
+			Err(Error::Parse(e)) => e.to_string(),
+			r => panic!("Unexpected result: {:?}", r),
+		};
+		assert_eq!(
+			"line 2, col 1: expected opcode, found \"foo +1\"",
+			error(b"acc +1\nfoo +1\n")
+		);
+		assert_eq!(
+			"line 1, col 5: expected immediate or label, found \"+x\"",
+			error(b"acc +x\n")
+		);
+		assert_eq!(
+			"line 1, col 7: expected end of line, found \"x\"",
+			error(b"acc +1x\n")
+		);
+		assert_eq!(
+			"line 1, col 4: expected space, found end of line",
+			error(b"jmp\n")
+		);
+	}
+
+	#[test]
+	fn test_assemble_lenient() {
+		let src = b"; count down
+	acc +3
+loop:	acc -1
+	jnz loop
+
+	halt
+";
+		let (p, errors) = assemble_lenient_with(Isa::FULL, src);
+		assert_eq!(assemble(src).unwrap(), p);
+		assert!(errors.is_empty());
+
+		// Bad lines are left out, and labels still point past them.
+		let src = b"a: acc +1\nfoo\nb: nop a\na: jmp b\njmp c\njmp a\n";
+		let (p, errors) = assemble_lenient_with(Isa::FULL, src);
+		assert_eq!("acc +1\nnop -1\njmp -1\njmp -3\n", disassemble(&p));
+		let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+		assert_eq!(
+			vec![
+				"line 2, col 1: expected opcode, found \"foo\"",
+				"line 4, col 1: expected new label, found \"a: jmp b\"",
+				"line 5, col 5: expected defined label, found \"c\"",
+			],
+			errors
+		);
+	}
+
+	#[test]
+	fn test_original_isa() {
+		assert!(assemble_with(Isa::ORIGINAL, b"acc +1\n").is_ok());
+		assert!(assemble_with(Isa::ORIGINAL, b"mul +1\n").is_err());
+	}
+
+	proptest! {
+		#[test]
+		fn prop_plain_source(p in prop::collection::vec(instrs(), 1..40)) {
+			let text = disassemble(&p);
+			let plain = parse::parse_complete(program, text.as_bytes());
+			let r = assemble(text.as_bytes()).expect("Failed to assemble");
+			prop_assert_eq!(plain.expect("Failed to parse"), r);
+		}
+	}
+}
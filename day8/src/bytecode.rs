@@ -0,0 +1,202 @@
+//! Text and binary forms of console programs.
+//!
+//! A program is encoded as the magic `D8BC` and a version byte followed
+//! by the number of instructions and the instructions. Each instruction
+//! is a byte for the opcode and sign of its immediate argument, then its
+//! arguments, immediates as LEB128 varints and registers a byte each.
+//! Traces use the same encoding for the instruction of each step.
+
+use crate::{
+	isa::{Kind, Operand},
+	Immediate, Instr, Op, Reg,
+};
+use aoc_common::Error;
+use std::convert::TryFrom;
+
+const MAGIC: &[u8] = b"D8BC";
+const VERSION: u8 = 1;
+
+pub(crate) fn zigzag(n: i64) -> u64 {
+	((n << 1) ^ (n >> 63)) as u64
+}
+
+pub(crate) fn unzigzag(n: u64) -> i64 {
+	(n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+	while n >= 0x80 {
+		out.push(n as u8 | 0x80);
+		n >>= 7;
+	}
+	out.push(n as u8);
+}
+
+pub(crate) fn read_varint(
+	data: &[u8],
+	pos: &mut usize,
+) -> Result<u64, &'static str> {
+	let mut n = 0_u64;
+	for shift in (0..64).step_by(7) {
+		let b = *data.get(*pos).ok_or("truncated data")?;
+		*pos += 1;
+		n |= u64::from(b & 0x7f) << shift;
+		if b & 0x80 == 0 {
+			return Ok(n);
+		}
+	}
+	Err("varint is too long")
+}
+
+pub(crate) fn write_instr(out: &mut Vec<u8>, instr: &Instr) {
+	let tag = out.len();
+	out.push((instr.op() as u8) << 1);
+	for o in instr.operands() {
+		match o {
+			Operand::Imm(Immediate::Pos(n)) => write_varint(out, n),
+			Operand::Imm(Immediate::Neg(n)) => {
+				out[tag] |= 1;
+				write_varint(out, n);
+			}
+			Operand::Reg(r) => out.push(r as u8),
+		}
+	}
+}
+
+pub(crate) fn read_instr(
+	data: &[u8],
+	pos: &mut usize,
+) -> Result<Instr, &'static str> {
+	let tag = *data.get(*pos).ok_or("truncated data")?;
+	*pos += 1;
+	let op = *Op::ALL
+		.get(usize::from(tag >> 1))
+		.ok_or("unknown instruction")?;
+	let mut operands = vec![];
+	for kind in op.operands() {
+		operands.push(match kind {
+			Kind::Imm => {
+				let n = read_varint(data, pos)?;
+				Operand::Imm(match tag & 1 {
+					0 => Immediate::Pos(n),
+					_ => Immediate::Neg(n),
+				})
+			}
+			Kind::Reg => {
+				let r = *data.get(*pos).ok_or("truncated data")?;
+				*pos += 1;
+				Operand::Reg(
+					*Reg::ALL.get(usize::from(r)).ok_or("unknown register")?,
+				)
+			}
+		});
+	}
+	Instr::new(op, &operands).ok_or("bad operands")
+}
+
+/// The program as text, one instruction per line, which `program` parses
+/// back to the same program unless it is empty.
+pub fn disassemble(p: &[Instr]) -> String {
+	p.iter().map(|instr| format!("{}\n", instr)).collect()
+}
+
+/// The program in the binary format.
+pub fn encode(p: &[Instr]) -> Vec<u8> {
+	let mut out = MAGIC.to_vec();
+	out.push(VERSION);
+	write_varint(&mut out, p.len() as u64);
+	for instr in p {
+		write_instr(&mut out, instr);
+	}
+	out
+}
+
+/// Program from the binary format.
+pub fn decode(data: &[u8]) -> Result<Vec<Instr>, Error> {
+	match data.get(..MAGIC.len() + 1) {
+		Some([m @ .., v]) if m == MAGIC && *v == VERSION => {}
+		Some([m @ .., v]) if m == MAGIC => {
+			return Err(Error::Invalid(format!(
+				"unsupported bytecode version {}",
+				v
+			)))
+		}
+		_ => return Err(Error::Invalid("not bytecode".into())),
+	}
+	let mut pos = MAGIC.len() + 1;
+	let invalid = |what: &str| Error::Invalid(format!("bytecode {}", what));
+	let len = read_varint(data, &mut pos).map_err(invalid)?;
+	let len = usize::try_from(len).map_err(|_| invalid("is too long"))?;
+	// Every instruction takes at least a byte.
+	let mut p = Vec::with_capacity(len.min(data.len()));
+	for n in 0..len {
+		let instr = read_instr(data, &mut pos).map_err(|e| {
+			Error::Invalid(format!("{} in bytecode instruction {}", e, n))
+		})?;
+		p.push(instr);
+	}
+	if pos != data.len() {
+		return Err(invalid("has trailing data"));
+	}
+	Ok(p)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{isa::tests::instrs, program, Day8};
+	use aoc_common::{parse, Solution};
+	use proptest::prelude::*;
+
+	const EXAMPLE: &[u8] = b"nop +0\nacc +1\njmp +4\nacc +3\njmp -3\n\
+		acc -99\nacc +1\njmp -4\nacc +6\n";
+
+	#[test]
+	fn test_disassemble() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		assert_eq!(EXAMPLE, disassemble(&p).as_bytes());
+		assert_eq!("", disassemble(&[]));
+	}
+
+	#[test]
+	fn test_encode() {
+		let p = Day8.parse(b"acc +1\njmp -2\nmov a acc\n").unwrap();
+		assert_eq!(
+			&b"D8BC\x01\x03\x00\x01\x03\x02\x0c\x01\x00"[..],
+			&encode(&p)[..]
+		);
+	}
+
+	#[test]
+	fn test_decode_errors() {
+		assert!(decode(b"").is_err());
+		assert!(decode(b"D8TR\x01\x00").is_err());
+		assert!(decode(b"D8BC\x02\x00").is_err());
+		assert!(decode(b"D8BC\x01").is_err());
+		assert!(decode(b"D8BC\x01\x01\x00").is_err());
+		assert!(decode(b"D8BC\x01\x01\x7e\x00").is_err());
+		assert!(decode(b"D8BC\x01\x01\x0c\x01\x05").is_err());
+		assert!(decode(b"D8BC\x01\x00\x00").is_err());
+		assert_eq!(Vec::<Instr>::new(), decode(b"D8BC\x01\x00").unwrap());
+	}
+
+	/// Programs of any instructions, which need not run.
+	fn programs() -> impl Strategy<Value = Vec<Instr>> {
+		prop::collection::vec(instrs(), 1..40)
+	}
+
+	proptest! {
+		#[test]
+		fn prop_disassemble_round_trip(p in programs()) {
+			let text = disassemble(&p);
+			let r = parse::parse_complete(program, text.as_bytes());
+			prop_assert_eq!(p, r.expect("Failed to parse"));
+		}
+
+		#[test]
+		fn prop_encode_round_trip(p in programs()) {
+			let r = decode(&encode(&p)).expect("Failed to decode");
+			prop_assert_eq!(p, r);
+		}
+	}
+}
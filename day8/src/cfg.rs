@@ -0,0 +1,418 @@
+//! Static control-flow analysis of console programs.
+//!
+//! The graph has a node per instruction and one for the end of the
+//! program, with an edge to each instruction that may run next. Jumps
+//! outside the program have no edge. Conditional jumps get an edge to
+//! both targets, so for programs using them reachability and
+//! termination are what may happen rather than what will.
+
+use crate::{Instr, VirtualMachine, VmFault};
+use aoc_common::Error;
+use std::io::{self, Write};
+
+/// How the path from the start ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathEnd {
+	/// Moves past the last instruction or halts.
+	Terminates,
+	/// About to run the instruction at this pc a second time.
+	Loop(usize),
+	/// Jumps out of the program from this pc.
+	Fault(usize),
+	/// Reaches a conditional jump at this pc, where the path is no longer
+	/// known.
+	Branch(usize),
+}
+
+/// Instructions run from the start, in order, as far as the control flow
+/// is known without running the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+	pub pcs: Vec<usize>,
+	pub end: PathEnd,
+}
+
+impl Path {
+	/// The instructions repeated forever if the path loops.
+	pub fn cycle(&self) -> &[usize] {
+		match self.end {
+			PathEnd::Loop(pc) => {
+				let start = self.pcs.iter().position(|&p| p == pc);
+				&self.pcs[start.unwrap_or(self.pcs.len())..]
+			}
+			_ => &[],
+		}
+	}
+}
+
+/// Control-flow graph of a program. The end of the program is the node
+/// at `program.len()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg<'a> {
+	program: &'a [Instr],
+	succ: Vec<Vec<usize>>,
+}
+
+/// Instructions that may run after the one at `pc`.
+fn successors(pc: usize, instr: &Instr, len: usize) -> Vec<usize> {
+	let mut succ = match instr {
+		Instr::Jmp(i) => vec![i.offset(pc)],
+		Instr::Jz(i) | Instr::Jnz(i) => vec![Some(pc + 1), i.offset(pc)],
+		Instr::Halt() => vec![Some(len)],
+		_ => vec![Some(pc + 1)],
+	}
+	.into_iter()
+	.flatten()
+	.filter(|&n| n <= len)
+	.collect::<Vec<_>>();
+	succ.dedup();
+	succ
+}
+
+/// Where the changed instruction would continue if the `jmp` or `nop` at
+/// `pc` were swapped, or `None` for other instructions.
+fn flipped(pc: usize, instr: &Instr) -> Option<Option<usize>> {
+	match instr {
+		Instr::Jmp(_) => Some(Some(pc + 1)),
+		Instr::Nop(i) => Some(i.offset(pc)),
+		_ => None,
+	}
+}
+
+impl<'a> Cfg<'a> {
+	pub fn new(program: &'a [Instr]) -> Self {
+		let len = program.len();
+		let succ = program
+			.iter()
+			.enumerate()
+			.map(|(pc, instr)| successors(pc, instr, len))
+			.collect();
+		Cfg { program, succ }
+	}
+
+	/// Number of nodes, the instructions and the end.
+	pub fn nodes(&self) -> usize {
+		self.program.len() + 1
+	}
+
+	/// Nodes that may follow `pc`.
+	pub fn successors(&self, pc: usize) -> &[usize] {
+		self.succ.get(pc).map_or(&[], Vec::as_slice)
+	}
+
+	fn predecessors(&self) -> Vec<Vec<usize>> {
+		let mut pred = vec![vec![]; self.nodes()];
+		for (pc, succ) in self.succ.iter().enumerate() {
+			for &n in succ {
+				pred[n].push(pc);
+			}
+		}
+		pred
+	}
+
+	/// Nodes reachable from the start, by pc.
+	pub fn reachable(&self) -> Vec<bool> {
+		let mut seen = vec![false; self.nodes()];
+		let mut todo = vec![0];
+		while let Some(pc) = todo.pop() {
+			if !std::mem::replace(&mut seen[pc], true) {
+				todo.extend(self.successors(pc));
+			}
+		}
+		seen
+	}
+
+	/// Nodes from which the end can be reached, by pc, found by walking
+	/// the edges backwards from the end.
+	pub fn terminating(&self) -> Vec<bool> {
+		let pred = self.predecessors();
+		let mut seen = vec![false; self.nodes()];
+		let mut todo = vec![self.program.len()];
+		while let Some(pc) = todo.pop() {
+			if !std::mem::replace(&mut seen[pc], true) {
+				todo.extend(&pred[pc]);
+			}
+		}
+		seen
+	}
+
+	/// Instructions that can not be reached from the start.
+	pub fn unreachable(&self) -> Vec<usize> {
+		let reachable = self.reachable();
+		(0..self.program.len())
+			.filter(|&pc| !reachable[pc])
+			.collect()
+	}
+
+	/// Follow the edges from the start while there is exactly one.
+	pub fn path(&self) -> Path {
+		let end = self.program.len();
+		let mut seen = vec![false; self.nodes()];
+		let mut pcs = vec![];
+		let mut pc = 0;
+		let end = loop {
+			if pc == end {
+				break PathEnd::Terminates;
+			}
+			if std::mem::replace(&mut seen[pc], true) {
+				break PathEnd::Loop(pc);
+			}
+			pcs.push(pc);
+			match self.successors(pc) {
+				[] => break PathEnd::Fault(pc),
+				[n] => pc = *n,
+				_ => break PathEnd::Branch(pc),
+			}
+		};
+		Path { pcs, end }
+	}
+
+	/// The `jmp` and `nop` instructions that make the program terminate
+	/// when swapped, in order of pc.
+	///
+	/// A swap on the path from the start terminates if the changed
+	/// instruction continues somewhere that reaches the end without
+	/// joining the path at or before the swap, which would make it loop.
+	/// Swaps off the path terminate if the program already does.
+	pub fn fixes(&self) -> Vec<usize> {
+		let path = self.path();
+		let terminating = self.terminating();
+		let reachable = self.reachable();
+
+		// Position on the path of every node on it, counting the end as
+		// the last one if the path terminates.
+		let mut pos = vec![None; self.nodes()];
+		for (k, &pc) in path.pcs.iter().enumerate() {
+			pos[pc] = Some(k);
+		}
+		if path.end == PathEnd::Terminates {
+			pos[self.program.len()] = Some(path.pcs.len());
+		}
+
+		// Position where the only way on from each node joins the path,
+		// `usize::MAX` if it never does or is not known. This is only
+		// asked of nodes that reach the end, so the way on can not loop.
+		let mut join = pos.clone();
+		let mut join_from = |pc: usize| {
+			let mut chain = vec![];
+			let mut n = pc;
+			let k = loop {
+				if let Some(k) = join[n] {
+					break k;
+				}
+				match self.successors(n) {
+					[next] => {
+						chain.push(n);
+						n = *next;
+					}
+					_ => {
+						chain.push(n);
+						break usize::MAX;
+					}
+				}
+			};
+			for n in chain {
+				join[n] = Some(k);
+			}
+			k
+		};
+
+		self.program
+			.iter()
+			.enumerate()
+			.filter(|&(pc, instr)| {
+				let to = match flipped(pc, instr) {
+					Some(Some(to)) if to < self.nodes() => to,
+					_ => return false,
+				};
+				match (pos[pc], path.end) {
+					(Some(k), _) => terminating[to] && join_from(to) > k,
+					(None, PathEnd::Branch(_)) => {
+						reachable[pc] && terminating[to]
+					}
+					(None, end) => end == PathEnd::Terminates,
+				}
+			})
+			.map(|(pc, _)| pc)
+			.collect()
+	}
+
+	/// Write the graph in Graphviz DOT format. Unreachable instructions
+	/// are dashed, the loop on the path from the start is red and
+	/// instructions that fix the program when swapped are green.
+	pub fn write_dot<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+		self.dot(w).map_err(Error::Write)
+	}
+
+	fn dot<W: Write>(&self, w: &mut W) -> io::Result<()> {
+		let path = self.path();
+		let cycle = path.cycle();
+		let fixes = self.fixes();
+		let reachable = self.reachable();
+		let node = |pc: usize| {
+			if pc == self.program.len() {
+				"end".to_string()
+			} else {
+				format!("n{}", pc)
+			}
+		};
+
+		writeln!(w, "digraph program {{")?;
+		writeln!(w, "\tnode [shape=box, fontname=monospace];")?;
+		for (pc, instr) in self.program.iter().enumerate() {
+			let mut attrs = vec![format!("label=\"{}: {}\"", pc, instr)];
+			if !reachable[pc] {
+				attrs.push("style=dashed".into());
+			}
+			if fixes.contains(&pc) {
+				attrs.push("color=green".into());
+			} else if cycle.contains(&pc) {
+				attrs.push("color=red".into());
+			}
+			writeln!(w, "\t{} [{}];", node(pc), attrs.join(", "))?;
+		}
+		writeln!(w, "\tend [shape=doublecircle];")?;
+		for (pc, succ) in self.succ.iter().enumerate() {
+			for &n in succ {
+				writeln!(w, "\t{} -> {};", node(pc), node(n))?;
+			}
+		}
+		writeln!(w, "}}")
+	}
+}
+
+/// Accumulator once the program terminates after changing one `jmp` to
+/// `nop` or `nop` to `jmp`, or `None` if no single change makes it
+/// terminate or it already does.
+///
+/// Unlike [`crate::find_answer_part2`] the change is found without
+/// running the program. The changed program is then run, which with
+/// conditional jumps may show it still loops, and the next change is run,
+/// or that it runs past the step limit, which fails.
+pub fn find_answer_part2(p: &[Instr]) -> Result<Option<i64>, Error> {
+	let cfg = Cfg::new(p);
+	if cfg.path().end == PathEnd::Terminates {
+		return Ok(None);
+	}
+	for pc in cfg.fixes() {
+		let mut fixed = p.to_vec();
+		fixed[pc] = match &p[pc] {
+			Instr::Jmp(i) => Instr::Nop(*i),
+			Instr::Nop(i) => Instr::Jmp(*i),
+			_ => continue,
+		};
+		// Conditional jumps may still make the changed program loop.
+		match VirtualMachine::new().run(&fixed) {
+			Ok(acc) => return Ok(Some(acc)),
+			Err(fault @ VmFault::StepLimit(..)) => return Err(fault.into()),
+			Err(_) => {}
+		}
+	}
+	Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{tests::programs, tests::run, Day8};
+	use aoc_common::Solution;
+	use proptest::prelude::*;
+
+	const EXAMPLE: &[u8] = b"nop +0\nacc +1\njmp +4\nacc +3\njmp -3\n\
+		acc -99\nacc +1\njmp -4\nacc +6\n";
+
+	#[test]
+	fn test_path() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let path = Cfg::new(&p).path();
+		assert_eq!(vec![0, 1, 2, 6, 7, 3, 4], path.pcs);
+		assert_eq!(PathEnd::Loop(1), path.end);
+		assert_eq!(&[1, 2, 6, 7, 3, 4], path.cycle());
+	}
+
+	#[test]
+	fn test_analysis() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let cfg = Cfg::new(&p);
+		assert_eq!(vec![5, 8], cfg.unreachable());
+		assert_eq!(
+			(0..10).map(|pc| pc >= 8).collect::<Vec<_>>(),
+			cfg.terminating()
+		);
+		assert_eq!(vec![7], cfg.fixes());
+		assert_eq!(Some(8), find_answer_part2(&p).expect("Failed to run"));
+	}
+
+	#[test]
+	fn test_conditional() {
+		let p = Day8
+			.parse(b"jz +2\njmp +0\nacc +1\nhalt\n")
+			.expect("Failed to parse input");
+		let cfg = Cfg::new(&p);
+		assert_eq!(&[1, 2], cfg.successors(0));
+		assert_eq!(&[4], cfg.successors(3));
+		assert_eq!(PathEnd::Branch(0), cfg.path().end);
+		assert_eq!(vec![1], cfg.fixes());
+	}
+
+	#[test]
+	fn test_dot() {
+		let p = Day8
+			.parse(b"nop +0\njmp -1\nacc +1\n")
+			.expect("Failed to parse input");
+		let mut out = vec![];
+		Cfg::new(&p).write_dot(&mut out).expect("Failed to write");
+		assert_eq!(
+			"digraph program {
+	node [shape=box, fontname=monospace];
+	n0 [label=\"0: nop +0\", color=red];
+	n1 [label=\"1: jmp -1\", color=green];
+	n2 [label=\"2: acc +1\", style=dashed];
+	end [shape=doublecircle];
+	n0 -> n1;
+	n1 -> n0;
+	n2 -> end;
+}
+",
+			String::from_utf8(out).unwrap()
+		);
+	}
+
+	proptest! {
+		#[test]
+		fn prop_fixes_match_every_flip(p in programs()) {
+			let fixes = (0..p.len())
+				.filter(|&pc| {
+					let mut flipped = p.clone();
+					flipped[pc] = match p[pc] {
+						Instr::Jmp(i) => Instr::Nop(i),
+						Instr::Nop(i) => Instr::Jmp(i),
+						_ => return false,
+					};
+					run(&flipped).is_ok()
+				})
+				.collect::<Vec<_>>();
+			prop_assert_eq!(fixes, Cfg::new(&p).fixes());
+		}
+
+		#[test]
+		fn prop_part2_matches(p in programs()) {
+			let r = find_answer_part2(&p).expect("Failed to run");
+			let dynamic = crate::find_answer_part2(&p).expect("Failed to run");
+			prop_assert_eq!(dynamic.is_some(), r.is_some());
+			if let Some(acc) = r {
+				let fixes = Cfg::new(&p).fixes();
+				let found = fixes.into_iter().any(|pc| {
+					let mut flipped = p.clone();
+					flipped[pc] = match p[pc] {
+						Instr::Jmp(i) => Instr::Nop(i),
+						Instr::Nop(i) => Instr::Jmp(i),
+						_ => return false,
+					};
+					run(&flipped) == Ok(acc)
+				});
+				prop_assert!(found);
+			}
+		}
+	}
+}
@@ -0,0 +1,253 @@
+//! The loop a program runs into.
+//!
+//! Found by running the program until a [`LoopDetector`] finds it loops,
+//! as `VirtualMachine::run` does. The body starts where the run was last
+//! at the same pc with no conditional jump since, or in the same state,
+//! so that it repeats forever.
+
+use crate::{
+	repair::{apply, Edit},
+	Instr, LoopDetector, VirtualMachine, VmFault,
+};
+use std::fmt;
+
+/// A loop reached from the start of a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle {
+	/// Pc of the instruction run a second time.
+	pub entry: usize,
+	/// Instructions of an iteration in the order they run, from the entry.
+	pub body: Vec<(usize, Instr)>,
+	/// Accumulator when the loop is entered.
+	pub acc: i64,
+	/// Change of the accumulator in an iteration.
+	pub acc_delta: i64,
+	/// Instructions of the body that make the program terminate when the
+	/// `jmp` or `nop` is swapped, in order of pc.
+	pub escapes: Vec<usize>,
+}
+
+impl Cycle {
+	/// Whether a single swap in the body makes the program terminate.
+	pub fn escapable(&self) -> bool {
+		!self.escapes.is_empty()
+	}
+}
+
+impl fmt::Display for Cycle {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(
+			f,
+			"Loop at pc {} of {} instructions, entered with acc {}, \
+			 acc {:+} per iteration",
+			self.entry,
+			self.body.len(),
+			self.acc,
+			self.acc_delta
+		)?;
+		for (pc, instr) in &self.body {
+			write!(f, "{:>6}: {}", pc, instr)?;
+			if self.escapes.contains(pc) {
+				write!(f, "  ; swap to escape")?;
+			}
+			writeln!(f)?;
+		}
+		match self.escapes.as_slice() {
+			[] => writeln!(f, "No single swap escapes"),
+			escapes => {
+				let pcs =
+					escapes.iter().map(usize::to_string).collect::<Vec<_>>();
+				writeln!(f, "Escapes: {}", pcs.join(" "))
+			}
+		}
+	}
+}
+
+/// The loop `p` runs into from the start, or `None` if it terminates.
+///
+/// Fails with the fault the run stops on, `StepLimit` if it is not found
+/// to loop or terminate, or `AccOverflow` at the entry if the change to
+/// acc in one iteration does not fit.
+pub fn find(p: &[Instr]) -> Result<Option<Cycle>, VmFault> {
+	let mut vm = VirtualMachine::new();
+	let mut detector = LoopDetector::new(p);
+	// pc, acc and registers before each step.
+	let mut states = vec![];
+	loop {
+		match detector.check(&vm, p) {
+			Ok(()) => {}
+			Err(VmFault::InfiniteLoopDetected(..)) => break,
+			Err(fault) => return Err(fault),
+		}
+		states.push((vm.pc, vm.acc, vm.regs));
+		if vm.step(p)?.is_some() {
+			return Ok(None);
+		}
+	}
+	let entry = vm.pc;
+	let now = (vm.pc, vm.acc, vm.regs);
+	let mut conditional = false;
+	let start = (0..states.len()).rev().find(|&n| {
+		let pc = states[n].0;
+		conditional |= matches!(p[pc], Instr::Jz(_) | Instr::Jnz(_));
+		pc == entry && (!conditional || states[n] == now)
+	});
+	let start = start.expect("The loop repeats a step of the run");
+	let body = states[start..]
+		.iter()
+		.map(|&(pc, ..)| (pc, p[pc].clone()))
+		.collect::<Vec<_>>();
+
+	let mut escapes = body
+		.iter()
+		.filter(|(_, instr)| matches!(instr, Instr::Jmp(_) | Instr::Nop(_)))
+		.map(|&(pc, _)| pc)
+		.filter(|&pc| {
+			let (q, _) = apply(p, &[Edit::Swap(pc)]);
+			VirtualMachine::new().run(&q).is_ok()
+		})
+		.collect::<Vec<_>>();
+	escapes.sort_unstable();
+	escapes.dedup();
+
+	let acc = states[start].1;
+	let acc_delta = vm
+		.acc
+		.checked_sub(acc)
+		.ok_or_else(|| VmFault::AccOverflow(entry, p[entry].clone()))?;
+	Ok(Some(Cycle {
+		entry,
+		body,
+		acc,
+		acc_delta,
+		escapes,
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		asm::assemble, cfg::Cfg, find_answer_part1, tests::programs, Day8,
+		Immediate,
+	};
+	use aoc_common::Solution;
+	use proptest::prelude::*;
+
+	const EXAMPLE: &[u8] = b"nop +0\nacc +1\njmp +4\nacc +3\njmp -3\n\
+		acc -99\nacc +1\njmp -4\nacc +6\n";
+
+	#[test]
+	fn test_find() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let cycle = find(&p).unwrap().expect("Missing loop");
+		assert_eq!(1, cycle.entry);
+		assert_eq!(
+			vec![1, 2, 6, 7, 3, 4],
+			cycle.body.iter().map(|s| s.0).collect::<Vec<_>>()
+		);
+		assert_eq!(Instr::Jmp(Immediate::Neg(4)), cycle.body[3].1);
+		assert_eq!(0, cycle.acc);
+		assert_eq!(5, cycle.acc_delta);
+		assert_eq!(vec![7], cycle.escapes);
+		assert!(cycle.escapable());
+	}
+
+	#[test]
+	fn test_find_conditional() {
+		// The countdown comes back to pc 1 but ends, and `jmp +0` loops.
+		let p = assemble(b"acc +2\nloop: acc -1\njnz loop\njmp +0\n").unwrap();
+		let cycle = find(&p).unwrap().expect("Missing loop");
+		assert_eq!(3, cycle.entry);
+		assert_eq!(1, cycle.body.len());
+		assert_eq!((0, 0), (cycle.acc, cycle.acc_delta));
+		assert_eq!(vec![3], cycle.escapes);
+
+		// After a conditional jump a loop without one repeats from the
+		// last time the run was at its entry.
+		let p = assemble(b"acc +1\njnz +1\nloop: acc +1\njmp loop\n").unwrap();
+		let cycle = find(&p).unwrap().expect("Missing loop");
+		assert_eq!(2, cycle.entry);
+		assert_eq!(
+			vec![2, 3],
+			cycle.body.iter().map(|s| s.0).collect::<Vec<_>>()
+		);
+		assert_eq!((1, 1), (cycle.acc, cycle.acc_delta));
+		assert_eq!(vec![3], cycle.escapes);
+
+		// A loop through a conditional jump repeats from the same state.
+		let p = assemble(b"loop: acc +1\nacc -1\njz loop\n").unwrap();
+		let cycle = find(&p).unwrap().expect("Missing loop");
+		assert_eq!(0, cycle.entry);
+		assert_eq!(
+			vec![0, 1, 2],
+			cycle.body.iter().map(|s| s.0).collect::<Vec<_>>()
+		);
+		assert_eq!((0, 0), (cycle.acc, cycle.acc_delta));
+		assert!(!cycle.escapable());
+
+		// Counting up never repeats, so there is no loop to show.
+		let p = assemble(b"acc +1\njnz -1\n").unwrap();
+		assert!(matches!(find(&p), Err(VmFault::StepLimit(..))));
+	}
+
+	#[test]
+	fn test_find_acc_delta_overflow() {
+		// Each step fits, but one iteration moves acc from -MAX to MAX.
+		let p = assemble(
+			b"acc -9223372036854775807
+loop: acc +9223372036854775807
+acc +9223372036854775807
+jmp loop
+",
+		)
+		.unwrap();
+		assert_eq!(
+			Err(VmFault::AccOverflow(1, p[1].clone())),
+			find(&p).map(|_| ())
+		);
+	}
+
+	#[test]
+	fn test_find_terminates() {
+		let p = Day8.parse(b"nop +0\nacc +1\n").unwrap();
+		assert_eq!(Ok(None), find(&p));
+		let p = Day8.parse(b"jmp -1\n").unwrap();
+		assert!(find(&p).is_err());
+	}
+
+	#[test]
+	fn test_display() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let cycle = find(&p).unwrap().expect("Missing loop");
+		assert_eq!(
+			"Loop at pc 1 of 6 instructions, entered with acc 0, \
+			 acc +5 per iteration
+     1: acc +1
+     2: jmp +4
+     6: acc +1
+     7: jmp -4  ; swap to escape
+     3: acc +3
+     4: jmp -3
+Escapes: 7
+",
+			cycle.to_string()
+		);
+	}
+
+	proptest! {
+		#[test]
+		fn prop_matches_part1_and_fixes(p in programs()) {
+			if let Ok(Some(cycle)) = find(&p) {
+				let acc = find_answer_part1(&p).expect("Failed to run");
+				prop_assert_eq!(Some(cycle.acc + cycle.acc_delta), acc);
+				let fixes = Cfg::new(&p)
+					.fixes()
+					.into_iter()
+					.filter(|pc| cycle.body.iter().any(|s| s.0 == *pc))
+					.collect::<Vec<_>>();
+				prop_assert_eq!(fixes, cycle.escapes);
+			}
+		}
+	}
+}
@@ -0,0 +1,562 @@
+//! Interactive step debugger for the handheld console.
+//!
+//! The debugger keeps every state the machine passed through, so that it
+//! can be rewound, and stops running at breakpoints, watchpoints, when an
+//! instruction is about to be executed a second time and when the program
+//! terminates or faults.
+
+use crate::{Instr, Op, Reg, VirtualMachine, VmFault};
+use aoc_common::Error;
+use std::{
+	fmt,
+	io::{BufRead, Write},
+	str,
+};
+
+const HELP: &str = "\
+Commands:
+  step [N]            Execute N instructions, 1 by default (s)
+  continue            Run until something stops execution (c)
+  rewind [N]          Undo N instructions, 1 by default (r)
+  break PC|OPCODE     Stop before executing at PC or an opcode (b)
+  watch [VALUE]       Stop when acc changes, or becomes VALUE (w)
+  delete ID           Remove a breakpoint or watchpoint (d)
+  info                List breakpoints and watchpoints (i)
+  list [N]            Show N instructions around pc, 3 by default (l)
+  print               Show pc, registers and steps taken (p)
+  help                Show this help (h)
+  quit                Exit the debugger (q)
+An empty line repeats the previous command.";
+
+/// Condition that stops execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Point {
+	/// Before executing the instruction at this pc.
+	Pc(usize),
+	/// Before executing an instruction of this kind.
+	Op(Op),
+	/// After an instruction changed acc.
+	AccChange,
+	/// After an instruction set acc to this value.
+	AccEquals(i64),
+}
+
+impl fmt::Display for Point {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Point::Pc(pc) => write!(f, "breakpoint at pc {}", pc),
+			Point::Op(op) => write!(f, "breakpoint on {}", op),
+			Point::AccChange => write!(f, "watchpoint on acc"),
+			Point::AccEquals(n) => write!(f, "watchpoint on acc == {}", n),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+	Step(usize),
+	Continue,
+	Rewind(usize),
+	Break(Point),
+	Delete(usize),
+	Info,
+	List(usize),
+	Print,
+	Help,
+	Quit,
+}
+
+fn count_arg(arg: Option<&str>, default: usize) -> Result<usize, Error> {
+	arg.map_or(Ok(default), |s| {
+		s.parse()
+			.map_err(|_| Error::Usage(format!("Invalid count {:?}", s)))
+	})
+}
+
+impl str::FromStr for Command {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut words = s.split_whitespace();
+		let cmd = words.next().unwrap_or_default();
+		let arg = words.next();
+		if let Some(extra) = words.next() {
+			return Err(Error::Usage(format!(
+				"Unexpected argument {:?}",
+				extra
+			)));
+		}
+		let missing = || Error::Usage(format!("Missing argument for {}", cmd));
+		match cmd {
+			"s" | "step" => Ok(Command::Step(count_arg(arg, 1)?)),
+			"c" | "continue" => Ok(Command::Continue),
+			"r" | "rewind" => Ok(Command::Rewind(count_arg(arg, 1)?)),
+			"b" | "break" => {
+				let arg = arg.ok_or_else(missing)?;
+				match arg.parse() {
+					Ok(pc) => Ok(Command::Break(Point::Pc(pc))),
+					Err(_) => Ok(Command::Break(Point::Op(arg.parse()?))),
+				}
+			}
+			"w" | "watch" => match arg {
+				Some(s) => s
+					.parse()
+					.map(|n| Command::Break(Point::AccEquals(n)))
+					.map_err(|_| {
+						Error::Usage(format!("Invalid value {:?}", s))
+					}),
+				None => Ok(Command::Break(Point::AccChange)),
+			},
+			"d" | "delete" => {
+				let arg = arg.ok_or_else(missing)?;
+				arg.parse()
+					.map(Command::Delete)
+					.map_err(|_| Error::Usage(format!("Invalid id {:?}", arg)))
+			}
+			"i" | "info" => Ok(Command::Info),
+			"l" | "list" => Ok(Command::List(count_arg(arg, 3)?)),
+			"p" | "print" => Ok(Command::Print),
+			"h" | "help" => Ok(Command::Help),
+			"q" | "quit" => Ok(Command::Quit),
+			_ => Err(Error::Usage(format!(
+				"Unknown command {:?}, try help",
+				cmd
+			))),
+		}
+	}
+}
+
+/// Why execution stopped.
+#[derive(Debug)]
+pub enum Stop {
+	/// The requested number of steps were taken.
+	Done,
+	/// A breakpoint or watchpoint with this id was hit.
+	Point(usize, Point),
+	/// The instruction at pc is about to be executed a second time.
+	Loop,
+	/// The program terminated with this acc.
+	Terminated(i64),
+	/// The instruction at pc can not be executed.
+	Fault(VmFault),
+	/// There are no earlier states to rewind to.
+	Start,
+}
+
+impl fmt::Display for Stop {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Stop::Done => Ok(()),
+			Stop::Point(id, p) => write!(f, "Hit {}: {}", id, p),
+			Stop::Loop => write!(f, "Instruction about to run a second time"),
+			Stop::Terminated(acc) => {
+				write!(f, "Program terminated with acc {}", acc)
+			}
+			Stop::Fault(e) => write!(f, "Fault: {}", e),
+			Stop::Start => write!(f, "At start of program"),
+		}
+	}
+}
+
+/// Debugger state for a program.
+#[derive(Debug)]
+pub struct Debugger<'a> {
+	program: &'a [Instr],
+	vm: VirtualMachine,
+	history: Vec<VirtualMachine>,
+	runs: Vec<usize>,
+	points: Vec<(usize, Point)>,
+	next_id: usize,
+}
+
+impl<'a> Debugger<'a> {
+	pub fn new(program: &'a [Instr]) -> Self {
+		Debugger {
+			program,
+			vm: VirtualMachine::new(),
+			history: vec![],
+			runs: vec![0; program.len()],
+			points: vec![],
+			next_id: 1,
+		}
+	}
+
+	pub fn vm(&self) -> &VirtualMachine {
+		&self.vm
+	}
+
+	/// Number of instructions executed to reach the current state.
+	pub fn steps(&self) -> usize {
+		self.history.len()
+	}
+
+	/// Add a breakpoint or watchpoint, returning its id.
+	pub fn add(&mut self, p: Point) -> usize {
+		let id = self.next_id;
+		self.next_id += 1;
+		self.points.push((id, p));
+		id
+	}
+
+	/// Remove the breakpoint or watchpoint `id`, returning it if it
+	/// existed.
+	pub fn delete(&mut self, id: usize) -> Option<Point> {
+		let n = self.points.iter().position(|&(i, _)| i == id)?;
+		Some(self.points.remove(n).1)
+	}
+
+	pub fn points(&self) -> &[(usize, Point)] {
+		&self.points
+	}
+
+	/// Breakpoint stopping execution before the instruction at pc.
+	fn breakpoint(&self) -> Option<(usize, Point)> {
+		let instr = self.program.get(self.vm.pc);
+		self.points.iter().copied().find(|&(_, p)| match p {
+			Point::Pc(pc) => pc == self.vm.pc,
+			Point::Op(op) => instr.map(Instr::op) == Some(op),
+			_ => false,
+		})
+	}
+
+	/// Watchpoint stopping execution after acc changed from `before`.
+	fn watchpoint(&self, before: i64) -> Option<(usize, Point)> {
+		let acc = self.vm.acc;
+		self.points.iter().copied().find(|&(_, p)| match p {
+			Point::AccChange => acc != before,
+			Point::AccEquals(n) => acc == n && acc != before,
+			_ => false,
+		})
+	}
+
+	/// Execute up to `steps` instructions, or until stopped if `None`.
+	///
+	/// Breakpoints and loops are only checked after the first instruction,
+	/// so that execution can continue from where it stopped.
+	pub fn run(&mut self, steps: Option<usize>) -> Stop {
+		let mut n = 0;
+		loop {
+			if n > 0 {
+				if steps == Some(n) {
+					return Stop::Done;
+				}
+				if let Some((id, p)) = self.breakpoint() {
+					return Stop::Point(id, p);
+				}
+				if self.runs.get(self.vm.pc) == Some(&1) {
+					return Stop::Loop;
+				}
+			}
+			let before = self.vm.clone();
+			match self.vm.step(self.program) {
+				Ok(Some(acc)) => return Stop::Terminated(acc),
+				Ok(None) => {}
+				Err(e) => return Stop::Fault(e),
+			}
+			self.runs[before.pc] += 1;
+			let acc = before.acc;
+			self.history.push(before);
+			n += 1;
+			if let Some((id, p)) = self.watchpoint(acc) {
+				return Stop::Point(id, p);
+			}
+		}
+	}
+
+	/// Undo up to `steps` instructions.
+	pub fn rewind(&mut self, steps: usize) -> Stop {
+		for _ in 0..steps {
+			match self.history.pop() {
+				Some(vm) => {
+					self.runs[vm.pc] -= 1;
+					self.vm = vm;
+				}
+				None => return Stop::Start,
+			}
+		}
+		Stop::Done
+	}
+
+	/// Write the instructions within `n` of pc, marking pc with `=>` and
+	/// breakpoints with `*`.
+	pub fn list<W: Write>(&self, w: &mut W, n: usize) -> Result<(), Error> {
+		let pc = self.vm.pc;
+		let start = pc.saturating_sub(n);
+		let end = pc.saturating_add(n + 1).min(self.program.len());
+		for (i, instr) in self.program.iter().enumerate().take(end).skip(start)
+		{
+			let marker = if i == pc { "=>" } else { "  " };
+			let bp = self.points.iter().any(|&(_, p)| match p {
+				Point::Pc(at) => at == i,
+				Point::Op(op) => op == instr.op(),
+				_ => false,
+			});
+			let bp = if bp { "*" } else { " " };
+			writeln!(w, "{}{} {:>5}: {}", marker, bp, i, instr)
+				.map_err(Error::Write)?;
+		}
+		if pc >= self.program.len() {
+			writeln!(w, "=>  {:>5}: <end>", pc).map_err(Error::Write)?;
+		}
+		Ok(())
+	}
+
+	/// Write pc with its instruction and acc.
+	pub fn print<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+		let instr = match self.program.get(self.vm.pc) {
+			Some(instr) => instr.to_string(),
+			None => "<end>".into(),
+		};
+		writeln!(
+			w,
+			"pc {}: {:<10} acc {}  steps {}",
+			self.vm.pc,
+			instr,
+			self.vm.acc,
+			self.steps()
+		)
+		.map_err(Error::Write)?;
+		if self.vm.regs != [0; 4] {
+			let regs = self.vm.regs.iter().zip(&Reg::ALL[1..]);
+			let regs = regs.map(|(n, r)| format!("{} {}", r, n));
+			writeln!(w, "{}", regs.collect::<Vec<_>>().join("  "))
+				.map_err(Error::Write)?;
+		}
+		if !self.vm.output.is_empty() {
+			let out = self.vm.output.iter().map(i64::to_string);
+			writeln!(w, "out {}", out.collect::<Vec<_>>().join(" "))
+				.map_err(Error::Write)?;
+		}
+		Ok(())
+	}
+
+	/// Execute `cmd`, writing its output to `w`. Returns `false` once the
+	/// debugger should exit.
+	pub fn execute<W: Write>(
+		&mut self,
+		cmd: Command,
+		w: &mut W,
+	) -> Result<bool, Error> {
+		let stop = match cmd {
+			Command::Step(n) => self.run(Some(n)),
+			Command::Continue => self.run(None),
+			Command::Rewind(n) => self.rewind(n),
+			Command::Break(p) => {
+				let id = self.add(p);
+				writeln!(w, "Added {}: {}", id, p).map_err(Error::Write)?;
+				return Ok(true);
+			}
+			Command::Delete(id) => {
+				match self.delete(id) {
+					Some(p) => writeln!(w, "Deleted {}: {}", id, p),
+					None => writeln!(w, "No breakpoint or watchpoint {}", id),
+				}
+				.map_err(Error::Write)?;
+				return Ok(true);
+			}
+			Command::Info => {
+				if self.points.is_empty() {
+					writeln!(w, "No breakpoints or watchpoints")
+						.map_err(Error::Write)?;
+				}
+				for (id, p) in &self.points {
+					writeln!(w, "{:>3}: {}", id, p).map_err(Error::Write)?;
+				}
+				return Ok(true);
+			}
+			Command::List(n) => {
+				self.list(w, n)?;
+				return Ok(true);
+			}
+			Command::Print => Stop::Done,
+			Command::Help => {
+				writeln!(w, "{}", HELP).map_err(Error::Write)?;
+				return Ok(true);
+			}
+			Command::Quit => return Ok(false),
+		};
+		if !matches!(stop, Stop::Done) {
+			writeln!(w, "{}", stop).map_err(Error::Write)?;
+		}
+		self.print(w)?;
+		Ok(true)
+	}
+}
+
+/// Read commands from `r` and execute them on `program` until `quit` or
+/// end of input, writing output and prompts to `w`.
+pub fn repl<R: BufRead, W: Write>(
+	program: &[Instr],
+	r: R,
+	mut w: W,
+) -> Result<(), Error> {
+	let mut dbg = Debugger::new(program);
+	let mut last = None;
+	dbg.print(&mut w)?;
+	write!(w, "(day8) ").map_err(Error::Write)?;
+	w.flush().map_err(Error::Write)?;
+	for line in r.lines() {
+		let line = line.map_err(|e| Error::Io(None, e))?;
+		let cmd = match (line.trim(), last) {
+			("", Some(cmd)) => Ok(cmd),
+			("", None) => Err(Error::Usage("No previous command".into())),
+			(s, _) => s.parse(),
+		};
+		match cmd {
+			Ok(cmd) => {
+				last = Some(cmd);
+				if !dbg.execute(cmd, &mut w)? {
+					return Ok(());
+				}
+			}
+			Err(e) => writeln!(w, "{}", e).map_err(Error::Write)?,
+		}
+		write!(w, "(day8) ").map_err(Error::Write)?;
+		w.flush().map_err(Error::Write)?;
+	}
+	writeln!(w).map_err(Error::Write)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Day8;
+	use aoc_common::Solution;
+
+	const EXAMPLE: &[u8] = b"nop +0\nacc +1\njmp +4\nacc +3\njmp -3\n\
+		acc -99\nacc +1\njmp -4\nacc +6\n";
+
+	fn example() -> Vec<Instr> {
+		Day8.parse(EXAMPLE).expect("Failed to parse input")
+	}
+
+	#[test]
+	fn test_parse_command() {
+		assert_eq!(Command::Step(1), "s".parse().unwrap());
+		assert_eq!(Command::Step(4), "step 4".parse().unwrap());
+		assert_eq!(Command::Break(Point::Pc(3)), "break 3".parse().unwrap());
+		assert_eq!(
+			Command::Break(Point::Op(Op::Jmp)),
+			"b jmp".parse().unwrap()
+		);
+		assert_eq!(Command::Break(Point::AccChange), "w".parse().unwrap());
+		assert_eq!(
+			Command::Break(Point::AccEquals(-2)),
+			"watch -2".parse().unwrap()
+		);
+		assert_eq!(Command::Delete(2), "d 2".parse().unwrap());
+		assert!("break".parse::<Command>().is_err());
+		assert!("break foo".parse::<Command>().is_err());
+		assert!("step 1 2".parse::<Command>().is_err());
+		assert!("jump".parse::<Command>().is_err());
+	}
+
+	#[test]
+	fn test_run_stops_at_loop() {
+		let p = example();
+		let mut dbg = Debugger::new(&p);
+		assert!(matches!(dbg.run(None), Stop::Loop));
+		assert_eq!(1, dbg.vm().pc);
+		assert_eq!(5, dbg.vm().acc);
+	}
+
+	#[test]
+	fn test_run_stops_at_breakpoint() {
+		let p = example();
+		let mut dbg = Debugger::new(&p);
+		let id = dbg.add(Point::Pc(4));
+		assert!(matches!(dbg.run(None), Stop::Point(i, _) if i == id));
+		assert_eq!(4, dbg.vm().pc);
+		assert_eq!(Some(Point::Pc(4)), dbg.delete(id));
+
+		let mut dbg = Debugger::new(&p);
+		let id = dbg.add(Point::Op(Op::Jmp));
+		assert!(matches!(dbg.run(None), Stop::Point(i, _) if i == id));
+		assert_eq!(2, dbg.vm().pc);
+	}
+
+	#[test]
+	fn test_run_stops_at_watchpoint() {
+		let p = example();
+		let mut dbg = Debugger::new(&p);
+		dbg.add(Point::AccEquals(5));
+		assert!(matches!(dbg.run(None), Stop::Point(..)));
+		assert_eq!(4, dbg.vm().pc);
+		assert_eq!(5, dbg.vm().acc);
+	}
+
+	#[test]
+	fn test_rewind() {
+		let p = example();
+		let mut dbg = Debugger::new(&p);
+		assert!(matches!(dbg.run(Some(3)), Stop::Done));
+		assert_eq!(6, dbg.vm().pc);
+		assert!(matches!(dbg.rewind(2), Stop::Done));
+		assert_eq!(1, dbg.vm().pc);
+		assert_eq!(0, dbg.vm().acc);
+		assert!(matches!(dbg.rewind(2), Stop::Start));
+		assert_eq!(0, dbg.steps());
+		assert!(matches!(dbg.run(None), Stop::Loop));
+		assert_eq!(7, dbg.steps());
+	}
+
+	#[test]
+	fn test_run_terminates() {
+		let p = Day8.parse(b"acc +2\n").unwrap();
+		let mut dbg = Debugger::new(&p);
+		assert!(matches!(dbg.run(None), Stop::Terminated(2)));
+		assert!(matches!(dbg.run(None), Stop::Terminated(2)));
+	}
+
+	#[test]
+	fn test_run_fault() {
+		let p = Day8.parse(b"jmp -1\n").unwrap();
+		let mut dbg = Debugger::new(&p);
+		assert!(matches!(dbg.run(None), Stop::Fault(_)));
+		assert_eq!(0, dbg.steps());
+	}
+
+	#[test]
+	fn test_list() {
+		let p = example();
+		let mut dbg = Debugger::new(&p);
+		dbg.add(Point::Pc(2));
+		dbg.run(Some(1));
+		let mut out = vec![];
+		dbg.list(&mut out, 1).unwrap();
+		assert_eq!(
+			"        0: nop +0\n=>      1: acc +1\n  *     2: jmp +4\n",
+			String::from_utf8(out).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_repl() {
+		let p = example();
+		let mut out = vec![];
+		repl(
+			&p,
+			&b"break 4\ncontinue\nstep\n\nrewind\nquit\n"[..],
+			&mut out,
+		)
+		.unwrap();
+		let out = String::from_utf8(out).unwrap();
+		let lines = out
+			.lines()
+			.map(|l| l.trim_start_matches("(day8) "))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			vec![
+				"pc 0: nop +0     acc 0  steps 0",
+				"Added 1: breakpoint at pc 4",
+				"Hit 1: breakpoint at pc 4",
+				"pc 4: jmp -3     acc 5  steps 6",
+				"pc 1: acc +1     acc 5  steps 7",
+				"pc 2: jmp +4     acc 6  steps 8",
+				"pc 1: acc +1     acc 5  steps 7",
+				"",
+			],
+			lines
+		);
+	}
+}
@@ -0,0 +1,531 @@
+//! The instruction set of the handheld console.
+//!
+//! Each opcode is declared once in the table below with its mnemonic,
+//! operands and what it does. [`Instr`], its parser, `Display` and the
+//! executor used by [`VirtualMachine::step`] are all generated from the
+//! table. An [`Isa`] selects the opcodes a program may use.
+
+use crate::{immediate, Immediate, Trap, VirtualMachine};
+use aoc_common::{
+	parse::{records, IResult},
+	Error,
+};
+use nom::{
+	character::complete::{alpha1, char, newline},
+	combinator::{map_opt, success},
+	error::context,
+	sequence::{preceded, terminated},
+};
+use std::{convert::TryFrom, fmt, str};
+
+/// Kind of operand an opcode takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+	Imm,
+	Reg,
+}
+
+/// An operand of any kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+	Imm(Immediate),
+	Reg(Reg),
+}
+
+impl fmt::Display for Operand {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Operand::Imm(i) => write!(f, "{}", i),
+			Operand::Reg(r) => write!(f, "{}", r),
+		}
+	}
+}
+
+/// A register, written by its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+	Acc,
+	A,
+	B,
+	C,
+	D,
+}
+
+impl Reg {
+	pub const ALL: [Reg; 5] = [Reg::Acc, Reg::A, Reg::B, Reg::C, Reg::D];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Reg::Acc => "acc",
+			Reg::A => "a",
+			Reg::B => "b",
+			Reg::C => "c",
+			Reg::D => "d",
+		}
+	}
+
+	fn from_name(name: &[u8]) -> Option<Self> {
+		Reg::ALL
+			.iter()
+			.copied()
+			.find(|r| r.name().as_bytes() == name)
+	}
+}
+
+impl fmt::Display for Reg {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.name())
+	}
+}
+
+impl str::FromStr for Reg {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Reg::from_name(s.as_bytes())
+			.ok_or_else(|| Error::Invalid(format!("unknown register {:?}", s)))
+	}
+}
+
+/// A register name.
+pub fn register(i: &[u8]) -> IResult<&[u8], Reg> {
+	context("register", map_opt(alpha1, Reg::from_name))(i)
+}
+
+/// Whether execution continues after an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+	Continue,
+	Halt,
+}
+
+/// Operand types usable in the opcode table.
+trait Arg: Copy + fmt::Display + Sized {
+	const KIND: Kind;
+
+	fn parse(i: &[u8]) -> IResult<&[u8], Self>;
+
+	fn into_operand(self) -> Operand;
+
+	fn from_operand(o: &Operand) -> Option<Self>;
+}
+
+impl Arg for Immediate {
+	const KIND: Kind = Kind::Imm;
+
+	fn parse(i: &[u8]) -> IResult<&[u8], Self> {
+		immediate(i)
+	}
+
+	fn into_operand(self) -> Operand {
+		Operand::Imm(self)
+	}
+
+	fn from_operand(o: &Operand) -> Option<Self> {
+		match *o {
+			Operand::Imm(i) => Some(i),
+			_ => None,
+		}
+	}
+}
+
+impl Arg for Reg {
+	const KIND: Kind = Kind::Reg;
+
+	fn parse(i: &[u8]) -> IResult<&[u8], Self> {
+		register(i)
+	}
+
+	fn into_operand(self) -> Operand {
+		Operand::Reg(self)
+	}
+
+	fn from_operand(o: &Operand) -> Option<Self> {
+		match *o {
+			Operand::Reg(r) => Some(r),
+			_ => None,
+		}
+	}
+}
+
+/// Declare the opcodes. Each entry is the `Instr` variant with its
+/// mnemonic and named operands, followed by a closure-like body that
+/// executes it on the machine and returns the `Flow`.
+macro_rules! isa {
+	($(
+		$(#[$doc:meta])*
+		$variant:ident($name:literal $(, $arg:ident: $ty:ty)*)
+			|$vm:ident| $exec:expr
+	),* $(,)?) => {
+		/// Opcode of an instruction, written as its mnemonic.
+		#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+		pub enum Op {
+			$($variant),*
+		}
+
+		/// An instruction, written as its mnemonic followed by its
+		/// operands separated by spaces, such as `acc +3`.
+		#[derive(Debug, Clone, PartialEq, Eq)]
+		pub enum Instr {
+			$($(#[$doc])* $variant($($ty),*)),*
+		}
+
+		impl Op {
+			/// All opcodes in the order they are declared.
+			pub const ALL: &'static [Op] = &[$(Op::$variant),*];
+
+			pub fn name(self) -> &'static str {
+				match self {
+					$(Op::$variant => $name),*
+				}
+			}
+
+			/// Kinds of the operands, in order.
+			pub fn operands(self) -> &'static [Kind] {
+				match self {
+					$(Op::$variant => &[$(<$ty as Arg>::KIND),*]),*
+				}
+			}
+
+			fn parse_operands(self, i: &[u8]) -> IResult<&[u8], Instr> {
+				match self {
+					$(Op::$variant => {
+						$(let (i, $arg) =
+							preceded(char(' '), <$ty as Arg>::parse)(i)?;)*
+						Ok((i, Instr::$variant($($arg),*)))
+					})*
+				}
+			}
+		}
+
+		impl Instr {
+			pub fn op(&self) -> Op {
+				match self {
+					$(Instr::$variant(..) => Op::$variant),*
+				}
+			}
+
+			pub fn operands(&self) -> Vec<Operand> {
+				match self {
+					$(Instr::$variant($($arg),*) => {
+						vec![$(Arg::into_operand(*$arg)),*]
+					})*
+				}
+			}
+
+			/// Instruction `op` with `operands`, or `None` if they are not
+			/// the operands the opcode takes.
+			pub fn new(op: Op, operands: &[Operand]) -> Option<Self> {
+				match op {
+					$(Op::$variant => {
+						let mut iter = operands.iter();
+						$(let $arg =
+							<$ty as Arg>::from_operand(iter.next()?)?;)*
+						match iter.next() {
+							Some(_) => None,
+							None => Some(Instr::$variant($($arg),*)),
+						}
+					})*
+				}
+			}
+
+			pub(crate) fn exec(
+				&self,
+				vm: &mut VirtualMachine,
+			) -> Result<Flow, Trap> {
+				match self {
+					$(Instr::$variant($($arg),*) => {
+						$(let $arg = *$arg;)*
+						let $vm = vm;
+						$exec
+					})*
+				}
+			}
+		}
+
+		impl fmt::Display for Instr {
+			fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				match self {
+					$(Instr::$variant($($arg),*) => {
+						write!(f, "{}", $name)?;
+						$(write!(f, " {}", $arg)?;)*
+						Ok(())
+					})*
+				}
+			}
+		}
+	};
+}
+
+isa! {
+	/// Add the argument to acc.
+	Acc("acc", i: Immediate) |vm| {
+		vm.acc = i.add_to(vm.acc).ok_or(Trap::Overflow(Reg::Acc))?;
+		vm.next()
+	},
+	/// Jump relative to this instruction.
+	Jmp("jmp", i: Immediate) |vm| vm.jump(i),
+	/// Do nothing.
+	Nop("nop", _i: Immediate) |vm| vm.next(),
+	/// Jump relative to this instruction if acc is zero.
+	Jz("jz", i: Immediate) |vm| {
+		if vm.acc == 0 {
+			vm.jump(i)
+		} else {
+			vm.next()
+		}
+	},
+	/// Jump relative to this instruction unless acc is zero.
+	Jnz("jnz", i: Immediate) |vm| {
+		if vm.acc != 0 {
+			vm.jump(i)
+		} else {
+			vm.next()
+		}
+	},
+	/// Multiply acc by the argument.
+	Mul("mul", i: Immediate) |vm| {
+		// The argument need not fit in an `i64` if the product does.
+		let n = match i {
+			Immediate::Pos(n) => i128::from(n),
+			Immediate::Neg(n) => -i128::from(n),
+		};
+		vm.acc = i64::try_from(i128::from(vm.acc) * n)
+			.map_err(|_| Trap::Overflow(Reg::Acc))?;
+		vm.next()
+	},
+	/// Copy the second register to the first.
+	Mov("mov", dst: Reg, src: Reg) |vm| {
+		vm.set_reg(dst, vm.reg(src));
+		vm.next()
+	},
+	/// Add the second register to the first.
+	Add("add", dst: Reg, src: Reg) |vm| {
+		let n = vm
+			.reg(dst)
+			.checked_add(vm.reg(src))
+			.ok_or(Trap::Overflow(dst))?;
+		vm.set_reg(dst, n);
+		vm.next()
+	},
+	/// Append the register to the output.
+	Out("out", src: Reg) |vm| {
+		let n = vm.reg(src);
+		vm.output.push(n);
+		vm.next()
+	},
+	/// Stop the program.
+	Halt("halt") |_vm| Ok(Flow::Halt),
+}
+
+impl Op {
+	fn from_name(name: &[u8]) -> Option<Self> {
+		Op::ALL
+			.iter()
+			.copied()
+			.find(|op| op.name().as_bytes() == name)
+	}
+}
+
+impl fmt::Display for Op {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.name())
+	}
+}
+
+impl str::FromStr for Op {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Op::from_name(s.as_bytes())
+			.ok_or_else(|| Error::Invalid(format!("unknown opcode {:?}", s)))
+	}
+}
+
+/// The opcodes a program may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Isa {
+	ops: u32,
+}
+
+impl Isa {
+	/// `acc`, `jmp` and `nop` as in the puzzle.
+	pub const ORIGINAL: Isa = Isa::new(&[Op::Acc, Op::Jmp, Op::Nop]);
+	/// All opcodes.
+	pub const FULL: Isa = Isa::new(Op::ALL);
+
+	pub const fn new(ops: &[Op]) -> Self {
+		let mut isa = Isa { ops: 0 };
+		let mut n = 0;
+		while n < ops.len() {
+			isa = isa.with(ops[n]);
+			n += 1;
+		}
+		isa
+	}
+
+	/// This instruction set with `op` added.
+	pub const fn with(self, op: Op) -> Self {
+		Isa {
+			ops: self.ops | 1 << op as u32,
+		}
+	}
+
+	pub fn contains(self, op: Op) -> bool {
+		self.ops & 1 << op as u32 != 0
+	}
+
+	/// The opcode named `name`, if it is part of this instruction set.
+	pub fn opcode(self, name: &[u8]) -> Option<Op> {
+		Op::from_name(name).filter(|&op| self.contains(op))
+	}
+
+	/// An instruction without its line ending.
+	pub fn operation(self, i: &[u8]) -> IResult<&[u8], Instr> {
+		let (i, op) =
+			context("opcode", map_opt(alpha1, |n| self.opcode(n)))(i)?;
+		op.parse_operands(i)
+	}
+
+	/// An instruction followed by a newline.
+	pub fn instruction(self, i: &[u8]) -> IResult<&[u8], Instr> {
+		terminated(|i| self.operation(i), context("newline", newline))(i)
+	}
+
+	/// A program, one instruction per line.
+	pub fn program(self, i: &[u8]) -> IResult<&[u8], Vec<Instr>> {
+		records(success(()), |i| self.instruction(i))(i)
+	}
+}
+
+impl Default for Isa {
+	fn default() -> Self {
+		Isa::FULL
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+	use aoc_common::parse;
+	use proptest::prelude::*;
+
+	fn run(program: &[u8]) -> Result<(VirtualMachine, Option<i64>), Error> {
+		let p = parse::parse_complete(|i| Isa::FULL.program(i), program)?;
+		let mut vm = VirtualMachine::new();
+		for _ in 0..1000 {
+			if let Some(acc) = vm.step(&p)? {
+				return Ok((vm, Some(acc)));
+			}
+		}
+		Ok((vm, None))
+	}
+
+	#[test]
+	fn test_isa_original() {
+		assert!(Isa::ORIGINAL.operation(b"acc +1").is_ok());
+		assert!(Isa::ORIGINAL.operation(b"mul +2").is_err());
+		assert!(Isa::FULL.operation(b"mul +2").is_ok());
+		assert!(Isa::ORIGINAL.with(Op::Mul).operation(b"mul +2").is_ok());
+		assert!(Isa::FULL.operation(b"mull +2").is_err());
+	}
+
+	#[test]
+	fn test_parse_operation() {
+		let (rest, r) =
+			Isa::FULL.operation(b"mov a acc").expect("Failed to parse");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Instr::Mov(Reg::A, Reg::Acc), r);
+		let (rest, r) = Isa::FULL.operation(b"halt").expect("Failed to parse");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Instr::Halt(), r);
+		assert!(Isa::FULL.operation(b"mov a").is_err());
+		assert!(Isa::FULL.operation(b"out e").is_err());
+	}
+
+	#[test]
+	fn test_parse_error() {
+		match parse::parse_complete(|i| Isa::ORIGINAL.program(i), b"mul +1\n") {
+			Err(Error::Parse(e)) => assert_eq!(
+				"line 1, col 1: expected opcode, found \"mul +1\"",
+				e.to_string()
+			),
+			r => panic!("Unexpected result: {:?}", r),
+		}
+	}
+
+	#[test]
+	fn test_jz_jnz() {
+		let (vm, r) = run(b"jz +2\nacc +5\njnz +2\nacc +7\nacc +1\n").unwrap();
+		assert_eq!(Some(8), r);
+		assert_eq!(5, vm.pc);
+		let (_, r) = run(b"acc +1\njz +2\nacc +5\njnz +2\nacc +7\n").unwrap();
+		assert_eq!(Some(6), r);
+	}
+
+	#[test]
+	fn test_mul() {
+		let (_, r) = run(b"acc +3\nmul -4\n").unwrap();
+		assert_eq!(Some(-12), r);
+		assert!(run(b"acc +2\nmul +9223372036854775807\n").is_err());
+		// Only the product has to fit.
+		let (_, r) = run(b"mul +18446744073709551615\n").unwrap();
+		assert_eq!(Some(0), r);
+		let (_, r) = run(b"acc -1\nmul +9223372036854775808\n").unwrap();
+		assert_eq!(Some(i64::MIN), r);
+		assert!(run(b"acc +1\nmul +9223372036854775808\n").is_err());
+	}
+
+	#[test]
+	fn test_registers() {
+		let (vm, r) =
+			run(b"acc +3\nmov a acc\nadd a a\nout a\nmov acc a\nout acc\n")
+				.unwrap();
+		assert_eq!(Some(6), r);
+		assert_eq!(6, vm.reg(Reg::A));
+		assert_eq!(vec![6, 6], vm.output);
+	}
+
+	#[test]
+	fn test_halt() {
+		let (vm, r) = run(b"acc +2\nhalt\nacc +1\n").unwrap();
+		assert_eq!(Some(2), r);
+		assert_eq!(1, vm.pc);
+	}
+
+	fn operand(kind: Kind) -> BoxedStrategy<Operand> {
+		match kind {
+			Kind::Imm => prop_oneof![
+				any::<u64>().prop_map(Immediate::Pos),
+				any::<u64>().prop_map(Immediate::Neg),
+			]
+			.prop_map(Operand::Imm)
+			.boxed(),
+			Kind::Reg => prop::sample::select(Reg::ALL.to_vec())
+				.prop_map(Operand::Reg)
+				.boxed(),
+		}
+	}
+
+	/// Any instruction of the full instruction set.
+	pub(crate) fn instrs() -> impl Strategy<Value = Instr> {
+		prop::sample::select(Op::ALL.to_vec()).prop_flat_map(|op| {
+			op.operands()
+				.iter()
+				.map(|&k| operand(k))
+				.collect::<Vec<_>>()
+				.prop_map(move |o| Instr::new(op, &o).expect("Bad operands"))
+		})
+	}
+
+	proptest! {
+		#[test]
+		fn prop_display_round_trip(instr in instrs()) {
+			let r = instr.to_string().parse::<Instr>();
+			prop_assert_eq!(instr.clone(), r.expect("Failed to parse"));
+			let operands = instr.operands();
+			let r = Instr::new(instr.op(), &operands);
+			prop_assert_eq!(Some(instr), r);
+		}
+	}
+}
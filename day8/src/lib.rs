@@ -0,0 +1,924 @@
+//! Day 8: Handheld Halting.
+//!
+//! Runs the boot code of a handheld console on a small virtual machine to
+//! find its infinite loop and the single instruction to change to fix it.
+
+pub mod asm;
+pub mod bytecode;
+pub mod cfg;
+pub mod cycle;
+pub mod debugger;
+pub mod isa;
+pub mod optimize;
+pub mod profile;
+pub mod repair;
+pub mod snapshot;
+pub mod trace;
+
+use aoc_common::{
+	parse::{self, IResult},
+	Answer, Error, ParseError, Part, Solution,
+};
+pub use isa::{Flow, Instr, Isa, Op, Reg};
+use nom::{
+	branch::alt,
+	bytes::complete::tag,
+	character::complete::digit1,
+	combinator::{map, map_res},
+	error::context,
+	sequence::pair,
+};
+use profile::Profile;
+use snapshot::VmState;
+use std::{convert::TryFrom, fmt, str};
+use trace::{Step, Trace};
+
+/// A signed instruction argument, written as `+3` or `-3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Immediate {
+	Pos(u64),
+	Neg(u64),
+}
+
+impl fmt::Display for Immediate {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Immediate::Pos(i) => write!(f, "+{}", i),
+			Immediate::Neg(i) => write!(f, "-{}", i),
+		}
+	}
+}
+
+impl str::FromStr for Immediate {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		parse::parse_complete(immediate, s.as_bytes())
+	}
+}
+
+impl str::FromStr for Instr {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		parse::parse_complete(operation, s.as_bytes())
+	}
+}
+
+impl Immediate {
+	/// `n` plus this argument, or `None` on overflow.
+	pub fn add_to(self, n: i64) -> Option<i64> {
+		match self {
+			Immediate::Pos(i) => n.checked_add_unsigned(i),
+			Immediate::Neg(i) => n.checked_sub_unsigned(i),
+		}
+	}
+
+	/// This argument as a number, or `None` if it does not fit.
+	pub fn value(self) -> Option<i64> {
+		match self {
+			Immediate::Pos(i) => i64::try_from(i).ok(),
+			Immediate::Neg(i) => 0_i64.checked_sub_unsigned(i),
+		}
+	}
+
+	/// `pc` moved by this argument, or `None` if out of range.
+	pub fn offset(self, pc: usize) -> Option<usize> {
+		match self {
+			Immediate::Pos(i) => {
+				usize::try_from(i).ok().and_then(|i| pc.checked_add(i))
+			}
+			Immediate::Neg(i) => {
+				usize::try_from(i).ok().and_then(|i| pc.checked_sub(i))
+			}
+		}
+	}
+
+	/// The argument that moves `pc` to `target`.
+	pub fn between(pc: usize, target: usize) -> Self {
+		let n = |d: usize| u64::try_from(d).expect("pc fits in u64");
+		if target < pc {
+			Immediate::Neg(n(pc - target))
+		} else {
+			Immediate::Pos(n(target - pc))
+		}
+	}
+}
+
+/// Reason the console can not go on, with the pc and instruction at
+/// fault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmFault {
+	/// pc is past the end of the program, which only a machine set there
+	/// can be.
+	PcOutOfRange(usize),
+	/// A jump would move pc past the end of the program.
+	PcOverflow(usize, Instr),
+	/// A jump would move pc before the first instruction.
+	PcUnderflow(usize, Instr),
+	/// acc does not fit in an `i64`.
+	AccOverflow(usize, Instr),
+	/// Another register does not fit in an `i64`.
+	RegOverflow(usize, Instr, Reg),
+	/// The run does not terminate, found before the instruction is
+	/// executed as a [`LoopDetector`] does.
+	InfiniteLoopDetected(usize, Instr),
+	/// The run went on for the step limit of a [`LoopDetector`] without
+	/// terminating or being found to loop.
+	StepLimit(usize, Instr),
+}
+
+impl VmFault {
+	pub fn pc(&self) -> usize {
+		match *self {
+			VmFault::PcOutOfRange(pc)
+			| VmFault::PcOverflow(pc, _)
+			| VmFault::PcUnderflow(pc, _)
+			| VmFault::AccOverflow(pc, _)
+			| VmFault::RegOverflow(pc, _, _)
+			| VmFault::InfiniteLoopDetected(pc, _)
+			| VmFault::StepLimit(pc, _) => pc,
+		}
+	}
+
+	pub fn instr(&self) -> Option<&Instr> {
+		match self {
+			VmFault::PcOutOfRange(_) => None,
+			VmFault::PcOverflow(_, i)
+			| VmFault::PcUnderflow(_, i)
+			| VmFault::AccOverflow(_, i)
+			| VmFault::RegOverflow(_, i, _)
+			| VmFault::InfiniteLoopDetected(_, i)
+			| VmFault::StepLimit(_, i) => Some(i),
+		}
+	}
+}
+
+impl fmt::Display for VmFault {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			VmFault::PcOutOfRange(pc) => write!(f, "pc {} out of range", pc),
+			VmFault::PcOverflow(pc, i) => {
+				write!(f, "jump past the end at pc {}: {}", pc, i)
+			}
+			VmFault::PcUnderflow(pc, i) => {
+				write!(f, "jump before the first pc at pc {}: {}", pc, i)
+			}
+			VmFault::AccOverflow(pc, i) => {
+				write!(f, "acc overflow at pc {}: {}", pc, i)
+			}
+			VmFault::RegOverflow(pc, i, r) => {
+				write!(f, "{} overflow at pc {}: {}", r, pc, i)
+			}
+			VmFault::InfiniteLoopDetected(pc, i) => {
+				write!(f, "infinite loop at pc {}: {}", pc, i)
+			}
+			VmFault::StepLimit(pc, i) => {
+				write!(f, "step limit reached at pc {}: {}", pc, i)
+			}
+		}
+	}
+}
+
+impl From<VmFault> for Error {
+	fn from(fault: VmFault) -> Self {
+		Error::Invalid(fault.to_string())
+	}
+}
+
+/// Fault raised by an instruction, without where it happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Trap {
+	PcUnderflow,
+	PcOverflow,
+	Overflow(Reg),
+}
+
+/// State of the console: the index of the next instruction, the
+/// accumulator, the other registers and the values written by `out`.
+///
+/// If `trace` is set every executed instruction is recorded in it, and
+/// if `profile` is set it is counted there.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VirtualMachine {
+	pub pc: usize,
+	pub acc: i64,
+	/// Registers `a` to `d`.
+	pub regs: [i64; 4],
+	pub output: Vec<i64>,
+	pub trace: Option<Trace>,
+	pub profile: Option<Profile>,
+}
+
+impl VirtualMachine {
+	pub fn new() -> Self {
+		VirtualMachine {
+			pc: 0,
+			acc: 0,
+			regs: [0; 4],
+			output: Vec::new(),
+			trace: None,
+			profile: None,
+		}
+	}
+
+	/// A machine that records a trace of its execution.
+	pub fn recording() -> Self {
+		VirtualMachine {
+			trace: Some(Trace::default()),
+			..Self::new()
+		}
+	}
+
+	/// A machine that profiles its execution.
+	pub fn profiling() -> Self {
+		VirtualMachine {
+			profile: Some(Profile::default()),
+			..Self::new()
+		}
+	}
+
+	/// The state of the machine, to continue from with `restore`.
+	pub fn snapshot(&self) -> VmState {
+		VmState {
+			pc: self.pc,
+			acc: self.acc,
+			regs: self.regs,
+			output: self.output.clone(),
+		}
+	}
+
+	/// Continue from `state`, keeping the trace and profile.
+	pub fn restore(&mut self, state: &VmState) {
+		self.pc = state.pc;
+		self.acc = state.acc;
+		self.regs = state.regs;
+		self.output = state.output.clone();
+	}
+
+	/// A machine in the same state that runs on separately, without a
+	/// trace or profile.
+	pub fn fork(&self) -> Self {
+		self.snapshot().into()
+	}
+
+	pub fn reg(&self, r: Reg) -> i64 {
+		match r {
+			Reg::Acc => self.acc,
+			r => self.regs[r as usize - 1],
+		}
+	}
+
+	pub fn set_reg(&mut self, r: Reg, n: i64) {
+		match r {
+			Reg::Acc => self.acc = n,
+			r => self.regs[r as usize - 1] = n,
+		}
+	}
+
+	fn next(&mut self) -> Result<Flow, Trap> {
+		self.pc += 1;
+		Ok(Flow::Continue)
+	}
+
+	fn jump(&mut self, i: Immediate) -> Result<Flow, Trap> {
+		self.pc = i.offset(self.pc).ok_or(match i {
+			Immediate::Pos(_) => Trap::PcOverflow,
+			Immediate::Neg(_) => Trap::PcUnderflow,
+		})?;
+		Ok(Flow::Continue)
+	}
+
+	/// Execute `instr` as the instruction at `pc`. On a fault the machine
+	/// is left as it was.
+	pub fn execute(&mut self, instr: &Instr) -> Result<Flow, VmFault> {
+		let pc = self.pc;
+		instr.exec(self).map_err(|t| match t {
+			Trap::PcUnderflow => VmFault::PcUnderflow(pc, instr.clone()),
+			Trap::PcOverflow => VmFault::PcOverflow(pc, instr.clone()),
+			Trap::Overflow(Reg::Acc) => VmFault::AccOverflow(pc, instr.clone()),
+			Trap::Overflow(r) => VmFault::RegOverflow(pc, instr.clone(), r),
+		})
+	}
+
+	/// Execute the instruction at `pc`, returning the accumulator once the
+	/// program terminates by moving just past its last instruction or
+	/// executing `halt`. A jump further than that faults.
+	pub fn step(&mut self, p: &[Instr]) -> Result<Option<i64>, VmFault> {
+		let instr = match p.get(self.pc) {
+			Some(instr) => instr,
+			None if self.pc == p.len() => return Ok(Some(self.acc)),
+			None => return Err(VmFault::PcOutOfRange(self.pc)),
+		};
+		let (pc, acc) = (self.pc, self.acc);
+		let flow = self.execute(instr)?;
+		if self.pc > p.len() {
+			// Only jumps get here, and they change nothing but pc.
+			self.pc = pc;
+			return Err(VmFault::PcOverflow(pc, instr.clone()));
+		}
+		if let Some(trace) = &mut self.trace {
+			trace.steps.push(Step {
+				pc,
+				instr: instr.clone(),
+				acc,
+				acc_after: self.acc,
+				next: self.pc,
+			});
+		}
+		if let Some(profile) = &mut self.profile {
+			profile.record(pc, instr, acc);
+		}
+		match flow {
+			Flow::Continue => Ok(None),
+			Flow::Halt => Ok(Some(self.acc)),
+		}
+	}
+
+	/// Execute instructions until the program terminates, returning the
+	/// accumulator, or faults. Stops before an instruction with the fault
+	/// a [`LoopDetector`] finds, `InfiniteLoopDetected` or `StepLimit`.
+	pub fn run(&mut self, p: &[Instr]) -> Result<i64, VmFault> {
+		self.run_with_limit(p, STEP_LIMIT)
+	}
+
+	/// [`VirtualMachine::run`] with `limit` steps allowed after the first
+	/// conditional jump.
+	pub fn run_with_limit(
+		&mut self,
+		p: &[Instr],
+		limit: usize,
+	) -> Result<i64, VmFault> {
+		let mut detector = LoopDetector::with_limit(p, limit);
+		loop {
+			detector.check(self, p)?;
+			if let Some(acc) = self.step(p)? {
+				return Ok(acc);
+			}
+		}
+	}
+}
+
+/// Steps a [`LoopDetector`] allows after the first conditional jump by
+/// default.
+pub const STEP_LIMIT: usize = 1_000_000;
+
+/// pc, acc and the other registers, which decide how a run goes on.
+type State = (usize, i64, [i64; 4]);
+
+/// Finds that a run does not terminate, asked before each step.
+///
+/// Without a conditional jump executed since an instruction last ran,
+/// coming back to it repeats the same instructions forever. After one,
+/// the run loops if it comes back to a state it was in, with the same
+/// pc, acc and registers, which is found with Brent's algorithm. A run
+/// that does neither within the step limit after its first conditional
+/// jump, such as one counting acc up until it overflows or a long count
+/// down, is stopped without knowing whether it terminates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopDetector {
+	/// Conditional jumps executed when each instruction last ran.
+	last_run: Vec<Option<usize>>,
+	conditionals: usize,
+	/// Steps since the first conditional jump, and how many are allowed.
+	steps: usize,
+	limit: usize,
+	/// State to find again, saved whenever `steps` is a power of two.
+	saved: Option<State>,
+}
+
+impl LoopDetector {
+	/// Detector for runs of `p`, with the [`STEP_LIMIT`].
+	pub fn new(p: &[Instr]) -> Self {
+		LoopDetector::with_limit(p, STEP_LIMIT)
+	}
+
+	/// Detector for runs of `p` that allows `limit` steps after the first
+	/// conditional jump.
+	pub fn with_limit(p: &[Instr], limit: usize) -> Self {
+		LoopDetector {
+			last_run: vec![None; p.len()],
+			conditionals: 0,
+			steps: 0,
+			limit,
+			saved: None,
+		}
+	}
+
+	/// Check the run of `p` on `vm`, about to execute the instruction at
+	/// pc, failing with `InfiniteLoopDetected` if it does not terminate
+	/// or `StepLimit` if it ran out of steps.
+	pub fn check(
+		&mut self,
+		vm: &VirtualMachine,
+		p: &[Instr],
+	) -> Result<(), VmFault> {
+		let (instr, last_run) =
+			match (p.get(vm.pc), self.last_run.get_mut(vm.pc)) {
+				(Some(instr), Some(last_run)) => (instr, last_run),
+				_ => return Ok(()),
+			};
+		if last_run.replace(self.conditionals) == Some(self.conditionals) {
+			return Err(VmFault::InfiniteLoopDetected(vm.pc, instr.clone()));
+		}
+		if self.conditionals > 0 {
+			let state = (vm.pc, vm.acc, vm.regs);
+			if self.saved == Some(state) {
+				let fault = VmFault::InfiniteLoopDetected(vm.pc, instr.clone());
+				return Err(fault);
+			}
+			if self.steps == self.limit {
+				return Err(VmFault::StepLimit(vm.pc, instr.clone()));
+			}
+			self.steps += 1;
+			if self.steps.is_power_of_two() {
+				self.saved = Some(state);
+			}
+		}
+		if matches!(instr, Instr::Jz(_) | Instr::Jnz(_)) {
+			self.conditionals += 1;
+		}
+		Ok(())
+	}
+}
+
+/// An instruction argument.
+pub fn immediate(i: &[u8]) -> IResult<&[u8], Immediate> {
+	map(
+		pair(
+			context("\"+\" or \"-\"", alt((tag("+"), tag("-")))),
+			context(
+				"number",
+				map_res(map_res(digit1, str::from_utf8), str::parse),
+			),
+		),
+		|(s, n)| {
+			if s == b"-" {
+				Immediate::Neg(n)
+			} else {
+				Immediate::Pos(n)
+			}
+		},
+	)(i)
+}
+
+/// An instruction of the full instruction set without its line ending.
+pub fn operation(i: &[u8]) -> IResult<&[u8], Instr> {
+	Isa::FULL.operation(i)
+}
+
+/// An instruction of the full instruction set followed by a newline.
+pub fn instruction(i: &[u8]) -> IResult<&[u8], Instr> {
+	Isa::FULL.instruction(i)
+}
+
+/// The program, one instruction per line.
+pub fn program(i: &[u8]) -> IResult<&[u8], Vec<Instr>> {
+	Isa::FULL.program(i)
+}
+
+/// Accumulator just before an instruction is executed a second time, or
+/// `None` if the program terminates. Fails with `StepLimit` if the run is
+/// not found to do either.
+pub fn find_answer_part1(p: &[Instr]) -> Result<Option<i64>, Error> {
+	let mut vm = VirtualMachine::new();
+	match vm.run(p) {
+		Ok(_) => Ok(None),
+		Err(VmFault::InfiniteLoopDetected(..)) => Ok(Some(vm.acc)),
+		Err(fault) => Err(fault.into()),
+	}
+}
+
+/// Accumulator once the program terminates after changing one `jmp` to
+/// `nop` or `nop` to `jmp`, or `None` if no single change makes it
+/// terminate or it already does. A program that faults rather than loops
+/// is fixed the same way, and a run does not terminate once a
+/// [`LoopDetector`] finds it loops. Fails with `StepLimit` if a run is
+/// not found to do either before the changes that terminate.
+pub fn find_answer_part2(p: &[Instr]) -> Result<Option<i64>, Error> {
+	let mut vm = VirtualMachine::new();
+	let mut detector = LoopDetector::new(p);
+	let mut seen = vec![false; p.len()];
+
+	// Each `jmp` or `nop` executed before the loop, or that faults, is a
+	// candidate for the change, saved as the state the changed instruction
+	// would continue from the first time it runs.
+	let mut candidates = vec![];
+	loop {
+		match detector.check(&vm, p) {
+			Ok(()) => {}
+			Err(VmFault::InfiniteLoopDetected(..)) => break,
+			Err(fault) => return Err(fault.into()),
+		}
+		if let Some(s) = seen.get_mut(vm.pc) {
+			let next = match &p[vm.pc] {
+				Instr::Nop(i) => i.offset(vm.pc),
+				Instr::Jmp(_) => Some(vm.pc + 1),
+				_ => None,
+			};
+			if let Some(pc) = next.filter(|_| !*s) {
+				candidates.push((
+					vm.pc,
+					VmState {
+						pc,
+						..vm.snapshot()
+					},
+				));
+			}
+			*s = true;
+		}
+		match vm.step(p) {
+			Ok(Some(_)) => return Ok(None),
+			Ok(None) => {}
+			Err(_) => break,
+		}
+	}
+
+	// With conditional jumps an instruction may lead elsewhere in a later
+	// run, so each changed program is run on its own.
+	if p.iter().any(|i| matches!(i, Instr::Jz(_) | Instr::Jnz(_))) {
+		let mut q = p.to_vec();
+		for (pc, state) in candidates {
+			repair::swap(&mut q[pc]);
+			let r = VirtualMachine::from(state).run(&q);
+			repair::swap(&mut q[pc]);
+			match r {
+				Ok(acc) => return Ok(Some(acc)),
+				Err(fault @ VmFault::StepLimit(..)) => return Err(fault.into()),
+				Err(_) => {}
+			}
+		}
+		return Ok(None);
+	}
+
+	// Instructions reached from an earlier candidate without terminating
+	// loop or fault for every later candidate too.
+	for (_, state) in &candidates {
+		vm.restore(state);
+		loop {
+			match seen.get_mut(vm.pc) {
+				Some(true) => break,
+				Some(s) => *s = true,
+				None => {}
+			}
+			match vm.step(p) {
+				Ok(Some(r)) => return Ok(Some(r)),
+				Ok(None) => {}
+				Err(_) => break,
+			}
+		}
+	}
+	Ok(None)
+}
+
+/// Solution for day 8.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Day8;
+
+impl Solution for Day8 {
+	const DAY: u32 = 8;
+
+	type Input<'a> = Vec<Instr>;
+
+	/// Puzzle input, or assembly source with labels and comments.
+	fn parse<'a>(&self, input: &'a [u8]) -> Result<Self::Input<'a>, Error> {
+		asm::assemble(input)
+	}
+
+	fn parse_lenient<'a>(
+		&self,
+		input: &'a [u8],
+	) -> Result<(Self::Input<'a>, Vec<ParseError>), Error> {
+		Ok(asm::assemble_lenient_with(Isa::FULL, input))
+	}
+
+	fn part1(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		find_answer_part1(input)?
+			.map(Answer::new)
+			.ok_or(Error::NoAnswer(Part::Part1))
+	}
+
+	fn part2(&self, input: &Self::Input<'_>) -> Result<Answer, Error> {
+		find_answer_part2(input)?
+			.map(Answer::new)
+			.ok_or(Error::NoAnswer(Part::Part2))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	#[test]
+	fn test_parse_immediate() {
+		let (rest, r) = immediate(b"+10").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Immediate::Pos(10), r);
+	}
+
+	#[test]
+	fn test_parse_immediate_negative() {
+		let (rest, r) = immediate(b"-10").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Immediate::Neg(10), r);
+	}
+
+	#[test]
+	fn test_parse_instruction_acc() {
+		let (rest, r) =
+			instruction(b"acc +10\n").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Instr::Acc(Immediate::Pos(10)), r);
+	}
+
+	#[test]
+	fn test_parse_instruction_jmp() {
+		let (rest, r) =
+			instruction(b"jmp +10\n").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Instr::Jmp(Immediate::Pos(10)), r);
+	}
+
+	#[test]
+	fn test_parse_instruction_nop() {
+		let (rest, r) =
+			instruction(b"nop +10\n").expect("Failed to parse input");
+		assert_eq!(b"", rest, "Not all input consumed");
+		assert_eq!(Instr::Nop(Immediate::Pos(10)), r);
+	}
+
+	#[test]
+	fn test_parse_lenient_labels() {
+		let src = b"; count down
+	acc +3
+loop:	acc -1  ; one less
+	jnz loop
+
+	jmp end
+	nop +0
+end:
+";
+		let (p, warnings) = Day8.parse_lenient(src).unwrap();
+		assert!(warnings.is_empty());
+		assert_eq!(Day8.parse(src).unwrap(), p);
+	}
+
+	#[test]
+	fn test_vm_step_acc() {
+		let mut vm = VirtualMachine::new();
+		vm.step(&[Instr::Acc(Immediate::Pos(1))])
+			.expect("Failed to step");
+		assert_eq!(vm.pc, 1);
+		assert_eq!(vm.acc, 1);
+	}
+
+	#[test]
+	fn test_vm_step_jmp_pos() {
+		let mut vm = VirtualMachine::new();
+		vm.step(&[Instr::Jmp(Immediate::Pos(1))])
+			.expect("Failed to step");
+		assert_eq!(vm.pc, 1);
+		assert_eq!(vm.acc, 0);
+	}
+	#[test]
+	fn test_vm_step_jmp_zero() {
+		let mut vm = VirtualMachine::new();
+		vm.step(&[Instr::Jmp(Immediate::Pos(0))])
+			.expect("Failed to step");
+		assert_eq!(vm.pc, 0);
+		assert_eq!(vm.acc, 0);
+	}
+
+	#[test]
+	fn test_vm_step_jmp_neg() {
+		let mut vm = VirtualMachine::new();
+		vm.pc = 1;
+		vm.step(&[
+			Instr::Nop(Immediate::Pos(0)),
+			Instr::Jmp(Immediate::Neg(1)),
+		])
+		.expect("Failed to step");
+		assert_eq!(vm.pc, 0);
+		assert_eq!(vm.acc, 0);
+	}
+
+	#[test]
+	fn test_vm_step_jmp_underflow() {
+		let mut vm = VirtualMachine::new();
+		let jmp = Instr::Jmp(Immediate::Neg(1));
+		assert_eq!(Err(VmFault::PcUnderflow(0, jmp.clone())), vm.step(&[jmp]));
+		assert_eq!(0, vm.pc);
+	}
+
+	#[test]
+	fn test_vm_step_acc_overflow() {
+		let mut vm = VirtualMachine::new();
+		vm.acc = i64::MAX;
+		let acc = Instr::Acc(Immediate::Pos(1));
+		assert_eq!(Err(VmFault::AccOverflow(0, acc.clone())), vm.step(&[acc]));
+		assert_eq!(i64::MAX, vm.acc);
+	}
+
+	#[test]
+	fn test_vm_step_out_of_range() {
+		let mut vm = VirtualMachine::new();
+		vm.pc = 2;
+		assert_eq!(
+			Err(VmFault::PcOutOfRange(2)),
+			vm.step(&[Instr::Nop(Immediate::Pos(0))])
+		);
+	}
+
+	#[test]
+	fn test_vm_run_loop() {
+		let p = [Instr::Acc(Immediate::Pos(1)), Instr::Jmp(Immediate::Neg(1))];
+		let mut vm = VirtualMachine::new();
+		assert_eq!(
+			Err(VmFault::InfiniteLoopDetected(0, p[0].clone())),
+			vm.run(&p)
+		);
+		assert_eq!(1, vm.acc);
+	}
+
+	#[test]
+	fn test_vm_step_jmp_past_end() {
+		let mut vm = VirtualMachine::new();
+		let p = [Instr::Nop(Immediate::Pos(0)), Instr::Jz(Immediate::Pos(2))];
+		vm.step(&p).expect("Failed to step");
+		assert_eq!(Err(VmFault::PcOverflow(1, p[1].clone())), vm.step(&p));
+		assert_eq!(1, vm.pc);
+		let jmp = Instr::Jmp(Immediate::Pos(u64::MAX));
+		assert_eq!(
+			Err(VmFault::PcOverflow(0, jmp.clone())),
+			VirtualMachine::new().run(&[jmp])
+		);
+	}
+
+	#[test]
+	fn test_vm_run_conditional_loop() {
+		// Coming back to pc 1 is not a loop while acc counts down.
+		let p = asm::assemble(b"acc +2\nloop: acc -1\njnz loop\n").unwrap();
+		assert_eq!(Ok(0), VirtualMachine::new().run(&p));
+		let p = asm::assemble(b"loop: acc +1\nacc -1\njz loop\n").unwrap();
+		assert_eq!(
+			Err(VmFault::InfiniteLoopDetected(0, p[0].clone())),
+			VirtualMachine::new().run(&p)
+		);
+		// A loop that never repeats a state stops at the step limit.
+		let p = asm::assemble(b"acc +1\njnz -1\n").unwrap();
+		let mut vm = VirtualMachine::new();
+		assert!(matches!(vm.run(&p), Err(VmFault::StepLimit(..))));
+		assert_eq!(STEP_LIMIT / 2 + 1, vm.acc as usize);
+		// So does a long count down, unless more steps are allowed.
+		let p = asm::assemble(b"acc +100\nloop: acc -1\njnz loop\n").unwrap();
+		let mut vm = VirtualMachine::new();
+		assert_eq!(
+			Err(VmFault::StepLimit(1, p[1].clone())),
+			vm.run_with_limit(&p, 100)
+		);
+		assert_eq!(Ok(0), vm.run_with_limit(&p, 200));
+	}
+
+	#[test]
+	fn test_find_answer_fault() {
+		let p = [
+			Instr::Nop(Immediate::Pos(0)),
+			Instr::Jmp(Immediate::Neg(5)),
+			Instr::Acc(Immediate::Pos(1)),
+		];
+		match find_answer_part1(&p) {
+			Err(Error::Invalid(msg)) => {
+				assert_eq!("jump before the first pc at pc 1: jmp -5", msg)
+			}
+			r => panic!("Unexpected result: {:?}", r),
+		}
+		assert_eq!(Some(1), find_answer_part2(&p).expect("Failed to run"));
+	}
+
+	#[test]
+	fn test_find_answer_conditional() {
+		let p = asm::assemble(b"acc +2\nloop: acc -1\njnz loop\n").unwrap();
+		assert_eq!(None, find_answer_part2(&p).expect("Failed to run"));
+		let p =
+			asm::assemble(b"acc +2\nloop: acc -1\njnz loop\njmp +0\n").unwrap();
+		assert_eq!(Some(0), find_answer_part2(&p).expect("Failed to run"));
+		assert_eq!(Some(0), cfg::find_answer_part2(&p).expect("Failed to run"));
+
+		// A count down longer than the step limit is not taken as a loop.
+		let p = asm::assemble(b"acc +600000\nloop: acc -1\njnz loop\njmp +0\n")
+			.unwrap();
+		let step_limit = |r: Result<Option<i64>, Error>| match r {
+			Err(Error::Invalid(msg)) => msg.starts_with("step limit reached"),
+			_ => false,
+		};
+		assert!(step_limit(find_answer_part1(&p)));
+		assert!(step_limit(find_answer_part2(&p)));
+		assert!(step_limit(cfg::find_answer_part2(&p)));
+	}
+
+	#[test]
+	fn test_find_answer_terminates() {
+		let p = [Instr::Acc(Immediate::Pos(1))];
+		assert_eq!(None, find_answer_part1(&p).expect("Failed to run"));
+		assert_eq!(None, find_answer_part2(&p).expect("Failed to run"));
+	}
+
+	/// Random programs whose jumps all land inside the program or just past
+	/// its end, so that every run either loops or terminates.
+	pub(crate) fn programs() -> impl Strategy<Value = Vec<Instr>> {
+		(1_usize..20).prop_flat_map(|len| {
+			prop::collection::vec((0_u8..3, -20_i64..20, 0..=len), len)
+				.prop_map(|v| {
+					v.into_iter()
+						.enumerate()
+						.map(|(pc, (op, acc, target))| {
+							let imm = |n: i64| {
+								if n < 0 {
+									Immediate::Neg(n.unsigned_abs())
+								} else {
+									Immediate::Pos(n as u64)
+								}
+							};
+							let offset = imm(target as i64 - pc as i64);
+							match op {
+								0 => Instr::Acc(imm(acc)),
+								1 => Instr::Jmp(offset),
+								_ => Instr::Nop(offset),
+							}
+						})
+						.collect()
+				})
+		})
+	}
+
+	fn value(i: &Immediate) -> i64 {
+		match *i {
+			Immediate::Pos(n) => n as i64,
+			Immediate::Neg(n) => -(n as i64),
+		}
+	}
+
+	/// Run `p` until it terminates, `Ok(acc)`, or is about to execute an
+	/// instruction a second time, `Err(acc)`.
+	pub(crate) fn run(p: &[Instr]) -> Result<i64, i64> {
+		let (mut pc, mut acc) = (0_i64, 0);
+		let mut seen = vec![false; p.len()];
+		while (pc as usize) < p.len() {
+			if seen[pc as usize] {
+				return Err(acc);
+			}
+			seen[pc as usize] = true;
+			match &p[pc as usize] {
+				Instr::Acc(i) => {
+					acc += value(i);
+					pc += 1;
+				}
+				Instr::Jmp(i) => pc += value(i),
+				Instr::Nop(_) => pc += 1,
+				_ => unreachable!(),
+			}
+		}
+		Ok(acc)
+	}
+
+	proptest! {
+		#[test]
+		fn prop_instr_display_round_trip(p in programs()) {
+			for instr in p {
+				let r = instr.to_string().parse::<Instr>();
+				prop_assert_eq!(instr, r.expect("Failed to parse"));
+			}
+		}
+
+		#[test]
+		fn prop_part1_matches_run(p in programs()) {
+			let r = find_answer_part1(&p).expect("Failed to run");
+			prop_assert_eq!(run(&p).err(), r);
+		}
+
+		#[test]
+		fn prop_part2_matches_every_flip(p in programs()) {
+			let fixes = (0..p.len())
+				.filter_map(|pc| {
+					let mut flipped = p.clone();
+					flipped[pc] = match p[pc] {
+						Instr::Jmp(i) => Instr::Nop(i),
+						Instr::Nop(i) => Instr::Jmp(i),
+						_ => return None,
+					};
+					run(&flipped).ok()
+				})
+				.collect::<Vec<_>>();
+			let r = find_answer_part2(&p).expect("Failed to run");
+			if run(&p).is_ok() {
+				prop_assert_eq!(None, r);
+			} else {
+				match r {
+					Some(acc) => prop_assert!(fixes.contains(&acc)),
+					None => prop_assert!(fixes.is_empty()),
+				}
+			}
+		}
+	}
+}
@@ -1,255 +1,624 @@
-use nom::{
-	branch::alt,
-	bytes::complete::tag,
-	character::complete::{digit1, newline},
-	combinator::{map, map_res},
-	error,
-	multi::many1,
-	sequence::{pair, preceded, terminated},
-	Err::Error,
-	IResult,
+use aoc_common::{
+	bench::{self, Time},
+	Args, Error, Options,
 };
-use std::{io, io::Read, str};
+use day8::{
+	bytecode,
+	cfg::{Cfg, PathEnd},
+	cycle, debugger, find_answer_part2, optimize, profile,
+	repair::{self, Search},
+	snapshot::VmState,
+	trace::{self, Trace},
+	Day8, Instr, LoopDetector, VirtualMachine,
+};
+use std::{
+	fs,
+	hint::black_box,
+	io::{self, Write},
+	path::{Path, PathBuf},
+	thread,
+};
+
+const TRACE_USAGE: &str = "Usage:
+  day8 trace record [--flip PC] -o FILE [-i INPUT]
+  day8 trace replay FILE [-i INPUT]
+  day8 trace diff FILE FILE
+  day8 trace summary FILE";
+
+#[derive(Debug, PartialEq)]
+enum TraceCommand {
+	/// Record a run, with the `jmp` or `nop` at `flip` changed.
+	Record {
+		flip: Option<usize>,
+		output: PathBuf,
+	},
+	Replay(PathBuf),
+	Diff(PathBuf, PathBuf),
+	Summary(PathBuf),
+}
+
+fn parse_trace_command(args: &[String]) -> Result<TraceCommand, Error> {
+	let mut options = Options::new(TRACE_USAGE, args);
+	let cmd = options
+		.next()
+		.ok_or_else(|| options.error("Missing command"))?;
+	match (cmd, options.rest()) {
+		("record", _) => {
+			let (mut flip, mut output) = (None, None);
+			while let Some(arg) = options.next() {
+				match arg {
+					"--flip" => flip = Some(options.parse(arg)?),
+					"-o" | "--output" => {
+						output = Some(options.value(arg)?.into())
+					}
+					_ => return Err(options.unknown(arg)),
+				}
+			}
+			let output =
+				output.ok_or_else(|| options.error("Missing -o FILE"))?;
+			Ok(TraceCommand::Record { flip, output })
+		}
+		("replay", [file]) => Ok(TraceCommand::Replay(file.into())),
+		("diff", [a, b]) => Ok(TraceCommand::Diff(a.into(), b.into())),
+		("summary", [file]) => Ok(TraceCommand::Summary(file.into())),
+		("replay", _) | ("diff", _) | ("summary", _) => {
+			Err(options.error(format!("Wrong number of files for {}", cmd)))
+		}
+		_ => Err(options.error(format!("Unknown command: {}", cmd))),
+	}
+}
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Immediate {
-	Pos(u64),
-	Neg(u64),
+fn load_program(args: &Args) -> Result<Vec<Instr>, Error> {
+	args.parse_input(&Day8, &args.load_input()?)
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum Instr {
-	Acc(Immediate),
-	Jmp(Immediate),
-	Nop(Immediate),
+fn load_trace(path: &Path) -> Result<Trace, Error> {
+	let data = fs::read(path).map_err(|e| Error::Io(Some(path.into()), e))?;
+	Trace::decode(&data)
 }
 
-#[derive(Debug)]
-struct VirtualMachine {
-	pc: usize,
-	acc: i64,
+/// Step through the program given with `-i` in the debugger, reading
+/// commands from stdin.
+fn debug(args: &Args) -> Result<(), Error> {
+	if args.input.is_none() {
+		return Err(Error::Usage(
+			"Give the program with -i, the debugger reads commands from stdin"
+				.into(),
+		));
+	}
+	let program = load_program(args)?;
+	let stdin = io::stdin();
+	let stdout = io::stdout();
+	debugger::repl(&program, stdin.lock(), stdout.lock())
 }
 
-impl VirtualMachine {
-	fn new() -> Self {
-		VirtualMachine { pc: 0, acc: 0 }
+fn join(pcs: &[usize]) -> String {
+	match pcs {
+		[] => "none".into(),
+		_ => pcs
+			.iter()
+			.map(usize::to_string)
+			.collect::<Vec<_>>()
+			.join(" "),
 	}
+}
 
-	fn step(&mut self, p: &Vec<Instr>) -> Option<i64> {
-		match p.get(self.pc) {
-			Some(Instr::Acc(Immediate::Pos(i))) => {
-				self.pc += 1;
-				self.acc += *i as i64;
-				None
-			}
-			Some(Instr::Acc(Immediate::Neg(i))) => {
-				self.pc += 1;
-				self.acc -= *i as i64;
-				None
-			}
-			Some(Instr::Jmp(Immediate::Pos(i))) => {
-				self.pc += *i as usize;
-				None
-			}
-			Some(Instr::Jmp(Immediate::Neg(i))) => {
-				self.pc -= *i as usize;
-				None
-			}
-			Some(Instr::Nop(_)) => {
-				self.pc += 1;
-				None
-			}
-			None if self.pc == p.len() => Some(self.acc),
-			None => panic!("Outside of bounds"),
+const CFG_USAGE: &str = "Usage: day8 cfg [--dot] [-i INPUT]";
+
+/// Write the control-flow graph in DOT format with `--dot`, otherwise a
+/// report of how the program ends and which swaps fix it.
+fn run_cfg<W: Write>(args: &Args, w: &mut W) -> Result<(), Error> {
+	let mut options = Options::new(CFG_USAGE, &args.free[1..]);
+	let mut dot = false;
+	while let Some(arg) = options.next() {
+		match arg {
+			"--dot" => dot = true,
+			_ => return Err(options.unknown(arg)),
 		}
 	}
+	let program = load_program(args)?;
+	let cfg = Cfg::new(&program);
+	if dot {
+		return cfg.write_dot(w);
+	}
+	let path = cfg.path();
+	let end = match path.end {
+		PathEnd::Terminates => "terminates".into(),
+		PathEnd::Loop(pc) => format!("loops at pc {}", pc),
+		PathEnd::Fault(pc) => format!("jumps out of the program at pc {}", pc),
+		PathEnd::Branch(pc) => format!("branches at pc {}", pc),
+	};
+	writeln!(w, "Path: {} instructions, {}", path.pcs.len(), end)
+		.and_then(|_| writeln!(w, "Loop: {}", join(path.cycle())))
+		.and_then(|_| writeln!(w, "Unreachable: {}", join(&cfg.unreachable())))
+		.and_then(|_| writeln!(w, "Fixes: {}", join(&cfg.fixes())))
+		.map_err(Error::Write)
 }
 
-fn immediate(i: &[u8]) -> IResult<&[u8], Immediate> {
-	map(
-		pair(
-			alt((tag("+"), tag("-"))),
-			map_res(map_res(digit1, str::from_utf8), str::parse),
-		),
-		|(s, n)| match s {
-			b"+" => Immediate::Pos(n),
-			b"-" => Immediate::Neg(n),
-			_ => panic!("Unknown sign"),
-		},
-	)(i)
+/// Describe the loop the program runs into.
+fn run_cycle<W: Write>(args: &Args, w: &mut W) -> Result<(), Error> {
+	Options::new("Usage: day8 cycle [-i INPUT]", &args.free[1..]).end()?;
+	let program = load_program(args)?;
+	match cycle::find(&program)? {
+		Some(c) => write!(w, "{}", c),
+		None => writeln!(w, "The program terminates"),
+	}
+	.map_err(Error::Write)
 }
 
-fn instruction(i: &[u8]) -> IResult<&[u8], Instr> {
-	terminated(
-		alt((
-			preceded(tag("acc "), map(immediate, |n| Instr::Acc(n))),
-			preceded(tag("jmp "), map(immediate, |n| Instr::Jmp(n))),
-			preceded(tag("nop "), map(immediate, |n| Instr::Nop(n))),
-		)),
-		newline,
-	)(i)
+/// Write the optimized program, with how many instructions it saved on
+/// stderr.
+fn run_optimize<W: Write>(args: &Args, w: &mut W) -> Result<(), Error> {
+	Options::new("Usage: day8 opt [-i INPUT]", &args.free[1..]).end()?;
+	let program = load_program(args)?;
+	let optimized = optimize::optimize(&program);
+	eprintln!(
+		"Optimized {} instructions to {}",
+		program.len(),
+		optimized.len()
+	);
+	write!(w, "{}", bytecode::disassemble(&optimized)).map_err(Error::Write)
 }
 
-fn program(i: &[u8]) -> IResult<&[u8], Vec<Instr>> {
-	many1(instruction)(i)
+/// Convert the program given with `-i` to bytecode, or bytecode back to
+/// text.
+fn run_convert<W: Write>(args: &Args, w: &mut W) -> Result<(), Error> {
+	const USAGE: &str = "Usage:
+  day8 asm -o FILE [-i INPUT]
+  day8 disasm FILE";
+	match (args.free[0].as_str(), &args.free[1..]) {
+		("asm", [flag, output]) if flag == "-o" || flag == "--output" => {
+			let data = bytecode::encode(&load_program(args)?);
+			fs::write(output, data).map_err(Error::Write)
+		}
+		("disasm", [input]) => {
+			let path = Path::new(input);
+			let data =
+				fs::read(path).map_err(|e| Error::Io(Some(path.into()), e))?;
+			let program = bytecode::decode(&data)?;
+			write!(w, "{}", bytecode::disassemble(&program))
+				.map_err(Error::Write)
+		}
+		_ => Err(Error::Usage(USAGE.into())),
+	}
 }
 
-fn find_answer_part1(p: &Vec<Instr>) -> i64 {
-	let mut vm = VirtualMachine::new();
-	let mut tr = vec![0; p.len()];
+const REPAIR_USAGE: &str =
+	"Usage: day8 repair [-k N] [--edits swap,imm,delete] [-i INPUT]";
 
-	loop {
-		match tr.get_mut(vm.pc) {
-			Some(n) if *n > 0 => return vm.acc,
-			Some(n) => *n += 1,
-			None => panic!(),
+fn parse_search(args: &[String]) -> Result<Search, Error> {
+	let mut options = Options::new(REPAIR_USAGE, args);
+	let mut search = Search::default();
+	while let Some(arg) = options.next() {
+		match arg {
+			"-k" => search.max_edits = options.parse(arg)?,
+			"--edits" => {
+				search.swaps = false;
+				search.immediates = false;
+				search.deletions = false;
+				for kind in options.value(arg)?.split(',') {
+					match kind {
+						"swap" => search.swaps = true,
+						"imm" => search.immediates = true,
+						"delete" => search.deletions = true,
+						_ => {
+							let msg = format!("Unknown edit: {}", kind);
+							return Err(options.error(msg));
+						}
+					}
+				}
+			}
+			_ => return Err(options.unknown(arg)),
 		}
-		vm.step(p);
 	}
+	Ok(search)
 }
 
-fn find_answer_part2(p: &Vec<Instr>) -> Option<i64> {
-	let mut vm = VirtualMachine::new();
-	let mut tr = vec![None; p.len()];
+/// List every repair of the program with up to `-k` edits, each as a diff.
+fn run_repair<W: Write>(args: &Args, w: &mut W) -> Result<(), Error> {
+	let search = parse_search(&args.free[1..])?;
+	let program = load_program(args)?;
+	let repairs = search.run(&program);
+	if repairs.is_empty() {
+		return writeln!(w, "No repairs").map_err(Error::Write);
+	}
+	for (n, r) in repairs.iter().enumerate() {
+		let edits = r
+			.edits
+			.iter()
+			.map(ToString::to_string)
+			.collect::<Vec<_>>()
+			.join(", ");
+		writeln!(
+			w,
+			"Repair {}: {}; {} steps, acc {}",
+			n + 1,
+			if edits.is_empty() { "none" } else { &edits },
+			r.steps,
+			r.acc
+		)
+		.and_then(|_| write!(w, "{}", r.diff(&program)))
+		.map_err(Error::Write)?;
+	}
+	Ok(())
+}
+
+const PROFILE_USAGE: &str =
+	"Usage: day8 profile [--steps N] [--top N] [--listing] [-i INPUT]";
+
+#[derive(Debug, PartialEq)]
+struct ProfileOptions {
+	/// Steps to run before giving up on the program terminating.
+	steps: usize,
+	/// Instructions to show in the hot-spot table.
+	top: usize,
+	listing: bool,
+}
 
-	loop {
-		match tr.get_mut(vm.pc) {
-			Some(Some(_)) => break,
-			Some(r @ None) => *r = Some((vm.pc, vm.acc)),
-			None => panic!(),
+fn parse_profile_options(args: &[String]) -> Result<ProfileOptions, Error> {
+	let mut args = Options::new(PROFILE_USAGE, args);
+	let mut options = ProfileOptions {
+		steps: 1_000_000,
+		top: 10,
+		listing: false,
+	};
+	while let Some(arg) = args.next() {
+		match arg {
+			"--steps" => options.steps = args.parse(arg)?,
+			"--top" => options.top = args.parse(arg)?,
+			"--listing" => options.listing = true,
+			_ => return Err(args.unknown(arg)),
 		}
-		vm.step(p);
 	}
+	Ok(options)
+}
 
-	let candidates = tr.iter().zip(p.iter()).filter_map(|r| match r {
-		(Some((pc, acc)), Instr::Nop(Immediate::Pos(i))) => {
-			Some((pc + *i as usize, *acc))
-		}
-		(Some((pc, acc)), Instr::Nop(Immediate::Neg(i))) => {
-			Some((pc - *i as usize, *acc))
+/// Run the program and report where its steps go, with `--listing` also
+/// the program annotated with the hits of each instruction.
+fn run_profile<W: Write>(args: &Args, w: &mut W) -> Result<(), Error> {
+	let options = parse_profile_options(&args.free[1..])?;
+	let program = load_program(args)?;
+	let (profile, r) = profile::profile(&program, options.steps);
+	let stop = match r {
+		Ok(Some(acc)) => format!("terminated with acc {}", acc),
+		Ok(None) => "stopped at the step limit".into(),
+		Err(fault) => fault.to_string(),
+	};
+	writeln!(w, "Profiled {} steps: {}", profile.steps(), stop)
+		.map_err(Error::Write)?;
+	profile.write_report(w, &program, options.top)?;
+	if options.listing {
+		writeln!(w, "Listing:").map_err(Error::Write)?;
+		profile.write_listing(w, &program)?;
+	}
+	Ok(())
+}
+
+const RUN_USAGE: &str =
+	"Usage: day8 run [--steps N] [--resume FILE] [--save FILE] [-i INPUT]";
+
+#[derive(Debug, Default, PartialEq)]
+struct RunOptions {
+	/// Steps to run, without a limit if `None`.
+	steps: Option<usize>,
+	resume: Option<PathBuf>,
+	save: Option<PathBuf>,
+}
+
+fn parse_run_options(args: &[String]) -> Result<RunOptions, Error> {
+	let mut args = Options::new(RUN_USAGE, args);
+	let mut options = RunOptions::default();
+	while let Some(arg) = args.next() {
+		match arg {
+			"--steps" => options.steps = Some(args.parse(arg)?),
+			"--resume" => options.resume = Some(args.value(arg)?.into()),
+			"--save" => options.save = Some(args.value(arg)?.into()),
+			_ => return Err(args.unknown(arg)),
 		}
-		(Some((pc, acc)), Instr::Jmp(_)) => Some((pc + 1, *acc)),
-		_ => None,
-	});
+	}
+	Ok(options)
+}
 
-	for (pc, acc) in candidates {
-		vm.pc = pc;
-		vm.acc = acc;
-		loop {
-			if tr.get(vm.pc).map_or(false, |r| r.is_some()) {
-				break;
-			}
-			if let Some(r) = vm.step(p) {
-				return Some(r);
+/// Run the program, from a saved state with `--resume`, until it stops,
+/// for `--steps` or until it is found to loop. With `--save` the state it
+/// stopped in is written out so the run can be resumed.
+fn run_vm<W: Write>(args: &Args, w: &mut W) -> Result<(), Error> {
+	let options = parse_run_options(&args.free[1..])?;
+	let program = load_program(args)?;
+	let mut vm = match &options.resume {
+		Some(path) => {
+			let data =
+				fs::read(path).map_err(|e| Error::Io(Some(path.into()), e))?;
+			VirtualMachine::from(VmState::decode(&data)?)
+		}
+		None => VirtualMachine::new(),
+	};
+	let mut detector = LoopDetector::new(&program);
+	let mut steps = 0;
+	// What stopped the run, and whether it was `--steps`, after which it
+	// can go on.
+	let (stop, resumable) = loop {
+		if options.steps == Some(steps) {
+			let stop = format!("stopped at pc {} with acc {}", vm.pc, vm.acc);
+			break (stop, true);
+		}
+		if let Err(fault) = detector.check(&vm, &program) {
+			break (format!("{} with acc {}", fault, vm.acc), false);
+		}
+		match vm.step(&program) {
+			Ok(Some(acc)) => {
+				break (format!("terminated with acc {}", acc), false)
 			}
+			Ok(None) => steps += 1,
+			Err(fault) => break (fault.to_string(), false),
+		}
+	};
+	writeln!(w, "Ran {} steps: {}", steps, stop).map_err(Error::Write)?;
+	match &options.save {
+		Some(path) => {
+			fs::write(path, vm.snapshot().encode()).map_err(Error::Write)
 		}
+		None if resumable => {
+			writeln!(w, "Save the state with --save FILE to resume it")
+				.map_err(Error::Write)
+		}
+		None => Ok(()),
 	}
-	None
 }
 
-fn main() {
-	let stdin = io::stdin();
-	let mut buffer = Vec::new();
-	stdin
-		.lock()
-		.read_to_end(&mut buffer)
-		.expect("Failed to read from stdin");
-	let (rest, p) = match program(&buffer) {
-		Err(Error(error::Error { input, code: _ })) => panic!(
-			"Error occured processing input: {}",
-			str::from_utf8(input).unwrap()
-		),
-		Ok(x) => x,
-		e => panic!("Other error: {:?}", e),
+const BENCH_USAGE: &str =
+	"Usage: day8 bench [--warmup N] [--runs N] [--threads N] [-i INPUT]";
+
+fn parse_bench_options(
+	args: &[String],
+) -> Result<(bench::Config, usize), Error> {
+	let mut options = Options::new(BENCH_USAGE, args);
+	let mut config = bench::Config::default();
+	let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+	while let Some(arg) = options.next() {
+		match arg {
+			"--warmup" => config.warmup = options.parse(arg)?,
+			"--runs" => config.runs = options.parse(arg)?,
+			"--threads" => threads = options.parse(arg)?,
+			_ => return Err(options.unknown(arg)),
+		}
+	}
+	Ok((config, threads))
+}
+
+/// Time the serial part 2 search against the parallel search for the
+/// lowest swap, on one thread and on `--threads`.
+fn run_bench<W: Write>(args: &Args, w: &mut W) -> Result<(), Error> {
+	let (config, threads) = parse_bench_options(&args.free[1..])?;
+	let program = load_program(args)?;
+	let serial = bench::sample(&config, || {
+		find_answer_part2(black_box(&program)).map(drop)
+	})?;
+	let parallel = |threads| {
+		bench::sample(&config, || {
+			repair::lowest_swap(black_box(&program), threads);
+			Ok(())
+		})
 	};
-	if rest.len() > 0 {
-		panic!(
-			"Not all input used: {}",
-			str::from_utf8(rest).unwrap_or("[Invalid utf8]")
-		);
+	let rows = [
+		("serial".to_string(), serial),
+		("parallel, 1 thread".into(), parallel(1)?),
+		(format!("parallel, {} threads", threads), parallel(threads)?),
+	];
+	writeln!(w, "{:<22}  {:>10}  {:>10}", "search", "median", "p95")
+		.map_err(Error::Write)?;
+	for (name, stats) in &rows {
+		writeln!(
+			w,
+			"{:<22}  {:>10}  {:>10}",
+			name,
+			Time(stats.median),
+			Time(stats.p95)
+		)
+		.map_err(Error::Write)?;
 	}
+	Ok(())
+}
 
-	let answer_part1 = find_answer_part1(&p);
-	let answer_part2 = find_answer_part2(&p).expect("No answer found for part2");
+fn run_trace<W: Write>(args: &Args, w: &mut W) -> Result<(), Error> {
+	match parse_trace_command(&args.free[1..])? {
+		TraceCommand::Record { flip, output } => {
+			let mut program = load_program(args)?;
+			if let Some(pc) = flip {
+				program[pc] = match program.get(pc) {
+					Some(Instr::Jmp(i)) => Instr::Nop(*i),
+					Some(Instr::Nop(i)) => Instr::Jmp(*i),
+					_ => {
+						return Err(Error::Usage(format!(
+							"No jmp or nop at pc {}",
+							pc
+						)))
+					}
+				};
+			}
+			let (trace, r) = trace::record(&program);
+			fs::write(&output, trace.encode()).map_err(Error::Write)?;
+			let stop = match r {
+				Ok(acc) => format!("terminated with acc {}", acc),
+				Err(fault) => fault.to_string(),
+			};
+			writeln!(w, "Recorded {} steps: {}", trace.steps.len(), stop)
+		}
+		TraceCommand::Replay(path) => {
+			let trace = load_trace(&path)?;
+			let vm = trace.replay(&load_program(args)?)?;
+			for s in &trace.steps {
+				writeln!(w, "{}", s).map_err(Error::Write)?;
+			}
+			writeln!(w, "pc {} acc {}", vm.pc, vm.acc)
+		}
+		TraceCommand::Diff(a, b) => {
+			let (ta, tb) = (load_trace(&a)?, load_trace(&b)?);
+			match ta.diff(&tb) {
+				Some(n) => {
+					writeln!(w, "Traces differ at step {}", n)
+						.map_err(Error::Write)?;
+					for (path, t) in [(&a, &ta), (&b, &tb)] {
+						match t.steps.get(n) {
+							Some(s) => writeln!(w, "{}: {}", path.display(), s),
+							None => writeln!(w, "{}: <end>", path.display()),
+						}
+						.map_err(Error::Write)?;
+					}
+					Ok(())
+				}
+				None => writeln!(w, "Traces are the same"),
+			}
+		}
+		TraceCommand::Summary(path) => {
+			let trace = load_trace(&path)?;
+			for (pc, (instr, n)) in trace.hits() {
+				writeln!(w, "{:>5}: {:<10} {}", pc, instr.to_string(), n)
+					.map_err(Error::Write)?;
+			}
+			Ok(())
+		}
+	}
+	.map_err(Error::Write)
+}
 
-	println!("Answer part1: {}", answer_part1);
-	println!("Answer part2: {}", answer_part2);
+fn main() {
+	aoc_common::run(|| {
+		let args = Args::from_env()?;
+		match args.free.first().map(String::as_str) {
+			Some("debug") => debug(&args),
+			Some("trace") => run_trace(&args, &mut io::stdout().lock()),
+			Some("cfg") => run_cfg(&args, &mut io::stdout().lock()),
+			Some("cycle") => run_cycle(&args, &mut io::stdout().lock()),
+			Some("bench") => run_bench(&args, &mut io::stdout().lock()),
+			Some("run") => run_vm(&args, &mut io::stdout().lock()),
+			Some("opt") => run_optimize(&args, &mut io::stdout().lock()),
+			Some("profile") => run_profile(&args, &mut io::stdout().lock()),
+			Some("repair") => run_repair(&args, &mut io::stdout().lock()),
+			Some("asm") | Some("disasm") => {
+				run_convert(&args, &mut io::stdout().lock())
+			}
+			_ => aoc_common::run_solution(&Day8, &args),
+		}
+	})
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
 
-	#[test]
-	fn test_parse_immediate() {
-		let (rest, r) = immediate(b"+10").expect("Failed to parse input");
-		assert_eq!(b"", rest, "Not all input consumed");
-		assert_eq!(Immediate::Pos(10), r);
+	fn args(s: &str) -> Vec<String> {
+		s.split_whitespace().map(String::from).collect()
 	}
 
 	#[test]
-	fn test_parse_immediate_negative() {
-		let (rest, r) = immediate(b"-10").expect("Failed to parse input");
-		assert_eq!(b"", rest, "Not all input consumed");
-		assert_eq!(Immediate::Neg(10), r);
+	fn test_parse_trace_command() {
+		assert_eq!(
+			TraceCommand::Record {
+				flip: Some(3),
+				output: "t".into()
+			},
+			parse_trace_command(&args("record --flip 3 -o t")).unwrap()
+		);
+		assert_eq!(
+			TraceCommand::Diff("a".into(), "b".into()),
+			parse_trace_command(&args("diff a b")).unwrap()
+		);
+		assert_eq!(
+			TraceCommand::Summary("a".into()),
+			parse_trace_command(&args("summary a")).unwrap()
+		);
 	}
 
 	#[test]
-	fn test_parse_instruction_acc() {
-		let (rest, r) =
-			instruction(b"acc +10\n").expect("Failed to parse input");
-		assert_eq!(b"", rest, "Not all input consumed");
-		assert_eq!(Instr::Acc(Immediate::Pos(10)), r);
+	fn test_parse_trace_command_invalid() {
+		assert!(parse_trace_command(&args("")).is_err());
+		assert!(parse_trace_command(&args("record")).is_err());
+		assert!(parse_trace_command(&args("record -o")).is_err());
+		assert!(parse_trace_command(&args("record --flip x -o t")).is_err());
+		assert!(parse_trace_command(&args("diff a")).is_err());
+		assert!(parse_trace_command(&args("play a")).is_err());
 	}
 
 	#[test]
-	fn test_parse_instruction_jmp() {
-		let (rest, r) =
-			instruction(b"jmp +10\n").expect("Failed to parse input");
-		assert_eq!(b"", rest, "Not all input consumed");
-		assert_eq!(Instr::Jmp(Immediate::Pos(10)), r);
+	fn test_parse_search() {
+		assert_eq!(Search::default(), parse_search(&[]).unwrap());
+		assert_eq!(
+			Search {
+				max_edits: 2,
+				swaps: true,
+				immediates: false,
+				deletions: true
+			},
+			parse_search(&args("-k 2 --edits swap,delete")).unwrap()
+		);
+		assert!(parse_search(&args("-k")).is_err());
+		assert!(parse_search(&args("-k x")).is_err());
+		assert!(parse_search(&args("--edits move")).is_err());
 	}
 
 	#[test]
-	fn test_parse_instruction_nop() {
-		let (rest, r) =
-			instruction(b"nop +10\n").expect("Failed to parse input");
-		assert_eq!(b"", rest, "Not all input consumed");
-		assert_eq!(Instr::Nop(Immediate::Pos(10)), r);
+	fn test_parse_bench_options() {
+		let (config, threads) =
+			parse_bench_options(&args("--runs 5 --threads 3 --warmup 0"))
+				.unwrap();
+		assert_eq!(bench::Config { warmup: 0, runs: 5 }, config);
+		assert_eq!(3, threads);
+		assert!(parse_bench_options(&args("--runs")).is_err());
+		assert!(parse_bench_options(&args("--threads x")).is_err());
+		assert!(parse_bench_options(&args("--cores 2")).is_err());
 	}
 
 	#[test]
-	fn test_vm_step_acc() {
-		let mut vm = VirtualMachine::new();
-		vm.step(&vec![Instr::Acc(Immediate::Pos(1))]);
-		assert_eq!(vm.pc, 1);
-		assert_eq!(vm.acc, 1);
+	fn test_parse_run_options() {
+		assert_eq!(RunOptions::default(), parse_run_options(&[]).unwrap());
+		assert_eq!(
+			RunOptions {
+				steps: Some(10),
+				resume: Some("a".into()),
+				save: Some("b".into())
+			},
+			parse_run_options(&args("--save b --steps 10 --resume a")).unwrap()
+		);
+		assert!(parse_run_options(&args("--save")).is_err());
+		assert!(parse_run_options(&args("--steps -1")).is_err());
+		assert!(parse_run_options(&args("--load a")).is_err());
 	}
 
 	#[test]
-	fn test_vm_step_jmp_pos() {
-		let mut vm = VirtualMachine::new();
-		vm.step(&vec![Instr::Jmp(Immediate::Pos(1))]);
-		assert_eq!(vm.pc, 1);
-		assert_eq!(vm.acc, 0);
-	}
-	#[test]
-	fn test_vm_step_jmp_zero() {
-		let mut vm = VirtualMachine::new();
-		vm.step(&vec![Instr::Jmp(Immediate::Pos(0))]);
-		assert_eq!(vm.pc, 0);
-		assert_eq!(vm.acc, 0);
+	fn test_run_vm_stops_on_loop() {
+		let input = Path::new(env!("CARGO_MANIFEST_DIR")).join("input/input");
+		let run = |opts: &str| {
+			let cmd = format!("run {} -i {}", opts, input.display());
+			let mut out = vec![];
+			run_vm(&Args::parse(args(&cmd)).unwrap(), &mut out).unwrap();
+			String::from_utf8(out).unwrap()
+		};
+		// A run known to loop would only loop again, so is not resumed.
+		assert_eq!(
+			"Ran 201 steps: infinite loop at pc 74: acc +1 with acc 2080\n",
+			run("")
+		);
+		assert_eq!(
+			"Ran 5 steps: stopped at pc 497 with acc 16\n\
+			Save the state with --save FILE to resume it\n",
+			run("--steps 5")
+		);
 	}
 
 	#[test]
-	fn test_vm_step_jmp_neg() {
-		let mut vm = VirtualMachine::new();
-		vm.pc = 1;
-		vm.step(&vec![
-			Instr::Nop(Immediate::Pos(0)),
-			Instr::Jmp(Immediate::Neg(1)),
-		]);
-		assert_eq!(vm.pc, 0);
-		assert_eq!(vm.acc, 0);
+	fn test_parse_profile_options() {
+		assert_eq!(
+			ProfileOptions {
+				steps: 50,
+				top: 3,
+				listing: true
+			},
+			parse_profile_options(&args("--top 3 --listing --steps 50"))
+				.unwrap()
+		);
+		assert!(parse_profile_options(&args("--top")).is_err());
+		assert!(parse_profile_options(&args("--steps x")).is_err());
+		assert!(parse_profile_options(&args("--depth 3")).is_err());
 	}
 }
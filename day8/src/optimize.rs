@@ -0,0 +1,315 @@
+//! Optimization passes over console programs.
+//!
+//! Every pass keeps how a program ends: it terminates with the same
+//! accumulator, faults, or runs forever as before. It may take fewer
+//! steps, and a fault may be reported at another pc. Passes that remove
+//! instructions move every jump to where it went before, and jumps out
+//! of the program stay out of it.
+
+use crate::{cfg::Cfg, Immediate, Instr};
+use std::convert::TryFrom;
+
+/// Argument of a jump.
+fn jump(instr: &Instr) -> Option<Immediate> {
+	match instr {
+		Instr::Jmp(i) | Instr::Jz(i) | Instr::Jnz(i) => Some(*i),
+		_ => None,
+	}
+}
+
+/// The jump `instr` with argument `i`.
+fn with_jump(instr: &Instr, i: Immediate) -> Instr {
+	match instr {
+		Instr::Jmp(_) => Instr::Jmp(i),
+		Instr::Jz(_) => Instr::Jz(i),
+		Instr::Jnz(_) => Instr::Jnz(i),
+		instr => instr.clone(),
+	}
+}
+
+/// Where `i` moves `pc`, which may be outside the program.
+fn target(pc: usize, i: Immediate) -> i128 {
+	match i {
+		Immediate::Pos(n) => pc as i128 + i128::from(n),
+		Immediate::Neg(n) => pc as i128 - i128::from(n),
+	}
+}
+
+/// The argument that moves `pc` to `target`, which must be no further
+/// from `pc` than an argument can move it.
+fn relative(pc: usize, target: i128) -> Immediate {
+	let d = target - pc as i128;
+	let n = |d: i128| u64::try_from(d).expect("Offset fits an argument");
+	if d < 0 {
+		Immediate::Neg(n(-d))
+	} else {
+		Immediate::Pos(n(d))
+	}
+}
+
+/// Pcs some jump may move to.
+fn jump_targets(p: &[Instr]) -> Vec<bool> {
+	let mut targeted = vec![false; p.len()];
+	for (pc, instr) in p.iter().enumerate() {
+		if let Some(i) = jump(instr) {
+			if let Some(t) = i.offset(pc).and_then(|t| targeted.get_mut(t)) {
+				*t = true;
+			}
+		}
+	}
+	targeted
+}
+
+/// `p` without the instructions that are not kept, with jumps moved to
+/// the same instructions. A jump to a removed instruction goes to the
+/// next one that is kept, which is where the removed one would go.
+fn rebuild(p: &[Instr], keep: &[bool]) -> Vec<Instr> {
+	// New pc of each instruction, and of the end.
+	let mut new_pc = Vec::with_capacity(p.len() + 1);
+	let mut n = 0;
+	for &k in keep {
+		new_pc.push(n);
+		n += usize::from(k);
+	}
+	new_pc.push(n);
+	let len = p.len() as i128;
+	let moved = |t: i128| match t {
+		t if t < 0 => t,
+		t if t > len => t - len + n as i128,
+		t => new_pc[t as usize] as i128,
+	};
+	p.iter()
+		.enumerate()
+		.filter(|&(pc, _)| keep[pc])
+		.map(|(pc, instr)| match jump(instr) {
+			Some(i) => {
+				let t = moved(target(pc, i));
+				with_jump(instr, relative(new_pc[pc], t))
+			}
+			None => instr.clone(),
+		})
+		.collect()
+}
+
+/// Jumps to a `jmp` moved to where the `jmp` goes, following chains of
+/// them unless they loop.
+pub fn thread_jumps(p: &[Instr]) -> Vec<Instr> {
+	let len = p.len();
+	p.iter()
+		.enumerate()
+		.map(|(pc, instr)| {
+			let mut t = match jump(instr).and_then(|i| i.offset(pc)) {
+				Some(t) if t < len => t,
+				_ => return instr.clone(),
+			};
+			// A chain longer than the program must loop.
+			for _ in 0..len {
+				match &p[t] {
+					Instr::Jmp(i) => match i.offset(t) {
+						Some(n) if n < len => t = n,
+						_ => break,
+					},
+					_ => return with_jump(instr, Immediate::between(pc, t)),
+				}
+			}
+			instr.clone()
+		})
+		.collect()
+}
+
+/// Without `nop`, `acc +0` and jumps to the next instruction.
+pub fn remove_no_ops(p: &[Instr]) -> Vec<Instr> {
+	let keep = p
+		.iter()
+		.map(|instr| match instr {
+			Instr::Nop(_) | Instr::Acc(Immediate::Pos(0)) => false,
+			instr => jump(instr) != Some(Immediate::Pos(1)),
+		})
+		.collect::<Vec<_>>();
+	rebuild(p, &keep)
+}
+
+/// `acc` instructions merged into the one before when no jump goes to
+/// them. Only arguments of the same sign are merged, as those overflow
+/// together exactly when one of them would.
+pub fn merge_acc(p: &[Instr]) -> Vec<Instr> {
+	let targeted = jump_targets(p);
+	let mut q = p.to_vec();
+	let mut keep = vec![true; p.len()];
+	let mut last = None;
+	for (pc, instr) in p.iter().enumerate() {
+		let merged = match (last, instr) {
+			(Some(l), Instr::Acc(b)) if !targeted[pc] => match (&q[l], b) {
+				(Instr::Acc(Immediate::Pos(a)), Immediate::Pos(b)) => {
+					a.checked_add(*b).map(Immediate::Pos)
+				}
+				(Instr::Acc(Immediate::Neg(a)), Immediate::Neg(b)) => {
+					a.checked_add(*b).map(Immediate::Neg)
+				}
+				_ => None,
+			},
+			_ => None,
+		};
+		match (merged, instr) {
+			(Some(i), _) => {
+				q[last.expect("Merged into an acc")] = Instr::Acc(i);
+				keep[pc] = false;
+			}
+			(None, Instr::Acc(_)) => last = Some(pc),
+			_ => last = None,
+		}
+	}
+	rebuild(&q, &keep)
+}
+
+/// Without the instructions that can not be reached from the start.
+pub fn remove_unreachable(p: &[Instr]) -> Vec<Instr> {
+	let reachable = Cfg::new(p).reachable();
+	rebuild(p, &reachable[..p.len()])
+}
+
+/// Run every pass until none of them changes the program.
+pub fn optimize(p: &[Instr]) -> Vec<Instr> {
+	let passes = [thread_jumps, remove_no_ops, merge_acc, remove_unreachable];
+	let mut p = p.to_vec();
+	loop {
+		let q = passes.iter().fold(p.clone(), |q, pass| pass(&q));
+		if q == p {
+			return p;
+		}
+		p = q;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		asm::assemble, bytecode::disassemble, isa::tests::instrs,
+		tests::programs, VirtualMachine, VmFault,
+	};
+	use proptest::prelude::*;
+
+	fn opt(pass: fn(&[Instr]) -> Vec<Instr>, src: &[u8]) -> String {
+		disassemble(&pass(&assemble(src).expect("Failed to assemble")))
+	}
+
+	#[test]
+	fn test_thread_jumps() {
+		assert_eq!(
+			"jz +3\njmp +2\njmp +1\nacc +1\n",
+			opt(thread_jumps, b"jz +1\njmp +1\njmp +1\nacc +1\n")
+		);
+		// Chains that loop are left alone.
+		assert_eq!(
+			"jmp +1\njmp +1\njmp -1\n",
+			opt(thread_jumps, b"jmp +1\njmp +1\njmp -1\n")
+		);
+		// So are jumps out of the program.
+		assert_eq!("jmp +1\njmp +5\n", opt(thread_jumps, b"jmp +1\njmp +5\n"));
+	}
+
+	#[test]
+	fn test_remove_no_ops() {
+		assert_eq!(
+			"acc +1\njmp -1\njmp +1\nacc +2\n",
+			opt(
+				remove_no_ops,
+				b"acc +1\nnop +4\njmp -2\nacc +0\njmp +2\njmp +1\nacc +2\n"
+			)
+		);
+		// Jumps out of the program stay out of it.
+		assert_eq!(
+			"jmp -3\njmp +3\n",
+			opt(remove_no_ops, b"nop +0\njmp -4\njmp +4\nnop +0\n")
+		);
+	}
+
+	#[test]
+	fn test_merge_acc() {
+		assert_eq!(
+			"acc +3\nacc -5\nacc +1\njmp -1\n",
+			opt(
+				merge_acc,
+				b"acc +1\nacc +2\nacc -2\nacc -3\nacc +1\njmp -1\n"
+			)
+		);
+		assert_eq!(
+			"acc +18446744073709551615\nacc +1\n",
+			opt(merge_acc, b"acc +18446744073709551615\nacc +1\n")
+		);
+	}
+
+	#[test]
+	fn test_remove_unreachable() {
+		assert_eq!(
+			"jmp +1\nacc +2\nhalt\n",
+			opt(
+				remove_unreachable,
+				b"jmp +3\nacc +1\nacc +1\nacc +2\nhalt\nacc +3\n"
+			)
+		);
+	}
+
+	#[test]
+	fn test_optimize() {
+		let src =
+			b"nop +0\nacc +1\nacc +2\njmp +1\nacc +3\njmp +2\nacc +9\njmp +1\n\
+			acc +0\n";
+		assert_eq!("acc +6\n", opt(optimize, src));
+		assert_eq!(
+			"acc +1\njmp -1\n",
+			opt(optimize, b"nop +0\nacc +1\njmp -1\n")
+		);
+	}
+
+	/// How a run of at most `limit` steps ends: the accumulator if the
+	/// program terminates, `None` if it is still running, or a fault.
+	fn outcome(p: &[Instr], limit: usize) -> Result<Option<i64>, ()> {
+		let mut vm = VirtualMachine::new();
+		for _ in 0..limit {
+			match vm.step(p) {
+				Ok(None) => {}
+				r => return r.map_err(drop),
+			}
+		}
+		Ok(None)
+	}
+
+	proptest! {
+		#[test]
+		fn prop_optimize_keeps_run(p in programs()) {
+			// Without conditional jumps a program that comes back to an
+			// instruction runs forever.
+			let q = optimize(&p);
+			let end = |r: Result<i64, VmFault>| match r {
+				Ok(acc) => Ok(Some(acc)),
+				Err(VmFault::InfiniteLoopDetected(..)) => Ok(None),
+				Err(_) => Err(()),
+			};
+			prop_assert_eq!(
+				end(VirtualMachine::new().run(&p)),
+				end(VirtualMachine::new().run(&q))
+			);
+			prop_assert!(q.len() <= p.len());
+		}
+
+		#[test]
+		fn prop_optimize_keeps_outcome(
+			p in prop::collection::vec(instrs(), 1..30)
+		) {
+			// The optimized program takes at most as many steps, and the
+			// original at most one more than its length for each of them.
+			let q = optimize(&p);
+			let limit = 1000;
+			let r = outcome(&p, limit);
+			if r != Ok(None) {
+				prop_assert_eq!(r, outcome(&q, limit));
+			}
+			let r = outcome(&q, limit);
+			if r != Ok(None) {
+				prop_assert_eq!(outcome(&p, limit * (p.len() + 1)), r);
+			}
+		}
+	}
+}
@@ -0,0 +1,298 @@
+//! Execution counts of a program run.
+//!
+//! A machine made with `VirtualMachine::profiling` counts every
+//! instruction it executes, by pc and by opcode, and whether each jump
+//! was taken.
+
+use crate::{Instr, Op, VirtualMachine, VmFault};
+use aoc_common::Error;
+use std::{
+	cmp::Reverse,
+	io::{self, Write},
+};
+
+/// How often a jump moved the pc and how often it fell through.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Branch {
+	pub taken: usize,
+	pub not_taken: usize,
+}
+
+/// Counts of the instructions executed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Profile {
+	/// Executions of each instruction, by pc.
+	pub hits: Vec<usize>,
+	/// Executions of each opcode, by its index in `Op::ALL`.
+	pub ops: Vec<usize>,
+	/// Outcomes of each jump, by pc. Instructions that never jump have
+	/// no branch.
+	pub branches: Vec<Option<Branch>>,
+}
+
+fn count<T: Default + Clone>(v: &mut Vec<T>, i: usize) -> &mut T {
+	if v.len() <= i {
+		v.resize(i + 1, T::default());
+	}
+	&mut v[i]
+}
+
+impl Profile {
+	/// Count `instr` executed at `pc` with `acc` before it.
+	pub(crate) fn record(&mut self, pc: usize, instr: &Instr, acc: i64) {
+		*count(&mut self.hits, pc) += 1;
+		*count(&mut self.ops, instr.op() as usize) += 1;
+		let taken = match instr {
+			Instr::Jmp(_) => true,
+			Instr::Jz(_) => acc == 0,
+			Instr::Jnz(_) => acc != 0,
+			_ => return,
+		};
+		let branch =
+			count(&mut self.branches, pc).get_or_insert_with(Branch::default);
+		if taken {
+			branch.taken += 1;
+		} else {
+			branch.not_taken += 1;
+		}
+	}
+
+	/// Number of instructions executed.
+	pub fn steps(&self) -> usize {
+		self.hits.iter().sum()
+	}
+
+	/// Executed instructions by pc, the most executed first.
+	pub fn hot_spots(&self) -> Vec<(usize, usize)> {
+		let mut spots = self
+			.hits
+			.iter()
+			.copied()
+			.enumerate()
+			.filter(|&(_, n)| n > 0)
+			.collect::<Vec<_>>();
+		spots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+		spots
+	}
+
+	/// Executed opcodes, the most executed first.
+	pub fn hot_ops(&self) -> Vec<(Op, usize)> {
+		let mut ops = Op::ALL
+			.iter()
+			.zip(self.ops.iter().copied())
+			.filter(|&(_, n)| n > 0)
+			.map(|(&op, n)| (op, n))
+			.collect::<Vec<_>>();
+		ops.sort_by_key(|&(_, n)| Reverse(n));
+		ops
+	}
+
+	fn branch(&self, pc: usize) -> Option<Branch> {
+		self.branches.get(pc).copied().flatten()
+	}
+
+	/// Write the `top` most executed instructions of `p`, the opcodes and
+	/// the jumps executed.
+	pub fn write_report<W: Write>(
+		&self,
+		w: &mut W,
+		p: &[Instr],
+		top: usize,
+	) -> Result<(), Error> {
+		self.report(w, p, top).map_err(Error::Write)
+	}
+
+	fn report<W: Write>(
+		&self,
+		w: &mut W,
+		p: &[Instr],
+		top: usize,
+	) -> io::Result<()> {
+		let steps = self.steps();
+		let percent = |n: usize| 100.0 * n as f64 / steps.max(1) as f64;
+
+		writeln!(w, "Hot spots:")?;
+		writeln!(
+			w,
+			"{:>5} {:>6}  {:<14} {:>10} {:>6}",
+			"rank", "pc", "instruction", "hits", "%"
+		)?;
+		for (rank, (pc, n)) in
+			self.hot_spots().into_iter().take(top).enumerate()
+		{
+			writeln!(
+				w,
+				"{:>5} {:>6}  {:<14} {:>10} {:>6.1}",
+				rank + 1,
+				pc,
+				p[pc].to_string(),
+				n,
+				percent(n)
+			)?;
+		}
+
+		writeln!(w, "Opcodes:")?;
+		for (op, n) in self.hot_ops() {
+			writeln!(w, "{:>5} {:>10} {:>6.1}", op.name(), n, percent(n))?;
+		}
+
+		writeln!(w, "Jumps:")?;
+		writeln!(
+			w,
+			"{:>6}  {:<14} {:>10} {:>10}",
+			"pc", "instruction", "taken", "not taken"
+		)?;
+		for (pc, instr) in p.iter().enumerate() {
+			if let Some(b) = self.branch(pc) {
+				writeln!(
+					w,
+					"{:>6}  {:<14} {:>10} {:>10}",
+					pc,
+					instr.to_string(),
+					b.taken,
+					b.not_taken
+				)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Write `p` with the number of times each instruction was executed,
+	/// and for jumps how often they were taken.
+	pub fn write_listing<W: Write>(
+		&self,
+		w: &mut W,
+		p: &[Instr],
+	) -> Result<(), Error> {
+		self.listing(w, p).map_err(Error::Write)
+	}
+
+	fn listing<W: Write>(&self, w: &mut W, p: &[Instr]) -> io::Result<()> {
+		for (pc, instr) in p.iter().enumerate() {
+			let hits = match self.hits.get(pc) {
+				Some(&n) if n > 0 => n.to_string(),
+				_ => "-".into(),
+			};
+			write!(w, "{:>10} {:>6}: {}", hits, pc, instr)?;
+			if let Some(b) = self.branch(pc) {
+				write!(w, "  ; taken {}/{}", b.taken, b.taken + b.not_taken)?;
+			}
+			writeln!(w)?;
+		}
+		Ok(())
+	}
+}
+
+/// Run `p` from the start for at most `limit` steps, counting the
+/// instructions executed.
+///
+/// Returns the profile with the accumulator on termination, `None` if
+/// the limit was reached first, or the fault.
+pub fn profile(
+	p: &[Instr],
+	limit: usize,
+) -> (Profile, Result<Option<i64>, VmFault>) {
+	let mut vm = VirtualMachine::profiling();
+	let mut r = Ok(None);
+	for _ in 0..limit {
+		r = vm.step(p);
+		if !matches!(r, Ok(None)) {
+			break;
+		}
+	}
+	(vm.profile.unwrap_or_default(), r)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{asm::assemble, tests::programs, trace::record, Day8};
+	use aoc_common::Solution;
+	use proptest::prelude::*;
+
+	const COUNTDOWN: &[u8] = b"acc +3\nloop: acc -1\njnz loop\nhalt\n";
+
+	#[test]
+	fn test_profile() {
+		let p = assemble(COUNTDOWN).expect("Failed to assemble");
+		let (profile, r) = profile(&p, 100);
+		assert_eq!(Ok(Some(0)), r);
+		assert_eq!(vec![1, 3, 3, 1], profile.hits);
+		assert_eq!(8, profile.steps());
+		assert_eq!(vec![(1, 3), (2, 3), (0, 1), (3, 1)], profile.hot_spots());
+		assert_eq!(
+			vec![(Op::Acc, 4), (Op::Jnz, 3), (Op::Halt, 1)],
+			profile.hot_ops()
+		);
+		assert_eq!(
+			Some(Branch {
+				taken: 2,
+				not_taken: 1
+			}),
+			profile.branch(2)
+		);
+		assert_eq!(None, profile.branch(1));
+	}
+
+	#[test]
+	fn test_profile_limit() {
+		let p = Day8.parse(b"nop +0\njmp -1\n").unwrap();
+		let (profile, r) = profile(&p, 5);
+		assert_eq!(Ok(None), r);
+		assert_eq!(vec![3, 2], profile.hits);
+	}
+
+	#[test]
+	fn test_write_listing() {
+		let p = assemble(b"acc +3\nloop: acc -1\njnz loop\nhalt\nnop +0\n")
+			.expect("Failed to assemble");
+		let (profile, _) = profile(&p, 100);
+		let mut out = vec![];
+		profile.write_listing(&mut out, &p).unwrap();
+		assert_eq!(
+			"         1      0: acc +3
+         3      1: acc -1
+         3      2: jnz -1  ; taken 2/3
+         1      3: halt
+         -      4: nop +0
+",
+			String::from_utf8(out).unwrap()
+		);
+	}
+
+	#[test]
+	fn test_write_report() {
+		let p = assemble(COUNTDOWN).expect("Failed to assemble");
+		let (profile, _) = profile(&p, 100);
+		let mut out = vec![];
+		profile.write_report(&mut out, &p, 2).unwrap();
+		assert_eq!(
+			"Hot spots:
+ rank     pc  instruction          hits      %
+    1      1  acc -1                  3   37.5
+    2      2  jnz -1                  3   37.5
+Opcodes:
+  acc          4   50.0
+  jnz          3   37.5
+ halt          1   12.5
+Jumps:
+    pc  instruction         taken  not taken
+     2  jnz -1                  2          1
+",
+			String::from_utf8(out).unwrap()
+		);
+	}
+
+	proptest! {
+		#[test]
+		fn prop_hits_match_trace(p in programs()) {
+			// Profile as many steps as are recorded before a loop.
+			let (trace, _) = record(&p);
+			let (profile, _) = profile(&p, trace.steps.len());
+			let hits = trace.hits();
+			for (pc, &n) in profile.hits.iter().enumerate() {
+				prop_assert_eq!(hits.get(&pc).map_or(0, |h| h.1), n);
+			}
+		}
+	}
+}
@@ -0,0 +1,689 @@
+//! Search for edits that make a program terminate.
+//!
+//! Edits are given by the pc of the instruction in the original program.
+//! A repair is a set of edits after which the program terminates, where
+//! no smaller set of the same edits does, so that edits that do not
+//! matter are never part of a repair. A run does not terminate once a
+//! [`LoopDetector`] finds it loops, and is not taken to if it reaches the
+//! step limit.
+
+use crate::{
+	isa::Operand, snapshot::VmState, Immediate, Instr, LoopDetector,
+	VirtualMachine,
+};
+use std::{
+	collections::HashSet,
+	fmt,
+	sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+	thread,
+};
+
+/// A change to a single instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Edit {
+	/// Change `jmp` to `nop` or `nop` to `jmp`.
+	Swap(usize),
+	/// Change the argument of a jump.
+	Immediate(usize, Immediate),
+	/// Remove the instruction, moving those after it up a line.
+	Delete(usize),
+}
+
+impl Edit {
+	pub fn pc(self) -> usize {
+		match self {
+			Edit::Swap(pc) | Edit::Immediate(pc, _) | Edit::Delete(pc) => pc,
+		}
+	}
+}
+
+impl fmt::Display for Edit {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Edit::Swap(pc) => write!(f, "swap at pc {}", pc),
+			Edit::Immediate(pc, i) => write!(f, "set {} at pc {}", i, pc),
+			Edit::Delete(pc) => write!(f, "delete pc {}", pc),
+		}
+	}
+}
+
+/// Edits that make the program terminate, in order of pc, with the
+/// number of instructions it then executes and its accumulator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repair {
+	pub edits: Vec<Edit>,
+	pub steps: usize,
+	pub acc: i64,
+}
+
+impl Repair {
+	/// The edits as a unified diff without context, with a line per
+	/// instruction.
+	pub fn diff(&self, p: &[Instr]) -> String {
+		let (repaired, _) = apply(p, &self.edits);
+		let mut out = String::from("--- original\n+++ repaired\n");
+		// Hunks go in order of line, counting the deletions before each.
+		let mut edits = self.edits.clone();
+		edits.sort_by_key(|e| e.pc());
+		let mut deleted = 0;
+		for e in &edits {
+			let line = e.pc() + 1;
+			let old = &p[e.pc()];
+			match e {
+				Edit::Delete(_) => {
+					deleted += 1;
+					out += &format!(
+						"@@ -{} +{},0 @@\n-{}\n",
+						line,
+						line - deleted,
+						old
+					);
+				}
+				_ => {
+					let new = &repaired[e.pc() - deleted];
+					out += &format!(
+						"@@ -{} +{} @@\n-{}\n+{}\n",
+						line,
+						line - deleted,
+						old,
+						new
+					);
+				}
+			}
+		}
+		out
+	}
+}
+
+/// Which edits to search and how many of them to combine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Search {
+	pub max_edits: usize,
+	pub swaps: bool,
+	/// Move jumps to any instruction or the end.
+	pub immediates: bool,
+	pub deletions: bool,
+}
+
+impl Default for Search {
+	fn default() -> Self {
+		Search {
+			max_edits: 1,
+			swaps: true,
+			immediates: true,
+			deletions: true,
+		}
+	}
+}
+
+/// Change `jmp` to `nop` or `nop` to `jmp`, leaving other instructions.
+pub(crate) fn swap(instr: &mut Instr) {
+	*instr = match &*instr {
+		Instr::Jmp(i) => Instr::Nop(*i),
+		Instr::Nop(i) => Instr::Jmp(*i),
+		other => other.clone(),
+	}
+}
+
+/// Program `p` with `edits`, and the pc in `p` of each instruction.
+pub fn apply(p: &[Instr], edits: &[Edit]) -> (Vec<Instr>, Vec<usize>) {
+	let mut q = p.to_vec();
+	let mut deleted = vec![false; p.len()];
+	for &e in edits {
+		match e {
+			Edit::Swap(pc) => swap(&mut q[pc]),
+			Edit::Immediate(pc, i) => {
+				let operands = q[pc]
+					.operands()
+					.into_iter()
+					.map(|o| match o {
+						Operand::Imm(_) => Operand::Imm(i),
+						o => o,
+					})
+					.collect::<Vec<_>>();
+				q[pc] = Instr::new(q[pc].op(), &operands)
+					.expect("Operands are of the same kinds");
+			}
+			Edit::Delete(pc) => deleted[pc] = true,
+		}
+	}
+	q.into_iter()
+		.enumerate()
+		.filter(|&(pc, _)| !deleted[pc])
+		.map(|(pc, instr)| (instr, pc))
+		.unzip()
+}
+
+/// Run `p` until it terminates, returning the accumulator, or stops
+/// before a loop or on a fault, with the instructions executed and the
+/// number of steps.
+fn run(p: &[Instr]) -> (Option<i64>, Vec<bool>, usize) {
+	let mut vm = VirtualMachine::new();
+	let mut detector = LoopDetector::new(p);
+	let mut seen = vec![false; p.len()];
+	let mut steps = 0;
+	loop {
+		if detector.check(&vm, p).is_err() {
+			return (None, seen, steps);
+		}
+		if let Some(s) = seen.get_mut(vm.pc) {
+			*s = true;
+			steps += 1;
+		}
+		match vm.step(p) {
+			Ok(Some(acc)) => return (Some(acc), seen, steps),
+			Ok(None) => {}
+			Err(_) => return (None, seen, steps),
+		}
+	}
+}
+
+impl Search {
+	/// Single edits to `q` that change how it runs, given the
+	/// instructions it executes. `map` gives the pc in the original
+	/// program of each instruction.
+	fn edits(&self, q: &[Instr], map: &[usize], seen: &[bool]) -> Vec<Edit> {
+		let mut edits = vec![];
+		let mut executed = (0..q.len()).filter(|&pc| seen[pc]);
+		for pc in executed.clone() {
+			match &q[pc] {
+				Instr::Jmp(_) | Instr::Nop(_) if self.swaps => {
+					edits.push(Edit::Swap(map[pc]));
+				}
+				_ => {}
+			}
+			match &q[pc] {
+				Instr::Jmp(i) | Instr::Jz(i) | Instr::Jnz(i)
+					if self.immediates =>
+				{
+					let targets =
+						(0..=q.len()).map(|t| Immediate::between(pc, t));
+					edits.extend(
+						targets
+							.filter(|t| t != i)
+							.map(|t| Edit::Immediate(map[pc], t)),
+					);
+				}
+				_ => {}
+			}
+		}
+		// Deleting an instruction moves every later one, so it matters up
+		// to the last instruction executed.
+		if let Some(last) = executed.next_back().filter(|_| self.deletions) {
+			edits.extend((0..=last).map(|pc| Edit::Delete(map[pc])));
+		}
+		edits
+	}
+
+	/// Every repair of `p` with up to `max_edits` edits, ordered by the
+	/// number of edits and then by the instructions executed. A program
+	/// that already terminates has the single repair without edits.
+	pub fn run(&self, p: &[Instr]) -> Vec<Repair> {
+		let (acc, seen, steps) = run(p);
+		if let Some(acc) = acc {
+			return vec![Repair {
+				edits: vec![],
+				steps,
+				acc,
+			}];
+		}
+
+		let mut repairs: Vec<Repair> = vec![];
+		let mut tried = HashSet::new();
+		let mut frontier =
+			vec![(vec![], p.to_vec(), (0..p.len()).collect::<Vec<_>>(), seen)];
+		for _ in 0..self.max_edits {
+			let mut next = vec![];
+			for (edits, q, map, seen) in &frontier {
+				for e in self.edits(q, map, seen) {
+					if edits.iter().any(|d: &Edit| d.pc() == e.pc()) {
+						continue;
+					}
+					let mut set = edits.clone();
+					set.push(e);
+					set.sort_by_key(|e| e.pc());
+					if !tried.insert(set.clone()) {
+						continue;
+					}
+					let contains =
+						|r: &Repair| r.edits.iter().all(|e| set.contains(e));
+					if repairs.iter().any(contains) {
+						continue;
+					}
+					let (q, map) = apply(p, &set);
+					match run(&q) {
+						(Some(acc), _, steps) => repairs.push(Repair {
+							edits: set,
+							steps,
+							acc,
+						}),
+						(None, seen, _) => next.push((set, q, map, seen)),
+					}
+				}
+			}
+			frontier = next;
+		}
+
+		repairs.sort_by(|a, b| {
+			(a.edits.len(), a.steps, &a.edits).cmp(&(
+				b.edits.len(),
+				b.steps,
+				&b.edits,
+			))
+		});
+		repairs
+	}
+}
+
+/// A `jmp` or `nop` executed from the start, with the state the swapped
+/// instruction would continue from and the steps before it.
+struct Candidate {
+	pc: usize,
+	state: VmState,
+	steps: usize,
+}
+
+/// The repair swapping the `jmp` or `nop` at the lowest pc, trying those
+/// the program executes on `threads` threads.
+///
+/// Once a swap is found only those at lower pcs are still tried, and as
+/// every one of those is, the result does not depend on the number of
+/// threads. A program that already terminates has the repair without
+/// edits.
+///
+/// Each swap is run on from the state the program first reaches it in,
+/// as the swapped program runs the same until then. Without conditional
+/// jumps where an instruction leads does not depend on the state, so the
+/// instructions of the original run and of every swap that failed are
+/// shared between the threads and end the runs that reach them, as
+/// `find_answer_part2` does. With them each run stops once a
+/// [`LoopDetector`] finds it loops or reaches the step limit.
+pub fn lowest_swap(p: &[Instr], threads: usize) -> Option<Repair> {
+	let mut vm = VirtualMachine::new();
+	let mut detector = LoopDetector::new(p);
+	let mut dead = vec![false; p.len()];
+	let mut candidates = vec![];
+	let mut steps = 0;
+	loop {
+		if detector.check(&vm, p).is_err() {
+			break;
+		}
+		if let Some(d) = dead.get_mut(vm.pc) {
+			let next = match &p[vm.pc] {
+				Instr::Nop(i) => i.offset(vm.pc),
+				Instr::Jmp(_) => Some(vm.pc + 1),
+				_ => None,
+			};
+			// Only the first time matters, when the swapped run leaves the
+			// original one.
+			if let Some(pc) = next.filter(|_| !*d) {
+				candidates.push(Candidate {
+					pc: vm.pc,
+					state: VmState {
+						pc,
+						..vm.snapshot()
+					},
+					steps,
+				});
+			}
+			*d = true;
+			steps += 1;
+		}
+		match vm.step(p) {
+			Ok(Some(acc)) => {
+				return Some(Repair {
+					edits: vec![],
+					steps,
+					acc,
+				});
+			}
+			Ok(None) => {}
+			Err(_) => break,
+		}
+	}
+	candidates.sort_by_key(|c| c.pc);
+
+	let shared = !p
+		.iter()
+		.any(|instr| matches!(instr, Instr::Jz(_) | Instr::Jnz(_)));
+	if !shared {
+		dead = vec![false; p.len()];
+	}
+	let dead = dead.into_iter().map(AtomicBool::new).collect::<Vec<_>>();
+
+	// Index of the next candidate to try and of the first that is known
+	// to terminate.
+	let next = AtomicUsize::new(0);
+	let found = AtomicUsize::new(candidates.len());
+	let worker = || {
+		let mut q = p.to_vec();
+		let mut repairs = vec![];
+		// Instructions of the current run, and the run that last executed
+		// each one.
+		let mut visited = vec![];
+		let mut run_of = vec![usize::MAX; p.len()];
+		loop {
+			let k = next.fetch_add(1, Ordering::Relaxed);
+			if k >= found.load(Ordering::Relaxed) {
+				return repairs;
+			}
+			let c = &candidates[k];
+			swap(&mut q[c.pc]);
+			let mut vm = VirtualMachine::from(c.state.clone());
+			let mut detector = (!shared).then(|| LoopDetector::new(&q));
+			let mut steps = c.steps + 1;
+			visited.clear();
+			visited.push(c.pc);
+			run_of[c.pc] = k;
+			let acc = loop {
+				if detector.as_mut().is_some_and(|d| d.check(&vm, &q).is_err())
+				{
+					break None;
+				}
+				if let Some(r) = run_of.get_mut(vm.pc) {
+					if shared {
+						if *r == k || dead[vm.pc].load(Ordering::Relaxed) {
+							break None;
+						}
+						*r = k;
+						visited.push(vm.pc);
+					}
+					steps += 1;
+				}
+				match vm.step(&q) {
+					Ok(Some(acc)) => break Some(acc),
+					Ok(None) => {}
+					Err(_) => break None,
+				}
+			};
+			swap(&mut q[c.pc]);
+			match acc {
+				Some(acc) => {
+					found.fetch_min(k, Ordering::Relaxed);
+					repairs.push(Repair {
+						edits: vec![Edit::Swap(c.pc)],
+						steps,
+						acc,
+					});
+				}
+				// The swapped instruction itself is not dead for the
+				// others.
+				None if shared => {
+					for &pc in &visited[1..] {
+						dead[pc].store(true, Ordering::Relaxed);
+					}
+				}
+				None => {}
+			}
+		}
+	};
+	let repairs = thread::scope(|s| {
+		let workers = (0..threads.max(1))
+			.map(|_| s.spawn(worker))
+			.collect::<Vec<_>>();
+		workers
+			.into_iter()
+			.flat_map(|w| w.join().expect("Repair worker panicked"))
+			.collect::<Vec<_>>()
+	});
+	repairs.into_iter().min_by_key(|r| r.edits[0].pc())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		cfg::{Cfg, PathEnd},
+		isa::tests::instrs,
+		tests::programs,
+		Day8,
+	};
+	use aoc_common::Solution;
+	use proptest::prelude::*;
+
+	const EXAMPLE: &[u8] = b"nop +0\nacc +1\njmp +4\nacc +3\njmp -3\n\
+		acc -99\nacc +1\njmp -4\nacc +6\n";
+
+	const SWAPS: Search = Search {
+		max_edits: 1,
+		swaps: true,
+		immediates: false,
+		deletions: false,
+	};
+
+	#[test]
+	fn test_swap() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		assert_eq!(
+			vec![Repair {
+				edits: vec![Edit::Swap(7)],
+				steps: 6,
+				acc: 8
+			}],
+			SWAPS.run(&p)
+		);
+	}
+
+	#[test]
+	fn test_two_swaps() {
+		let p = Day8.parse(b"jmp +0\njmp +0\n").unwrap();
+		assert_eq!(Vec::<Repair>::new(), SWAPS.run(&p));
+		let two = Search {
+			max_edits: 2,
+			..SWAPS
+		};
+		let repairs = two.run(&p);
+		assert_eq!(1, repairs.len());
+		assert_eq!(vec![Edit::Swap(0), Edit::Swap(1)], repairs[0].edits);
+	}
+
+	#[test]
+	fn test_all_edits() {
+		let p = Day8.parse(b"acc +1\njmp -1\n").unwrap();
+		let repairs = Search::default()
+			.run(&p)
+			.into_iter()
+			.map(|r| (r.edits, r.steps, r.acc))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			vec![
+				(vec![Edit::Delete(1)], 1, 1),
+				(vec![Edit::Swap(1)], 2, 1),
+				(vec![Edit::Immediate(1, Immediate::Pos(1))], 2, 1),
+			],
+			repairs
+		);
+	}
+
+	#[test]
+	fn test_minimal() {
+		// Swapping pc 0 fixes the program, so no repair adds to it.
+		let p = Day8.parse(b"nop +3\njmp -1\njmp -2\n").unwrap();
+		let two = Search {
+			max_edits: 2,
+			..SWAPS
+		};
+		let edits =
+			two.run(&p).into_iter().map(|r| r.edits).collect::<Vec<_>>();
+		assert_eq!(
+			vec![vec![Edit::Swap(0)], vec![Edit::Swap(1), Edit::Swap(2)]],
+			edits
+		);
+	}
+
+	#[test]
+	fn test_diff() {
+		let p = Day8.parse(b"acc +1\nnop +0\njmp -2\nacc +2\n").unwrap();
+		let r = Repair {
+			edits: vec![Edit::Delete(1), Edit::Swap(2)],
+			steps: 0,
+			acc: 0,
+		};
+		assert_eq!(
+			"--- original\n+++ repaired\n\
+			 @@ -2 +1,0 @@\n-nop +0\n\
+			 @@ -3 +2 @@\n-jmp -2\n+nop -2\n",
+			r.diff(&p)
+		);
+	}
+
+	/// Lines of `p` with the hunks of a diff from `Repair::diff` applied,
+	/// checking that each new line is at the line the hunk gives.
+	fn patch(p: &[Instr], diff: &str) -> Vec<String> {
+		let mut lines =
+			p.iter().map(|i| Some(i.to_string())).collect::<Vec<_>>();
+		let mut hunks = diff.lines().skip(2).peekable();
+		let mut added = vec![];
+		while let Some(header) = hunks.next() {
+			let nums = header
+				.split(|c: char| !c.is_ascii_digit())
+				.filter(|n| !n.is_empty())
+				.map(|n| n.parse::<usize>().unwrap())
+				.collect::<Vec<_>>();
+			let old = hunks.next().expect("Missing old line");
+			assert_eq!(lines[nums[0] - 1].as_deref(), Some(&old[1..]));
+			lines[nums[0] - 1] = None;
+			if let Some(new) = hunks.next_if(|l| l.starts_with('+')) {
+				lines[nums[0] - 1] = Some(new[1..].to_string());
+				added.push((nums[1], new[1..].to_string()));
+			}
+		}
+		let lines = lines.into_iter().flatten().collect::<Vec<_>>();
+		for (line, new) in added {
+			assert_eq!(new, lines[line - 1]);
+		}
+		lines
+	}
+
+	#[test]
+	fn test_diff_of_search() {
+		let p = Day8.parse(b"jmp +0\njmp +0\n").unwrap();
+		let two = Search {
+			max_edits: 2,
+			..Search::default()
+		};
+		let repairs = two.run(&p);
+		assert!(repairs
+			.iter()
+			.any(|r| r.edits == vec![Edit::Delete(0), Edit::Swap(1)]));
+		for r in repairs {
+			let (q, _) = apply(&p, &r.edits);
+			let q = q.iter().map(ToString::to_string).collect::<Vec<_>>();
+			assert_eq!(q, patch(&p, &r.diff(&p)), "{:?}", r.edits);
+		}
+		// Out of order edits are shown in order of line.
+		let p = Day8.parse(b"acc +1\nnop +0\njmp -2\nacc +2\n").unwrap();
+		let r = Repair {
+			edits: vec![Edit::Swap(2), Edit::Delete(1)],
+			steps: 0,
+			acc: 0,
+		};
+		assert_eq!(
+			"--- original\n+++ repaired\n\
+			 @@ -2 +1,0 @@\n-nop +0\n\
+			 @@ -3 +2 @@\n-jmp -2\n+nop -2\n",
+			r.diff(&p)
+		);
+	}
+
+	#[test]
+	fn test_lowest_swap() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let expected = Repair {
+			edits: vec![Edit::Swap(7)],
+			steps: 6,
+			acc: 8,
+		};
+		assert_eq!(Some(expected), lowest_swap(&p, 4));
+		// Swapping either of the jumps terminates, with different acc.
+		let p = Day8.parse(b"nop +3\nacc +1\njmp -2\n").unwrap();
+		for threads in 1..4 {
+			let r = lowest_swap(&p, threads).expect("Missing repair");
+			assert_eq!((vec![Edit::Swap(0)], 0), (r.edits, r.acc));
+		}
+		let p = Day8.parse(b"acc +1\n").unwrap();
+		assert_eq!(Some(vec![]), lowest_swap(&p, 2).map(|r| r.edits));
+		let p = Day8.parse(b"jmp +0\njmp +0\n").unwrap();
+		assert_eq!(None, lowest_swap(&p, 2));
+	}
+
+	#[test]
+	fn test_conditional_loops() {
+		// The countdown comes back to pc 1 and still terminates.
+		let p = Day8.parse(b"acc +2\nacc -1\njnz -1\n").unwrap();
+		let done = Repair {
+			edits: vec![],
+			steps: 5,
+			acc: 0,
+		};
+		assert_eq!(vec![done.clone()], Search::default().run(&p));
+		assert_eq!(Some(done), lowest_swap(&p, 2));
+		let p = Day8.parse(b"acc +2\nacc -1\njnz -1\njmp +0\n").unwrap();
+		let fixed = Repair {
+			edits: vec![Edit::Swap(3)],
+			steps: 6,
+			acc: 0,
+		};
+		assert_eq!(vec![fixed.clone()], SWAPS.run(&p));
+		assert_eq!(Some(fixed), lowest_swap(&p, 2));
+		// Coming back to pc 0 with acc 0 loops, but not to pc 1.
+		let p = Day8.parse(b"acc +1\nacc -1\njz -2\n").unwrap();
+		assert_eq!(
+			vec![
+				vec![Edit::Immediate(2, Immediate::Pos(1))],
+				vec![Edit::Immediate(2, Immediate::Neg(1))]
+			],
+			Search {
+				immediates: true,
+				..SWAPS
+			}
+			.run(&p)
+			.into_iter()
+			.map(|r| r.edits)
+			.collect::<Vec<_>>()
+		);
+	}
+
+	/// The repair with the lowest swap, found by the serial search.
+	fn first_swap(p: &[Instr]) -> Option<Repair> {
+		SWAPS.run(p).into_iter().min_by_key(|r| r.edits.clone())
+	}
+
+	proptest! {
+		#[test]
+		fn prop_lowest_swap_matches_search(
+			p in programs(),
+			threads in 1..5_usize,
+		) {
+			prop_assert_eq!(first_swap(&p), lowest_swap(&p, threads));
+		}
+
+		#[test]
+		fn prop_lowest_swap_any_instrs(
+			p in prop::collection::vec(instrs(), 1..30),
+			threads in 1..5_usize,
+		) {
+			prop_assert_eq!(first_swap(&p), lowest_swap(&p, threads));
+		}
+
+		#[test]
+		fn prop_swaps_match_cfg(p in programs()) {
+			let cfg = Cfg::new(&p);
+			let expected = match cfg.path().end {
+				PathEnd::Terminates => vec![vec![]],
+				_ => cfg
+					.fixes()
+					.into_iter()
+					.map(|pc| vec![Edit::Swap(pc)])
+					.collect(),
+			};
+			let mut edits = SWAPS
+				.run(&p)
+				.into_iter()
+				.map(|r| r.edits)
+				.collect::<Vec<_>>();
+			edits.sort();
+			prop_assert_eq!(expected, edits);
+		}
+	}
+}
@@ -0,0 +1,217 @@
+//! Saved states of the console.
+//!
+//! A state is encoded as the magic `D8VS` and a version byte followed by
+//! pc, acc, the registers `a` to `d`, the number of values written by
+//! `out` and the values, as LEB128 varints. All but pc and the count are
+//! zigzag encoded.
+
+use crate::{
+	bytecode::{read_varint, unzigzag, write_varint, zigzag},
+	VirtualMachine,
+};
+use aoc_common::Error;
+use std::convert::TryFrom;
+
+const MAGIC: &[u8] = b"D8VS";
+const VERSION: u8 = 1;
+
+/// Everything a machine needs to continue a run, which leaves out its
+/// trace and profile.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VmState {
+	pub pc: usize,
+	pub acc: i64,
+	/// Registers `a` to `d`.
+	pub regs: [i64; 4],
+	pub output: Vec<i64>,
+}
+
+impl VmState {
+	/// The state in the snapshot file format.
+	pub fn encode(&self) -> Vec<u8> {
+		let mut out = MAGIC.to_vec();
+		out.push(VERSION);
+		write_varint(&mut out, self.pc as u64);
+		write_varint(&mut out, zigzag(self.acc));
+		for &r in &self.regs {
+			write_varint(&mut out, zigzag(r));
+		}
+		write_varint(&mut out, self.output.len() as u64);
+		for &n in &self.output {
+			write_varint(&mut out, zigzag(n));
+		}
+		out
+	}
+
+	/// State from the snapshot file format.
+	pub fn decode(data: &[u8]) -> Result<Self, Error> {
+		match data.get(..MAGIC.len() + 1) {
+			Some([m @ .., v]) if m == MAGIC && *v == VERSION => {}
+			Some([m @ .., v]) if m == MAGIC => {
+				return Err(Error::Invalid(format!(
+					"unsupported snapshot version {}",
+					v
+				)))
+			}
+			_ => return Err(Error::Invalid("not a snapshot".into())),
+		}
+		let mut pos = MAGIC.len() + 1;
+		let invalid = |what: &str| Error::Invalid(format!("snapshot {}", what));
+		let mut read = || read_varint(data, &mut pos).map_err(invalid);
+		let pc =
+			usize::try_from(read()?).map_err(|_| invalid("pc is too large"))?;
+		let acc = unzigzag(read()?);
+		let mut regs = [0; 4];
+		for r in &mut regs {
+			*r = unzigzag(read()?);
+		}
+		let len = read()?;
+		let mut output = vec![];
+		for _ in 0..len {
+			output.push(unzigzag(read()?));
+		}
+		if pos != data.len() {
+			return Err(invalid("has trailing data"));
+		}
+		Ok(VmState {
+			pc,
+			acc,
+			regs,
+			output,
+		})
+	}
+}
+
+impl From<VmState> for VirtualMachine {
+	fn from(state: VmState) -> Self {
+		let mut vm = VirtualMachine::new();
+		vm.restore(&state);
+		vm
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{asm::assemble, tests::programs, Day8};
+	use aoc_common::Solution;
+	use proptest::prelude::*;
+
+	#[test]
+	fn test_snapshot_restore() {
+		let p =
+			assemble(b"acc +2\nout acc\nmov a acc\nacc +1\nhalt\n").unwrap();
+		let mut vm = VirtualMachine::recording();
+		for _ in 0..3 {
+			vm.step(&p).expect("Failed to step");
+		}
+		let state = vm.snapshot();
+		assert_eq!(
+			VmState {
+				pc: 3,
+				acc: 2,
+				regs: [2, 0, 0, 0],
+				output: vec![2]
+			},
+			state
+		);
+		assert_eq!(Ok(3), vm.run(&p));
+		vm.restore(&state);
+		assert_eq!(state, vm.snapshot());
+		// The trace goes on from the steps before the restore.
+		assert_eq!(5, vm.trace.map_or(0, |t| t.steps.len()));
+	}
+
+	#[test]
+	fn test_fork() {
+		let p = Day8.parse(b"acc +1\njmp -1\n").unwrap();
+		let mut vm = VirtualMachine::recording();
+		vm.step(&p).unwrap();
+		let mut fork = vm.fork();
+		assert_eq!(vm.snapshot(), fork.snapshot());
+		assert_eq!(None, fork.trace);
+		fork.step(&p).unwrap();
+		fork.step(&p).unwrap();
+		assert_eq!((1, 1), (vm.pc, vm.acc));
+		assert_eq!((1, 2), (fork.pc, fork.acc));
+	}
+
+	#[test]
+	fn test_encode() {
+		let state = VmState {
+			pc: 3,
+			acc: -1,
+			regs: [1, 0, 0, 0],
+			output: vec![2],
+		};
+		assert_eq!(
+			&b"D8VS\x01\x03\x01\x02\x00\x00\x00\x01\x04"[..],
+			&state.encode()[..]
+		);
+	}
+
+	#[test]
+	fn test_decode_errors() {
+		assert!(VmState::decode(b"").is_err());
+		assert!(
+			VmState::decode(b"D8TR\x01\x00\x00\x00\x00\x00\x00\x00").is_err()
+		);
+		assert!(
+			VmState::decode(b"D8VS\x02\x00\x00\x00\x00\x00\x00\x00").is_err()
+		);
+		assert!(VmState::decode(b"D8VS\x01\x00\x00\x00\x00\x00\x00").is_err());
+		assert!(
+			VmState::decode(b"D8VS\x01\x00\x00\x00\x00\x00\x00\x01").is_err()
+		);
+		assert!(VmState::decode(b"D8VS\x01\x00\x00\x00\x00\x00\x00\x00\x00")
+			.is_err());
+		assert_eq!(
+			Ok(VmState::default()),
+			VmState::decode(b"D8VS\x01\x00\x00\x00\x00\x00\x00\x00")
+				.map_err(|e| e.to_string())
+		);
+	}
+
+	fn states() -> impl Strategy<Value = VmState> {
+		(
+			any::<usize>(),
+			any::<i64>(),
+			any::<[i64; 4]>(),
+			prop::collection::vec(any::<i64>(), 0..10),
+		)
+			.prop_map(|(pc, acc, regs, output)| VmState {
+				pc,
+				acc,
+				regs,
+				output,
+			})
+	}
+
+	proptest! {
+		#[test]
+		fn prop_encode_round_trip(state in states()) {
+			let r = VmState::decode(&state.encode()).expect("Failed to decode");
+			prop_assert_eq!(state, r);
+		}
+
+		#[test]
+		fn prop_resume_matches_run(p in programs(), n in 0..20_usize) {
+			// A machine resumed from a saved state runs on like the one
+			// that was saved.
+			let mut vm = VirtualMachine::new();
+			for _ in 0..n {
+				prop_assume!(vm.step(&p) == Ok(None));
+			}
+			let state = VmState::decode(&vm.snapshot().encode()).unwrap();
+			let mut resumed = VirtualMachine::from(state);
+			for _ in 0..20 {
+				let r = vm.step(&p);
+				prop_assert_eq!(&r, &resumed.step(&p));
+				prop_assert_eq!(vm.snapshot(), resumed.snapshot());
+				if r != Ok(None) {
+					break;
+				}
+			}
+		}
+	}
+}
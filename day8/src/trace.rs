@@ -0,0 +1,349 @@
+//! Execution traces of the handheld console.
+//!
+//! A trace is encoded as the magic `D8TR` and a version byte followed by
+//! one record per step: the instruction as in the binary program format,
+//! then the distance of pc from the previous step's next pc and the
+//! distance of acc from the previous step's acc after, as LEB128 varints.
+//! Distances are zigzag encoded and are zero for consecutive steps, so
+//! that most steps take four bytes. The acc after and next pc
+//! of each step are recomputed when decoding, except that instructions
+//! reading registers also store the change to acc, as the other
+//! registers are not part of the trace.
+
+use crate::{
+	bytecode::{
+		read_instr, read_varint, unzigzag, write_instr, write_varint, zigzag,
+	},
+	isa::Kind,
+	Instr, VirtualMachine, VmFault,
+};
+use aoc_common::Error;
+use std::{collections::BTreeMap, fmt};
+
+const MAGIC: &[u8] = b"D8TR";
+const VERSION: u8 = 1;
+
+/// A single executed instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+	pub pc: usize,
+	pub instr: Instr,
+	/// Accumulator before the instruction.
+	pub acc: i64,
+	/// Accumulator after the instruction.
+	pub acc_after: i64,
+	/// Next pc, the jump target for `jmp`.
+	pub next: usize,
+}
+
+impl fmt::Display for Step {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{:>5}: {:<10} acc {} -> {}  next {}",
+			self.pc,
+			self.instr.to_string(),
+			self.acc,
+			self.acc_after,
+			self.next
+		)
+	}
+}
+
+/// Steps in the order they were executed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Trace {
+	pub steps: Vec<Step>,
+}
+
+impl Trace {
+	/// The trace in the compact file format.
+	pub fn encode(&self) -> Vec<u8> {
+		let mut out = MAGIC.to_vec();
+		out.push(VERSION);
+		let (mut pc, mut acc) = (0_usize, 0_i64);
+		for s in &self.steps {
+			write_instr(&mut out, &s.instr);
+			write_varint(&mut out, zigzag(s.pc.wrapping_sub(pc) as i64));
+			write_varint(&mut out, zigzag(s.acc.wrapping_sub(acc)));
+			if s.instr.op().operands().contains(&Kind::Reg) {
+				write_varint(&mut out, zigzag(s.acc_after.wrapping_sub(s.acc)));
+			}
+			pc = s.next;
+			acc = s.acc_after;
+		}
+		out
+	}
+
+	/// Trace from the compact file format.
+	pub fn decode(data: &[u8]) -> Result<Self, Error> {
+		match data.get(..MAGIC.len() + 1) {
+			Some([m @ .., v]) if m == MAGIC && *v == VERSION => {}
+			Some([m @ .., v]) if m == MAGIC => {
+				return Err(Error::Invalid(format!(
+					"unsupported trace version {}",
+					v
+				)))
+			}
+			_ => return Err(Error::Invalid("not a trace".into())),
+		}
+		let mut pos = MAGIC.len() + 1;
+		let mut vm = VirtualMachine::new();
+		let mut steps = vec![];
+		while pos < data.len() {
+			let invalid = |what: &str| {
+				Error::Invalid(format!(
+					"{} in trace step {}",
+					what,
+					steps.len()
+				))
+			};
+			let instr = read_instr(data, &mut pos).map_err(invalid)?;
+			let pc = unzigzag(read_varint(data, &mut pos).map_err(invalid)?);
+			let acc = unzigzag(read_varint(data, &mut pos).map_err(invalid)?);
+			vm.pc = vm.pc.wrapping_add(pc as usize);
+			vm.acc = vm.acc.wrapping_add(acc);
+			let (pc, acc) = (vm.pc, vm.acc);
+			// The other registers are unknown, and only the next pc is taken
+			// from instructions that read them.
+			vm.regs = [0; 4];
+			vm.output.clear();
+			vm.execute(&instr).map_err(|e| {
+				Error::Invalid(format!("trace step {}: {}", steps.len(), e))
+			})?;
+			if instr.op().operands().contains(&Kind::Reg) {
+				let change =
+					unzigzag(read_varint(data, &mut pos).map_err(invalid)?);
+				vm.acc = acc.wrapping_add(change);
+			}
+			steps.push(Step {
+				pc,
+				instr,
+				acc,
+				acc_after: vm.acc,
+				next: vm.pc,
+			});
+		}
+		Ok(Trace { steps })
+	}
+
+	/// Execute the steps of the trace on `p`, failing on the first step
+	/// that does not match the program. Returns the machine after the
+	/// last step.
+	pub fn replay(&self, p: &[Instr]) -> Result<VirtualMachine, Error> {
+		let mut vm = VirtualMachine::new();
+		for (n, s) in self.steps.iter().enumerate() {
+			let mismatch = |what| {
+				Error::Invalid(format!(
+					"trace step {} at pc {}: {}",
+					n, s.pc, what
+				))
+			};
+			if p.get(s.pc) != Some(&s.instr) {
+				return Err(mismatch("instruction differs from program"));
+			}
+			vm.pc = s.pc;
+			vm.acc = s.acc;
+			vm.step(p)?;
+			if (vm.pc, vm.acc) != (s.next, s.acc_after) {
+				return Err(mismatch("result differs from program"));
+			}
+		}
+		Ok(vm)
+	}
+
+	/// Index of the first step that differs from `other`, or `None` if the
+	/// traces are the same.
+	pub fn diff(&self, other: &Trace) -> Option<usize> {
+		let n = self
+			.steps
+			.iter()
+			.zip(&other.steps)
+			.position(|(a, b)| a != b);
+		match n {
+			Some(n) => Some(n),
+			None if self.steps.len() != other.steps.len() => {
+				Some(self.steps.len().min(other.steps.len()))
+			}
+			None => None,
+		}
+	}
+
+	/// Number of times each executed instruction was hit, by pc.
+	pub fn hits(&self) -> BTreeMap<usize, (&Instr, usize)> {
+		let mut hits = BTreeMap::new();
+		for s in &self.steps {
+			hits.entry(s.pc).or_insert((&s.instr, 0)).1 += 1;
+		}
+		hits
+	}
+}
+
+/// Run `p` from the start until it terminates, faults or is about to
+/// execute an instruction a second time, recording a trace.
+///
+/// Returns the trace with the accumulator on termination or the fault,
+/// `InfiniteLoopDetected` if stopped before a loop.
+pub fn record(p: &[Instr]) -> (Trace, Result<i64, VmFault>) {
+	let mut vm = VirtualMachine::recording();
+	let r = vm.run(p);
+	(vm.trace.unwrap_or_default(), r)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{isa::tests::instrs, Day8, Immediate};
+	use aoc_common::Solution;
+	use proptest::prelude::*;
+
+	const EXAMPLE: &[u8] = b"nop +0\nacc +1\njmp +4\nacc +3\njmp -3\n\
+		acc -99\nacc +1\njmp -4\nacc +6\n";
+
+	fn record_steps(p: &[Instr], steps: usize) -> Trace {
+		let mut vm = VirtualMachine::recording();
+		for _ in 0..steps {
+			if vm.step(p).expect("Failed to step").is_some() {
+				break;
+			}
+		}
+		vm.trace.expect("Missing trace")
+	}
+
+	#[test]
+	fn test_record() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let trace = record_steps(&p, 3);
+		assert_eq!(
+			vec![
+				"    0: nop +0     acc 0 -> 0  next 1",
+				"    1: acc +1     acc 0 -> 1  next 2",
+				"    2: jmp +4     acc 1 -> 1  next 6",
+			],
+			trace
+				.steps
+				.iter()
+				.map(ToString::to_string)
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn test_record_stops_at_loop() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let (trace, r) = record(&p);
+		assert_eq!(
+			Err(VmFault::InfiniteLoopDetected(
+				1,
+				Instr::Acc(Immediate::Pos(1))
+			)),
+			r
+		);
+		assert_eq!(record_steps(&p, 7), trace);
+		let mut fixed = p.clone();
+		fixed[7] = Instr::Nop(Immediate::Neg(4));
+		let (trace, r) = record(&fixed);
+		assert_eq!(Ok(8), r);
+		assert_eq!(Some(4), trace.diff(&record_steps(&p, 7)));
+	}
+
+	#[test]
+	fn test_encode() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let data = record_steps(&p, 3).encode();
+		assert_eq!(
+			&b"D8TR\x01\x04\x00\x00\x00\x00\x01\x00\x00\x02\x04\x00\x00"[..],
+			&data[..]
+		);
+	}
+
+	#[test]
+	fn test_decode_errors() {
+		assert!(Trace::decode(b"").is_err());
+		assert!(Trace::decode(b"D8TX\x01").is_err());
+		assert!(Trace::decode(b"D8TR\x02").is_err());
+		assert!(Trace::decode(b"D8TR\x01\x7e\x00\x00\x00").is_err());
+		assert!(Trace::decode(b"D8TR\x01\x00\x80").is_err());
+		let empty = Trace::decode(b"D8TR\x01").expect("Failed to decode");
+		assert_eq!(Trace::default(), empty);
+	}
+
+	#[test]
+	fn test_replay_mismatch() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let trace = record_steps(&p, 5);
+		assert_eq!(
+			VirtualMachine {
+				pc: 3,
+				acc: 2,
+				..VirtualMachine::new()
+			},
+			trace.replay(&p).expect("Failed to replay")
+		);
+		let mut changed = p.clone();
+		changed[6] = Instr::Acc(Immediate::Pos(4));
+		assert!(trace.replay(&changed).is_err());
+	}
+
+	#[test]
+	fn test_diff() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let a = record_steps(&p, 5);
+		assert_eq!(None, a.diff(&a));
+		assert_eq!(Some(3), a.diff(&record_steps(&p, 3)));
+		let mut changed = p.clone();
+		changed[2] = Instr::Nop(Immediate::Pos(4));
+		assert_eq!(Some(2), a.diff(&record_steps(&changed, 5)));
+	}
+
+	#[test]
+	fn test_hits() {
+		let p = Day8.parse(EXAMPLE).expect("Failed to parse input");
+		let trace = record_steps(&p, 9);
+		let hits = trace
+			.hits()
+			.into_iter()
+			.map(|(pc, (_, n))| (pc, n))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			vec![(0, 1), (1, 2), (2, 2), (3, 1), (4, 1), (6, 1), (7, 1)],
+			hits
+		);
+	}
+
+	/// Steps of arbitrary instructions that execute without a fault.
+	/// Steps need not follow on from each other, as when the machine is
+	/// reset between runs.
+	fn steps() -> impl Strategy<Value = Vec<Step>> {
+		let state = (any::<usize>(), any::<i64>(), any::<[i64; 4]>());
+		prop::collection::vec((instrs(), state), 0..40).prop_map(|v| {
+			v.into_iter()
+				.filter_map(|(instr, (pc, acc, regs))| {
+					let mut vm = VirtualMachine {
+						pc,
+						acc,
+						regs,
+						..VirtualMachine::new()
+					};
+					vm.execute(&instr).ok()?;
+					Some(Step {
+						pc,
+						instr,
+						acc,
+						acc_after: vm.acc,
+						next: vm.pc,
+					})
+				})
+				.collect()
+		})
+	}
+
+	proptest! {
+		#[test]
+		fn prop_encode_round_trip(steps in steps()) {
+			let trace = Trace { steps };
+			let r = Trace::decode(&trace.encode()).expect("Failed to decode");
+			prop_assert_eq!(trace, r);
+		}
+	}
+}
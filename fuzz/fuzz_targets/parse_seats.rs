@@ -0,0 +1,10 @@
+#![no_main]
+use aoc_common::{report, Mode};
+use day5::Day5;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	for &mode in &[Mode::Strict, Mode::Lenient] {
+		let _ = report::measure(&Day5, data, mode);
+	}
+});